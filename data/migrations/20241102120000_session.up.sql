@@ -0,0 +1,22 @@
+CREATE TABLE
+  sessions (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    last_seen_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    expires_at TIMESTAMPTZ NOT NULL,
+    revoked_at TIMESTAMPTZ
+  );
+
+CREATE INDEX sessions_user_id_idx ON sessions (user_id);
+
+CREATE TABLE
+  session_refresh_tokens (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES sessions (id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    used_at TIMESTAMPTZ
+  );
+
+CREATE INDEX session_refresh_tokens_session_id_idx ON session_refresh_tokens (session_id);
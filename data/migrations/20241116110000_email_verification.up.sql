@@ -0,0 +1,17 @@
+ALTER TABLE users
+ADD COLUMN email_verified_at TIMESTAMPTZ;
+
+CREATE TABLE
+  user_tokens (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    purpose VARCHAR(32) NOT NULL,
+    -- address the token was sent to
+    email VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    expires_at TIMESTAMPTZ NOT NULL,
+    consumed_at TIMESTAMPTZ,
+    CONSTRAINT user_tokens_purpose_check CHECK (purpose IN ('email_verification'))
+  );
+
+CREATE INDEX user_tokens_user_id_purpose_idx ON user_tokens (user_id, purpose);
@@ -0,0 +1,5 @@
+ALTER TABLE user_tokens
+DROP CONSTRAINT user_tokens_purpose_check;
+
+ALTER TABLE user_tokens
+ADD CONSTRAINT user_tokens_purpose_check CHECK (purpose IN ('email_verification', 'password_reset'));
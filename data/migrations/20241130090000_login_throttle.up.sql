@@ -0,0 +1,12 @@
+-- failed logins per account (keyed by the email tried, so unknown emails
+-- behave like existing ones) and per source (e.g. client IP)
+CREATE TABLE
+  login_throttle (
+    scope VARCHAR(16) NOT NULL,
+    subject VARCHAR(255) NOT NULL,
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    locked_until TIMESTAMPTZ,
+    PRIMARY KEY (scope, subject),
+    CONSTRAINT login_throttle_scope_check CHECK (scope IN ('account', 'source'))
+  );
@@ -0,0 +1,5 @@
+-- fails if addresses differing only in case already exist, merge those first
+ALTER TABLE users
+DROP CONSTRAINT users_email_key;
+
+CREATE UNIQUE INDEX users_email_lower_key ON users (lower(email));
@@ -0,0 +1,19 @@
+CREATE TABLE
+  user_totp (
+    user_id UUID PRIMARY KEY REFERENCES users (id) ON DELETE CASCADE,
+    -- AES-256-GCM nonce followed by the encrypted secret
+    secret BYTEA NOT NULL,
+    -- time step of the last accepted code, older and equal steps are replays
+    last_used_step BIGINT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    -- unconfirmed enrollments don't ask for a second factor yet
+    confirmed_at TIMESTAMPTZ
+  );
+
+CREATE TABLE
+  user_recovery_codes (
+    user_id UUID NOT NULL REFERENCES user_totp (user_id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMPTZ,
+    PRIMARY KEY (user_id, code_hash)
+  );
@@ -0,0 +1,78 @@
+CREATE TABLE
+  permissions (
+    name VARCHAR(64) PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT ''
+  );
+
+CREATE TABLE
+  roles (
+    name VARCHAR(64) PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT '',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW ()
+  );
+
+CREATE TABLE
+  role_permissions (
+    role VARCHAR(64) NOT NULL REFERENCES roles (name) ON DELETE CASCADE,
+    permission VARCHAR(64) NOT NULL REFERENCES permissions (name) ON DELETE CASCADE,
+    PRIMARY KEY (role, permission)
+  );
+
+CREATE TABLE
+  user_roles (
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    role VARCHAR(64) NOT NULL REFERENCES roles (name) ON DELETE CASCADE,
+    granted_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    PRIMARY KEY (user_id, role)
+  );
+
+CREATE INDEX user_roles_role_idx ON user_roles (role);
+
+INSERT INTO
+  permissions (name, description)
+VALUES
+  ('users:read', 'See user accounts'),
+  ('users:write', 'Change user accounts, e.g. unlock them or reset passwords'),
+  ('users:delete', 'Delete user accounts'),
+  ('roles:assign', 'Assign and revoke roles'),
+  ('items:moderate', 'Hide or remove items of other users'),
+  ('audit:read', 'Read the audit trail');
+
+INSERT INTO
+  roles (name, description)
+VALUES
+  ('admin', 'Everything'),
+  ('moderator', 'Keeps content in check'),
+  ('support', 'Helps users with their accounts'),
+  ('auditor', 'Read-only access for reviews');
+
+INSERT INTO
+  role_permissions (role, permission)
+SELECT
+  'admin',
+  name
+FROM
+  permissions;
+
+INSERT INTO
+  role_permissions (role, permission)
+VALUES
+  ('moderator', 'users:read'),
+  ('moderator', 'items:moderate'),
+  ('support', 'users:read'),
+  ('support', 'users:write'),
+  ('auditor', 'users:read'),
+  ('auditor', 'audit:read');
+
+INSERT INTO
+  user_roles (user_id, role)
+SELECT
+  id,
+  'admin'
+FROM
+  users
+WHERE
+  is_admin;
+
+ALTER TABLE users
+DROP COLUMN is_admin;
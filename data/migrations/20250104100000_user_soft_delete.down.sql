@@ -0,0 +1,9 @@
+-- deleted users would come back otherwise
+DELETE FROM users
+WHERE
+  deleted_at IS NOT NULL;
+
+DROP INDEX users_deleted_at_idx;
+
+ALTER TABLE users
+DROP COLUMN deleted_at;
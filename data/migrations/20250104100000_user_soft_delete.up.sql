@@ -0,0 +1,7 @@
+ALTER TABLE users
+ADD COLUMN deleted_at TIMESTAMPTZ;
+
+-- the purge only looks at deleted users
+CREATE INDEX users_deleted_at_idx ON users (deleted_at)
+WHERE
+  deleted_at IS NOT NULL;
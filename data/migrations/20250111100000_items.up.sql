@@ -0,0 +1,25 @@
+CREATE TABLE
+  items (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    -- repository::item::canonical form of url, a page is saved once per user
+    canonical_url TEXT NOT NULL,
+    title TEXT,
+    excerpt TEXT,
+    status VARCHAR(16) NOT NULL DEFAULT 'unread',
+    favorite BOOLEAN NOT NULL DEFAULT FALSE,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    read_at TIMESTAMPTZ,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    CONSTRAINT items_status_check CHECK (status IN ('unread', 'archived'))
+  );
+
+-- keyset pagination of a user's list
+CREATE INDEX items_user_id_added_at_id_idx ON items (user_id, added_at, id);
+
+CREATE UNIQUE INDEX items_user_id_canonical_url_key ON items (user_id, canonical_url);
+
+CREATE TRIGGER items_set_updated_at BEFORE
+UPDATE ON items FOR EACH ROW
+EXECUTE FUNCTION set_updated_at ();
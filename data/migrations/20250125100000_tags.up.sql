@@ -0,0 +1,18 @@
+CREATE TABLE
+  tags (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- normalised, see model::tag::normalize_tag_name
+    name VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    CONSTRAINT tags_user_id_name_key UNIQUE (user_id, name)
+  );
+
+CREATE TABLE
+  item_tags (
+    item_id UUID NOT NULL REFERENCES items (id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tags (id) ON DELETE CASCADE,
+    PRIMARY KEY (item_id, tag_id)
+  );
+
+CREATE INDEX item_tags_tag_id_idx ON item_tags (tag_id);
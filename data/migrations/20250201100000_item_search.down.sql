@@ -0,0 +1,12 @@
+DROP INDEX items_user_id_domain_idx;
+
+ALTER TABLE items
+DROP COLUMN domain;
+
+DROP INDEX items_search_vector_idx;
+
+ALTER TABLE items
+DROP COLUMN search_vector;
+
+ALTER TABLE items
+DROP COLUMN content;
@@ -0,0 +1,16 @@
+ALTER TABLE items
+ADD COLUMN content TEXT;
+
+-- title ranks above excerpt above content, see ts_rank weights
+ALTER TABLE items
+ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+  setweight(to_tsvector('english'::regconfig, COALESCE(title, '')), 'A') || setweight(to_tsvector('english'::regconfig, COALESCE(excerpt, '')), 'B') || setweight(to_tsvector('english'::regconfig, COALESCE(content, '')), 'C')
+) STORED;
+
+CREATE INDEX items_search_vector_idx ON items USING GIN (search_vector);
+
+-- canonical urls are always https, see repository::item::canonical
+ALTER TABLE items
+ADD COLUMN domain TEXT GENERATED ALWAYS AS (substring(canonical_url FROM '^https://([^/?#:]+)')) STORED;
+
+CREATE INDEX items_user_id_domain_idx ON items (user_id, domain);
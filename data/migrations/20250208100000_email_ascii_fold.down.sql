@@ -0,0 +1,3 @@
+DROP INDEX users_email_lower_key;
+
+CREATE UNIQUE INDEX users_email_lower_key ON users (lower(email));
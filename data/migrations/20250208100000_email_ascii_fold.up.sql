@@ -0,0 +1,5 @@
+-- lower() folds non-ASCII letters only in some database locales, the "C" collation
+-- keeps it to A-Z everywhere, same as Email::lookup_key
+DROP INDEX users_email_lower_key;
+
+CREATE UNIQUE INDEX users_email_lower_key ON users (lower(email COLLATE "C"));
@@ -0,0 +1,5 @@
+-- digests can't be turned back into emails, the counters start over
+DELETE FROM login_throttle;
+
+ALTER TABLE login_throttle
+ALTER COLUMN subject TYPE VARCHAR(255);
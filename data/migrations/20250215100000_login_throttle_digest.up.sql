@@ -0,0 +1,15 @@
+-- subjects are stored as hash_token digests (unpadded URL-safe base64 of SHA-256)
+-- so emails and sources of any length can be throttled
+UPDATE login_throttle
+SET
+  subject = rtrim(
+    translate(
+      encode(sha256(convert_to(subject, 'UTF8')), 'base64'),
+      '+/',
+      '-_'
+    ),
+    '='
+  );
+
+ALTER TABLE login_throttle
+ALTER COLUMN subject TYPE VARCHAR(43);
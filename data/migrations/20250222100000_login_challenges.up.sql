@@ -0,0 +1,11 @@
+CREATE TABLE
+  login_challenges (
+    token_hash VARCHAR(64) PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    -- wrong codes given for it, the challenge is dropped after too many
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW (),
+    expires_at TIMESTAMPTZ NOT NULL
+  );
+
+CREATE INDEX login_challenges_user_id_idx ON login_challenges (user_id);
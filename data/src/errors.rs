@@ -1,3 +1,7 @@
+use std::fmt::Display;
+
+use crate::Error;
+
 pub const NOT_NULL_VIOLATION: &str = "23502";
 pub const FOREIGN_KEY_VIOLATION: &str = "23503";
 pub const UNIQUE_VIOLATION: &str = "23505";
@@ -39,3 +43,35 @@ impl ErrorExt for sqlx::Error {
         }
     }
 }
+
+/// Converts a failed query into a domain [`Error`] using [`ErrorExt::kind_ext`].
+///
+/// `entity` identifies what the query was about (id, email, ...) and is reported in
+/// `NotFound`, `AlreadyExists` and `CannotDeleteReferenced`. Errors that don't map to any
+/// domain variant are wrapped with `fallback`, e.g. `Error::ReadError`.
+pub(crate) fn map_sqlx_error(
+    err: sqlx::Error,
+    entity: impl Display,
+    fallback: fn(sqlx::Error) -> Error,
+) -> Error {
+    if let sqlx::Error::RowNotFound = err {
+        return Error::NotFound(entity.to_string());
+    }
+
+    match err.kind_ext() {
+        ErrorKindExt::UniqueViolation => Error::AlreadyExists(entity.to_string()),
+        ErrorKindExt::ForeignKeyViolation => Error::CannotDeleteReferenced(entity.to_string()),
+        ErrorKindExt::NotNullViolation | ErrorKindExt::CheckViolation => {
+            Error::DataIntegrity(database_message(&err))
+        }
+        ErrorKindExt::InsufficientPrivilege => Error::InsufficientPrivilege(database_message(&err)),
+        ErrorKindExt::Other => fallback(err),
+    }
+}
+
+fn database_message(err: &sqlx::Error) -> String {
+    match err {
+        sqlx::Error::Database(db_err) => db_err.message().to_string(),
+        _ => err.to_string(),
+    }
+}
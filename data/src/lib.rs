@@ -1,8 +1,11 @@
 pub mod errors;
 pub mod model;
 pub mod repository;
-mod settings;
+pub mod settings;
+mod token;
 
+use chrono::{DateTime, Utc};
+use model::user::PasswordViolation;
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
     ConnectOptions, PgPool,
@@ -25,16 +28,47 @@ pub enum Error {
     NotFound(String),
     #[error("Entity already exists: {0}")]
     AlreadyExists(String),
+    #[error("Entity \"{0}\" was modified concurrently")]
+    Conflict(String),
     #[error("Cannot delete entity \"{0}\" with active references to it")]
     CannotDeleteReferenced(String),
+    #[error("Insufficient privilege: {0}")]
+    InsufficientPrivilege(String),
     #[error("Invalid argument: {0}")]
     InvalidArgument(String),
+    #[error("Validation failed: {0}")]
+    Validation(String),
     #[error("Can't save password")]
     Hash,
+    #[error("Password does not meet the policy: {}", format_violations(.0))]
+    WeakPassword(Vec<PasswordViolation>),
+    #[error("Password does not match")]
+    PasswordMismatch,
+    #[error("Too many failed login attempts, locked until {0}")]
+    LockedOut(DateTime<Utc>),
+    #[error("Email address is not verified")]
+    EmailNotVerified,
+    /// Carries the challenge that finishes the login, it isn't part of the message.
+    #[error("Second factor required")]
+    SecondFactorRequired(String),
+    #[error("Invalid or already used code")]
+    InvalidCode,
+    #[error("Invalid or expired token")]
+    InvalidToken,
+    #[error("Refresh token was already used, session revoked")]
+    TokenReuse,
     #[error("Failed to run task")]
     SpawnTask,
 }
 
+fn format_violations(violations: &[PasswordViolation]) -> String {
+    violations
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub async fn connect(settings: &settings::PostgresSettings) -> Result<PgPool> {
@@ -1,3 +1,10 @@
+pub mod api_key;
+pub mod email;
+pub mod item;
+pub mod page;
+pub mod role;
+pub mod session;
+pub mod tag;
 pub mod user;
 
 // pub type TimestampTz = sqlx::types::chrono::DateTime<sqlx::types::chrono::Utc>;
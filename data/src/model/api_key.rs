@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Error;
+
+use super::user::User;
+
+/// Scopes a key can be limited to.
+pub mod scope {
+    pub const ITEMS_READ: &str = "items:read";
+    pub const ITEMS_WRITE: &str = "items:write";
+
+    pub const ALL: &[&str] = &[ITEMS_READ, ITEMS_WRITE];
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    /// Public part of the key, safe to show to identify it.
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewApiKey {
+    pub name: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl NewApiKey {
+    /// Checks the key can be created: a name, known [`scope`]s listed once and an expiry
+    /// still to come. Anything else gives [`Error::Validation`].
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.name.trim().is_empty() {
+            return Err(Error::Validation("api key name is empty".to_string()));
+        }
+
+        for (index, requested) in self.scopes.iter().enumerate() {
+            if !scope::ALL.contains(&requested.as_str()) {
+                return Err(Error::Validation(format!("unknown scope: {requested}")));
+            }
+            if self.scopes[..index].contains(requested) {
+                return Err(Error::Validation(format!("duplicate scope: {requested}")));
+            }
+        }
+
+        if let Some(expires_at) = self.expires_at {
+            if expires_at <= Utc::now() {
+                return Err(Error::Validation(format!(
+                    "api key expiry is in the past: {expires_at}"
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Api key together with the full plain key, it's not stored and can't be read again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedApiKey {
+    pub api_key: ApiKey,
+    pub key: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeyOwner {
+    pub api_key: ApiKey,
+    pub user: User,
+}
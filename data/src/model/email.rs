@@ -0,0 +1,95 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+/// Longest address that fits the `users.email` column.
+pub const MAX_EMAIL_LENGTH: usize = 255;
+
+/// Validated email address.
+///
+/// Surrounding whitespace is dropped and the domain is lowercased (ASCII letters only),
+/// the local part is kept as typed. Addresses are still compared case-insensitively as a whole, see
+/// [`Email::lookup_key`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Email(String);
+
+impl Email {
+    pub fn parse(email: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidArgument(format!("invalid email: {email}"));
+
+        let trimmed = email.trim();
+
+        if trimmed.len() > MAX_EMAIL_LENGTH
+            || trimmed.chars().any(|c| c.is_whitespace() || c.is_control())
+        {
+            return Err(invalid());
+        }
+
+        let (local, domain) = trimmed.rsplit_once('@').ok_or_else(invalid)?;
+
+        if local.is_empty() || local.len() > 64 {
+            return Err(invalid());
+        }
+
+        let labels: Vec<&str> = domain.split('.').collect();
+        let valid_label = |label: &&str| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_alphanumeric() || c == '-')
+        };
+
+        if labels.len() < 2 || !labels.iter().all(valid_label) {
+            return Err(invalid());
+        }
+
+        Ok(Self(format!("{local}@{}", domain.to_ascii_lowercase())))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Form the uniqueness and lookups go by, matches `lower(email COLLATE "C")` in
+    /// Postgres: only ASCII letters are folded, `É` and `é` stay different.
+    pub fn lookup_key(email: &str) -> String {
+        email.trim().to_ascii_lowercase()
+    }
+}
+
+impl fmt::Display for Email {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Email {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Email {
+    type Err = Error;
+
+    fn from_str(email: &str) -> Result<Self, Self::Err> {
+        Self::parse(email)
+    }
+}
+
+impl TryFrom<String> for Email {
+    type Error = Error;
+
+    fn try_from(email: String) -> Result<Self, Self::Error> {
+        Self::parse(&email)
+    }
+}
+
+impl From<Email> for String {
+    fn from(email: Email) -> Self {
+        email.0
+    }
+}
@@ -0,0 +1,294 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Error;
+
+use super::{
+    page::{decode_cursor, encode_cursor},
+    tag::normalize_tag_name,
+};
+
+/// Saved page of a user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Item {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub url: String,
+    /// Identifies the page among the user's items, see
+    /// [`canonicalize`](crate::repository::item::canonical::canonicalize).
+    pub canonical_url: String,
+    pub title: Option<String>,
+    pub excerpt: Option<String>,
+    /// Text extracted from the page, searched but not shown in listings.
+    pub content: Option<String>,
+    pub status: ItemStatus,
+    pub favorite: bool,
+    pub added_at: DateTime<Utc>,
+    /// When the item was archived, cleared when it goes back to unread.
+    pub read_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemStatus {
+    #[default]
+    Unread,
+    Archived,
+}
+
+impl ItemStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Unread => "unread",
+            Self::Archived => "archived",
+        }
+    }
+}
+
+impl fmt::Display for ItemStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ItemStatus {
+    type Err = Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "unread" => Ok(Self::Unread),
+            "archived" => Ok(Self::Archived),
+            _ => Err(Error::InvalidArgument(format!(
+                "unknown item status: {value}"
+            ))),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewItem {
+    /// Absolute `http` or `https` URL.
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub excerpt: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+}
+
+/// Item moved over from another service, e.g. read from a Pocket export by
+/// [`pocket::parse_export`](crate::repository::item::pocket::parse_export).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportItem {
+    /// Checked on import, invalid URLs are reported as failed.
+    pub url: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub status: ItemStatus,
+    /// Defaults to the time of the import.
+    #[serde(default)]
+    pub added_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of an import, every entry ends up in exactly one of the lists.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub created: Vec<Item>,
+    /// URLs of entries the user had saved already, by canonical URL, or that appeared
+    /// earlier in the import.
+    pub skipped: Vec<String>,
+    pub failed: Vec<ImportFailure>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportFailure {
+    pub url: String,
+    pub reason: String,
+}
+
+/// Fields left `None` stay as they are.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct UpdateItem {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub excerpt: Option<String>,
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Archiving sets `read_at`, going back to unread clears it.
+    #[serde(default)]
+    pub status: Option<ItemStatus>,
+    #[serde(default)]
+    pub favorite: Option<bool>,
+    /// `updated_at` of the item as last read by the caller, the update fails with
+    /// [`crate::Error::Conflict`] if the item was changed since then.
+    #[serde(default)]
+    pub expected_updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ItemFilter {
+    pub status: Option<ItemStatus>,
+    pub favorite: Option<bool>,
+    /// Items carrying this tag.
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Items on this host or its subdomains, `example.com` matches `www.example.com`.
+    #[serde(default)]
+    pub domain: Option<String>,
+}
+
+impl ItemFilter {
+    /// Normalises `tag` like tag names and `domain` like hosts of canonical URLs.
+    pub fn normalized(&self) -> Result<Self, Error> {
+        let tag = self.tag.as_deref().map(normalize_tag_name).transpose()?;
+
+        let domain = match self.domain.as_deref() {
+            Some(domain) => {
+                let normalized = domain.trim().trim_end_matches('.').to_lowercase();
+                if normalized.is_empty() || normalized.contains(['/', ':', ' ']) {
+                    return Err(Error::InvalidArgument(format!("invalid domain: {domain}")));
+                }
+                Some(normalized)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            status: self.status,
+            favorite: self.favorite,
+            tag,
+            domain,
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ItemSort {
+    /// Newest first, like a reading list.
+    #[default]
+    AddedAtDesc,
+    AddedAtAsc,
+}
+
+impl ItemSort {
+    pub fn is_desc(&self) -> bool {
+        matches!(self, Self::AddedAtDesc)
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ListItems {
+    #[serde(default)]
+    pub filter: ItemFilter,
+    #[serde(default)]
+    pub sort: ItemSort,
+    /// Page size, see [`page_size`](super::page::page_size).
+    pub limit: Option<u32>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub with_total: bool,
+}
+
+/// Position after which the next page starts, tie-broken by id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ItemCursor {
+    pub added_at: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl ItemCursor {
+    const KIND: &'static str = "added_at";
+
+    pub fn after(item: &Item) -> Self {
+        Self {
+            added_at: item.added_at,
+            id: item.id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        encode_cursor(
+            Self::KIND,
+            &self.added_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+            &self.id.to_string(),
+        )
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, Error> {
+        let (key, id) = decode_cursor(Self::KIND, cursor)?;
+        let invalid = || Error::InvalidArgument(format!("invalid cursor: {cursor}"));
+
+        let added_at = DateTime::parse_from_rfc3339(&key).map_err(|_| invalid())?;
+        let id = Uuid::parse_str(&id).map_err(|_| invalid())?;
+
+        Ok(Self {
+            added_at: added_at.with_timezone(&Utc),
+            id,
+        })
+    }
+}
+
+/// Full-text search, see [`search::parse_query`](crate::repository::item::search::parse_query)
+/// for the query syntax.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct SearchItems {
+    pub query: String,
+    #[serde(default)]
+    pub filter: ItemFilter,
+    /// Page size, see [`page_size`](super::page::page_size).
+    pub limit: Option<u32>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+}
+
+/// Item matching a search, best matches first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub item: Item,
+    pub rank: f32,
+    /// Excerpt and content around the matches, wrapped in `<mark>` tags.
+    pub snippet: Option<String>,
+}
+
+/// Position after which the next page of search hits starts, tie-broken by id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchCursor {
+    pub rank: f32,
+    pub id: Uuid,
+}
+
+impl SearchCursor {
+    const KIND: &'static str = "rank";
+
+    pub fn after(hit: &SearchHit) -> Self {
+        Self {
+            rank: hit.rank,
+            id: hit.item.id,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        encode_cursor(Self::KIND, &self.rank.to_string(), &self.id.to_string())
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, Error> {
+        let (key, id) = decode_cursor(Self::KIND, cursor)?;
+        let invalid = || Error::InvalidArgument(format!("invalid cursor: {cursor}"));
+
+        let rank = key.parse::<f32>().map_err(|_| invalid())?;
+        let id = Uuid::parse_str(&id).map_err(|_| invalid())?;
+
+        Ok(Self { rank, id })
+    }
+}
@@ -0,0 +1,44 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::{Deserialize, Serialize};
+
+use crate::Error;
+
+pub const DEFAULT_PAGE_SIZE: u32 = 50;
+pub const MAX_PAGE_SIZE: u32 = 500;
+
+/// One page of a keyset paginated listing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Cursor to pass back for the next page, `None` on the last page.
+    pub next_cursor: Option<String>,
+    /// Number of items matching the filters, only when requested.
+    pub total: Option<i64>,
+}
+
+pub fn page_size(limit: Option<u32>) -> u32 {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+/// Cursors are opaque for clients, internally it's `kind|key|id`.
+pub(crate) fn encode_cursor(kind: &str, key: &str, id: &str) -> String {
+    URL_SAFE_NO_PAD.encode(format!("{kind}|{key}|{id}"))
+}
+
+/// Returns the `(key, id)` pair of a cursor created with [`encode_cursor`] for `kind`.
+pub(crate) fn decode_cursor(kind: &str, cursor: &str) -> Result<(String, String), Error> {
+    let invalid = || Error::InvalidArgument(format!("invalid cursor: {cursor}"));
+
+    let bytes = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let value = String::from_utf8(bytes).map_err(|_| invalid())?;
+
+    // keys may contain the separator (e.g. email), kind and id never do
+    let (cursor_kind, rest) = value.split_once('|').ok_or_else(invalid)?;
+    let (key, id) = rest.rsplit_once('|').ok_or_else(invalid)?;
+
+    if cursor_kind != kind {
+        return Err(invalid());
+    }
+
+    Ok((key.to_string(), id.to_string()))
+}
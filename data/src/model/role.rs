@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Role every permission is granted to, the one former `is_admin` users got.
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Permissions seeded by the migrations, roles grant any number of them.
+pub mod permission {
+    pub const USERS_READ: &str = "users:read";
+    pub const USERS_WRITE: &str = "users:write";
+    pub const USERS_DELETE: &str = "users:delete";
+    pub const ROLES_ASSIGN: &str = "roles:assign";
+    pub const ITEMS_MODERATE: &str = "items:moderate";
+    pub const AUDIT_READ: &str = "audit:read";
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Role {
+    pub name: String,
+    pub description: String,
+    pub permissions: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,22 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    /// Absolute expiry, the session can't be extended past it.
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// Session together with its plain tokens, they are not stored and can't be read again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IssuedSession {
+    pub session: Session,
+    pub token: String,
+    pub refresh_token: String,
+}
@@ -0,0 +1,57 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::Error;
+
+/// In characters, like the column.
+pub const MAX_TAG_LENGTH: usize = 64;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Tag with the number of items carrying it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagUsage {
+    #[serde(flatten)]
+    pub tag: Tag,
+    pub item_count: i64,
+}
+
+/// Lowercases `name` and collapses its whitespace, so `Rust  Async` and `rust async`
+/// are the same tag. Empty, too long and control characters give
+/// [`Error::InvalidArgument`].
+pub fn normalize_tag_name(name: &str) -> Result<String, Error> {
+    let normalized = name
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+
+    if normalized.is_empty()
+        || normalized.chars().count() > MAX_TAG_LENGTH
+        || normalized.chars().any(char::is_control)
+    {
+        return Err(Error::InvalidArgument(format!("invalid tag: {name}")));
+    }
+
+    Ok(normalized)
+}
+
+/// Normalises every name with [`normalize_tag_name`], sorted and without duplicates.
+pub fn normalize_tag_names(names: &[String]) -> Result<Vec<String>, Error> {
+    let mut normalized = names
+        .iter()
+        .map(|name| normalize_tag_name(name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    normalized.sort();
+    normalized.dedup();
+
+    Ok(normalized)
+}
@@ -1,23 +1,37 @@
-use chrono::{DateTime, Utc};
+use std::fmt;
+
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::Error;
+
+use super::page::{decode_cursor, encode_cursor};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct User {
     pub id: Uuid,
     pub email: String,
     #[serde(skip_serializing)]
     pub hash: String,
-    #[serde(skip_serializing)]
-    pub is_admin: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// Set while the user is soft deleted, see
+    /// [`UserRepository::delete_user`](crate::repository::user::UserRepository::delete_user).
+    pub deleted_at: Option<DateTime<Utc>>,
 }
 
+/// The email is changed through
+/// [`UserRepository::request_email_change`](crate::repository::user::UserRepository::request_email_change)
+/// instead, so it is confirmed by the new address first.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateUser {
-    pub email: Option<String>,
     pub password: Option<PasswordUpdate>,
+    /// `updated_at` of the user as last read by the caller, the update fails with
+    /// [`crate::Error::Conflict`] if the user was changed since then.
+    #[serde(default)]
+    pub expected_updated_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,3 +39,179 @@ pub struct PasswordUpdate {
     pub old_password: String,
     pub new_password: String,
 }
+
+/// Rule of [`crate::repository::user::policy::PasswordPolicy`] a password breaks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum PasswordViolation {
+    TooShort { min: usize },
+    TooLong { max: usize },
+    MissingLowercase,
+    MissingUppercase,
+    MissingDigit,
+    MissingSymbol,
+    ContainsEmail,
+    Breached,
+}
+
+impl fmt::Display for PasswordViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooShort { min } => write!(f, "shorter than {min} characters"),
+            Self::TooLong { max } => write!(f, "longer than {max} characters"),
+            Self::MissingLowercase => write!(f, "no lowercase letter"),
+            Self::MissingUppercase => write!(f, "no uppercase letter"),
+            Self::MissingDigit => write!(f, "no digit"),
+            Self::MissingSymbol => write!(f, "no symbol"),
+            Self::ContainsEmail => write!(f, "same as the email"),
+            Self::Breached => write!(f, "found in a data breach"),
+        }
+    }
+}
+
+/// Requested change of the login email, applied once `token` comes back from
+/// `new_email`. `current_email` should be told about the request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailChange {
+    pub user_id: Uuid,
+    pub current_email: String,
+    pub new_email: String,
+    #[serde(skip_serializing)]
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Confirmed [`EmailChange`], `previous_email` should be told the login email changed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailChanged {
+    pub user: User,
+    pub previous_email: String,
+}
+
+/// TOTP secret to add to an authenticator app, as base32 for manual entry or as
+/// `otpauth://` URI for a QR code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+/// User moved over from another system, its password hash is kept as is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportUser {
+    pub email: String,
+    /// argon2, scrypt or PBKDF2 PHC string, or a bcrypt hash.
+    pub hash: String,
+    /// Defaults to the time of the import.
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub email_verified_at: Option<DateTime<Utc>>,
+    /// Names of the roles to assign.
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+/// How many stored password hashes don't use the configured hash settings yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HashReport {
+    pub total: i64,
+    pub outdated: i64,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct UserFilter {
+    /// Case-insensitive substring of the email.
+    pub email_contains: Option<String>,
+    /// Users holding the role.
+    pub role: Option<String>,
+    /// Inclusive lower bound of `created_at`.
+    pub created_after: Option<DateTime<Utc>>,
+    /// Exclusive upper bound of `created_at`.
+    pub created_before: Option<DateTime<Utc>>,
+    /// List soft deleted users too, they are left out by default.
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UserSort {
+    #[default]
+    CreatedAtAsc,
+    CreatedAtDesc,
+    EmailAsc,
+    EmailDesc,
+}
+
+impl UserSort {
+    pub fn is_desc(&self) -> bool {
+        matches!(self, Self::CreatedAtDesc | Self::EmailDesc)
+    }
+
+    fn cursor_kind(&self) -> &'static str {
+        match self {
+            Self::CreatedAtAsc | Self::CreatedAtDesc => "created_at",
+            Self::EmailAsc | Self::EmailDesc => "email",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ListUsers {
+    #[serde(default)]
+    pub filter: UserFilter,
+    #[serde(default)]
+    pub sort: UserSort,
+    /// Page size, see [`page_size`](super::page::page_size).
+    pub limit: Option<u32>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub with_total: bool,
+}
+
+/// Position after which the next page starts, always tie-broken by id.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserCursor {
+    CreatedAt(DateTime<Utc>, Uuid),
+    Email(String, Uuid),
+}
+
+impl UserCursor {
+    pub fn after(user: &User, sort: UserSort) -> Self {
+        match sort {
+            UserSort::CreatedAtAsc | UserSort::CreatedAtDesc => {
+                Self::CreatedAt(user.created_at, user.id)
+            }
+            UserSort::EmailAsc | UserSort::EmailDesc => Self::Email(user.email.clone(), user.id),
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        match self {
+            Self::CreatedAt(created_at, id) => encode_cursor(
+                UserSort::CreatedAtAsc.cursor_kind(),
+                &created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+                &id.to_string(),
+            ),
+            Self::Email(email, id) => {
+                encode_cursor(UserSort::EmailAsc.cursor_kind(), email, &id.to_string())
+            }
+        }
+    }
+
+    pub fn decode(cursor: &str, sort: UserSort) -> Result<Self, Error> {
+        let (key, id) = decode_cursor(sort.cursor_kind(), cursor)?;
+        let invalid = || Error::InvalidArgument(format!("invalid cursor: {cursor}"));
+
+        let id = Uuid::parse_str(&id).map_err(|_| invalid())?;
+
+        match sort {
+            UserSort::CreatedAtAsc | UserSort::CreatedAtDesc => {
+                let created_at = DateTime::parse_from_rfc3339(&key).map_err(|_| invalid())?;
+                Ok(Self::CreatedAt(created_at.with_timezone(&Utc), id))
+            }
+            UserSort::EmailAsc | UserSort::EmailDesc => Ok(Self::Email(key, id)),
+        }
+    }
+}
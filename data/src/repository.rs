@@ -1 +1,5 @@
+pub mod api_key;
+pub mod item;
+pub mod role;
+pub mod session;
 pub mod user;
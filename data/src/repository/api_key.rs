@@ -0,0 +1,127 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::{
+    connect,
+    model::api_key::{ApiKey, ApiKeyOwner, IssuedApiKey, NewApiKey},
+    settings::PostgresSettings,
+    Error,
+};
+
+use self::{mock::MockApiKeyRepository, postgres::PostgresApiKeyRepository};
+use super::user::AnyUserRepository;
+
+pub mod mock;
+pub mod postgres;
+
+/// Every key starts with it, so leaked keys are easy to recognise (e.g. by secret scanners).
+pub const API_KEY_LABEL: &str = "spk_";
+
+#[derive(Debug, Clone)]
+pub enum ApiKeyRepositorySettings {
+    Mock,
+    Postgres(PostgresSettings),
+}
+
+pub trait ApiKeyRepository {
+    /// Creates a key for the user, the returned plain key is shown only this once.
+    /// Unknown and deleted users give [`Error::NotFound`], keys failing
+    /// [`NewApiKey::validate`] give [`Error::Validation`].
+    fn create_api_key(
+        &self,
+        user_id: &Uuid,
+        new_key: NewApiKey,
+    ) -> impl Future<Output = Result<IssuedApiKey, Error>> + Send;
+
+    fn list_api_keys(
+        &self,
+        user_id: &Uuid,
+    ) -> impl Future<Output = Result<Vec<ApiKey>, Error>> + Send;
+
+    fn revoke_api_key(&self, id: &Uuid) -> impl Future<Output = Result<ApiKey, Error>> + Send;
+
+    /// Resolves a presented key to its owner and records its use.
+    ///
+    /// Unknown, malformed, revoked and expired keys give [`Error::InvalidToken`].
+    fn resolve_api_key(&self, key: &str)
+        -> impl Future<Output = Result<ApiKeyOwner, Error>> + Send;
+}
+
+/// Splits `spk_<prefix>.<secret>` into prefix and secret.
+pub(crate) fn split_api_key(key: &str) -> Option<(&str, &str)> {
+    let (prefix, secret) = key.strip_prefix(API_KEY_LABEL)?.split_once('.')?;
+
+    if prefix.is_empty() || secret.is_empty() {
+        return None;
+    }
+
+    Some((prefix, secret))
+}
+
+pub(crate) fn format_api_key(prefix: &str, secret: &str) -> String {
+    format!("{API_KEY_LABEL}{prefix}.{secret}")
+}
+
+/// [`ApiKeyRepository`] backend picked at runtime from [`ApiKeyRepositorySettings`].
+#[derive(Debug, Clone)]
+pub enum AnyApiKeyRepository {
+    Mock(MockApiKeyRepository),
+    Postgres(PostgresApiKeyRepository),
+}
+
+impl AnyApiKeyRepository {
+    /// The mock backend serves the users of the mock [`AnyUserRepository`], other user
+    /// backends give [`Error::InvalidArgument`].
+    pub async fn from_settings(
+        settings: &ApiKeyRepositorySettings,
+        users: &AnyUserRepository,
+    ) -> Result<Self, Error> {
+        match (settings, users) {
+            (ApiKeyRepositorySettings::Mock, AnyUserRepository::Mock(users)) => {
+                Ok(Self::Mock(MockApiKeyRepository::new(users.clone())))
+            }
+            (ApiKeyRepositorySettings::Mock, _) => Err(Error::InvalidArgument(
+                "mock api keys need mock users".to_string(),
+            )),
+            (ApiKeyRepositorySettings::Postgres(pg_settings), _) => {
+                let pool = connect(pg_settings).await?;
+                Ok(Self::Postgres(PostgresApiKeyRepository::new(pool)))
+            }
+        }
+    }
+}
+
+impl ApiKeyRepository for AnyApiKeyRepository {
+    async fn create_api_key(
+        &self,
+        user_id: &Uuid,
+        new_key: NewApiKey,
+    ) -> Result<IssuedApiKey, Error> {
+        match self {
+            Self::Mock(repo) => repo.create_api_key(user_id, new_key).await,
+            Self::Postgres(repo) => repo.create_api_key(user_id, new_key).await,
+        }
+    }
+
+    async fn list_api_keys(&self, user_id: &Uuid) -> Result<Vec<ApiKey>, Error> {
+        match self {
+            Self::Mock(repo) => repo.list_api_keys(user_id).await,
+            Self::Postgres(repo) => repo.list_api_keys(user_id).await,
+        }
+    }
+
+    async fn revoke_api_key(&self, id: &Uuid) -> Result<ApiKey, Error> {
+        match self {
+            Self::Mock(repo) => repo.revoke_api_key(id).await,
+            Self::Postgres(repo) => repo.revoke_api_key(id).await,
+        }
+    }
+
+    async fn resolve_api_key(&self, key: &str) -> Result<ApiKeyOwner, Error> {
+        match self {
+            Self::Mock(repo) => repo.resolve_api_key(key).await,
+            Self::Postgres(repo) => repo.resolve_api_key(key).await,
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, SubsecRound, Utc};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    model::api_key::{ApiKey, ApiKeyOwner, IssuedApiKey, NewApiKey},
+    model::user::User,
+    repository::user::mock::{active_user, MockUserRepository},
+    token::{generate_prefix, generate_token, hash_token, verify_token},
+    Error,
+};
+
+use super::{format_api_key, split_api_key, ApiKeyRepository};
+
+/// In-memory [`ApiKeyRepository`] used where a database is not available (e.g. service tests).
+///
+/// Serves the users of a [`MockUserRepository`] and shares its keys with it, so keys
+/// resolve to the current user and go when the user is purged.
+#[derive(Debug, Clone)]
+pub struct MockApiKeyRepository {
+    users: Arc<RwLock<HashMap<Uuid, User>>>,
+    api_keys: Arc<RwLock<HashMap<Uuid, MockApiKey>>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MockApiKey {
+    pub(crate) api_key: ApiKey,
+    secret_hash: String,
+}
+
+impl MockApiKeyRepository {
+    pub fn new(users: MockUserRepository) -> Self {
+        Self {
+            api_keys: users.api_keys(),
+            users: users.users(),
+        }
+    }
+}
+
+fn now() -> DateTime<Utc> {
+    // Postgres TIMESTAMPTZ keeps microseconds only
+    Utc::now().trunc_subsecs(6)
+}
+
+impl ApiKeyRepository for MockApiKeyRepository {
+    async fn create_api_key(
+        &self,
+        user_id: &Uuid,
+        new_key: NewApiKey,
+    ) -> Result<IssuedApiKey, Error> {
+        new_key.validate()?;
+
+        // deleted users don't get new keys
+        active_user(&self.users, user_id)
+            .await
+            .ok_or_else(|| Error::NotFound(user_id.to_string()))?;
+
+        let prefix = generate_prefix();
+        let secret = generate_token();
+
+        let api_key = ApiKey {
+            id: Uuid::new_v4(),
+            user_id: *user_id,
+            name: new_key.name,
+            prefix,
+            scopes: new_key.scopes,
+            created_at: now(),
+            last_used_at: None,
+            expires_at: new_key
+                .expires_at
+                .map(|expires_at| expires_at.trunc_subsecs(6)),
+            revoked_at: None,
+        };
+
+        self.api_keys.write().await.insert(
+            api_key.id,
+            MockApiKey {
+                api_key: api_key.clone(),
+                secret_hash: hash_token(&secret),
+            },
+        );
+
+        Ok(IssuedApiKey {
+            key: format_api_key(&api_key.prefix, &secret),
+            api_key,
+        })
+    }
+
+    async fn list_api_keys(&self, user_id: &Uuid) -> Result<Vec<ApiKey>, Error> {
+        let mut keys: Vec<ApiKey> = self
+            .api_keys
+            .read()
+            .await
+            .values()
+            .filter(|stored| stored.api_key.user_id == *user_id)
+            .map(|stored| stored.api_key.clone())
+            .collect();
+
+        keys.sort_by_key(|key| (key.created_at, key.id));
+
+        Ok(keys)
+    }
+
+    async fn revoke_api_key(&self, id: &Uuid) -> Result<ApiKey, Error> {
+        let mut api_keys = self.api_keys.write().await;
+
+        let stored = api_keys
+            .get_mut(id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        stored.api_key.revoked_at.get_or_insert(now());
+
+        Ok(stored.api_key.clone())
+    }
+
+    async fn resolve_api_key(&self, key: &str) -> Result<ApiKeyOwner, Error> {
+        let (prefix, secret) = split_api_key(key).ok_or(Error::InvalidToken)?;
+
+        // not holding the keys while the owner is looked up, purges lock users first
+        let stored = self
+            .api_keys
+            .read()
+            .await
+            .values()
+            .find(|stored| stored.api_key.prefix == prefix)
+            .cloned();
+
+        let user = match &stored {
+            Some(stored) => active_user(&self.users, &stored.api_key.user_id).await,
+            None => None,
+        };
+
+        // compare against something even for unknown prefixes
+        let stored_hash = stored
+            .as_ref()
+            .map(|stored| stored.secret_hash.clone())
+            .unwrap_or_else(|| hash_token(""));
+        let matches = verify_token(secret, &stored_hash);
+
+        let (stored, user) = match (stored, user) {
+            (Some(stored), Some(user)) if matches => (stored, user),
+            _ => return Err(Error::InvalidToken),
+        };
+
+        let mut api_keys = self.api_keys.write().await;
+
+        let now = now();
+        let api_key = api_keys
+            .get_mut(&stored.api_key.id)
+            .map(|stored| &mut stored.api_key)
+            .filter(|api_key| {
+                api_key.revoked_at.is_none()
+                    && api_key.expires_at.is_none_or(|expires_at| expires_at > now)
+            })
+            .ok_or(Error::InvalidToken)?;
+
+        api_key.last_used_at = Some(now);
+
+        Ok(ApiKeyOwner {
+            api_key: api_key.clone(),
+            user,
+        })
+    }
+}
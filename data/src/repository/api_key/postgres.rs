@@ -0,0 +1,247 @@
+use chrono::Utc;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    errors::{map_sqlx_error, ErrorExt, ErrorKindExt},
+    model::{
+        api_key::{ApiKey, ApiKeyOwner, IssuedApiKey, NewApiKey},
+        user::User,
+    },
+    token::{generate_prefix, generate_token, hash_token, verify_token},
+    Error,
+};
+
+use super::{format_api_key, split_api_key, ApiKeyRepository};
+
+#[derive(Debug, Clone)]
+pub struct PostgresApiKeyRepository {
+    pub pool: PgPool,
+}
+
+impl PostgresApiKeyRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl ApiKeyRepository for PostgresApiKeyRepository {
+    async fn create_api_key(
+        &self,
+        user_id: &Uuid,
+        new_key: NewApiKey,
+    ) -> Result<IssuedApiKey, Error> {
+        new_key.validate()?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let new_id = Uuid::new_v4();
+        let prefix = generate_prefix();
+        let secret = generate_token();
+
+        let result = sqlx::query!(
+            r#"
+                INSERT INTO api_keys ( id, user_id, name, prefix, secret_hash, scopes, expires_at )
+                SELECT $1, id, $3, $4, $5, $6, $7
+                FROM users
+                WHERE id = $2 AND deleted_at IS NULL
+                RETURNING *;
+            "#,
+            new_id,
+            user_id,
+            new_key.name,
+            prefix,
+            hash_token(&secret),
+            &new_key.scopes,
+            new_key.expires_at
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| match err.kind_ext() {
+            ErrorKindExt::ForeignKeyViolation => Error::NotFound(user_id.to_string()),
+            _ => map_sqlx_error(err, &prefix, Error::WriteError),
+        })?
+        // deleted users don't get new keys
+        .ok_or_else(|| Error::NotFound(user_id.to_string()))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(IssuedApiKey {
+            key: format_api_key(&result.prefix, &secret),
+            api_key: ApiKey {
+                id: result.id,
+                user_id: result.user_id,
+                name: result.name,
+                prefix: result.prefix,
+                scopes: result.scopes,
+                created_at: result.created_at,
+                last_used_at: result.last_used_at,
+                expires_at: result.expires_at,
+                revoked_at: result.revoked_at,
+            },
+        })
+    }
+
+    async fn list_api_keys(&self, user_id: &Uuid) -> Result<Vec<ApiKey>, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"SELECT * FROM api_keys WHERE user_id = $1 ORDER BY created_at, id;"#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user_id, Error::ReadError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        let keys: Vec<ApiKey> = result
+            .into_iter()
+            .map(|value| ApiKey {
+                id: value.id,
+                user_id: value.user_id,
+                name: value.name,
+                prefix: value.prefix,
+                scopes: value.scopes,
+                created_at: value.created_at,
+                last_used_at: value.last_used_at,
+                expires_at: value.expires_at,
+                revoked_at: value.revoked_at,
+            })
+            .collect();
+
+        Ok(keys)
+    }
+
+    async fn revoke_api_key(&self, id: &Uuid) -> Result<ApiKey, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE api_keys
+                SET revoked_at = COALESCE(revoked_at, NOW())
+                WHERE id = $1
+                RETURNING *;
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(ApiKey {
+            id: result.id,
+            user_id: result.user_id,
+            name: result.name,
+            prefix: result.prefix,
+            scopes: result.scopes,
+            created_at: result.created_at,
+            last_used_at: result.last_used_at,
+            expires_at: result.expires_at,
+            revoked_at: result.revoked_at,
+        })
+    }
+
+    async fn resolve_api_key(&self, key: &str) -> Result<ApiKeyOwner, Error> {
+        let (prefix, secret) = split_api_key(key).ok_or(Error::InvalidToken)?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                SELECT
+                    k.id, k.user_id, k.name, k.prefix, k.secret_hash, k.scopes,
+                    k.created_at, k.last_used_at, k.expires_at, k.revoked_at,
+                    u.email, u.hash, u.email_verified_at, u.deleted_at,
+                    u.created_at AS user_created_at, u.updated_at AS user_updated_at
+                FROM api_keys k
+                JOIN users u ON u.id = k.user_id
+                WHERE k.prefix = $1 AND u.deleted_at IS NULL;
+            "#,
+            prefix
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, prefix, Error::ReadError))?;
+
+        // compare against something even for unknown prefixes
+        let stored_hash = result
+            .as_ref()
+            .map(|value| value.secret_hash.clone())
+            .unwrap_or_else(|| hash_token(""));
+        let matches = verify_token(secret, &stored_hash);
+
+        let result = match result {
+            Some(value) if matches => value,
+            _ => return Err(Error::InvalidToken),
+        };
+
+        let expired = result
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now());
+
+        if result.revoked_at.is_some() || expired {
+            return Err(Error::InvalidToken);
+        }
+
+        let last_used_at = sqlx::query_scalar!(
+            r#"
+                UPDATE api_keys
+                SET last_used_at = NOW()
+                WHERE id = $1
+                RETURNING last_used_at;
+            "#,
+            result.id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, result.id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(ApiKeyOwner {
+            api_key: ApiKey {
+                id: result.id,
+                user_id: result.user_id,
+                name: result.name,
+                prefix: result.prefix,
+                scopes: result.scopes,
+                created_at: result.created_at,
+                last_used_at,
+                expires_at: result.expires_at,
+                revoked_at: result.revoked_at,
+            },
+            user: User {
+                id: result.user_id,
+                email: result.email,
+                hash: result.hash,
+                created_at: result.user_created_at,
+                updated_at: result.user_updated_at,
+                email_verified_at: result.email_verified_at,
+                deleted_at: result.deleted_at,
+            },
+        })
+    }
+}
@@ -0,0 +1,314 @@
+use std::future::Future;
+
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    connect,
+    model::{
+        item::{
+            ImportItem, ImportReport, Item, ListItems, NewItem, SearchHit, SearchItems, UpdateItem,
+        },
+        page::Page,
+        tag::{normalize_tag_names, Tag, TagUsage},
+    },
+    settings::PostgresSettings,
+    Error,
+};
+
+use self::{mock::MockItemRepository, postgres::PostgresItemRepository};
+use super::user::AnyUserRepository;
+
+pub mod canonical;
+pub mod mock;
+pub mod pocket;
+pub mod postgres;
+pub mod search;
+
+/// Entries of an import written per transaction.
+pub const IMPORT_BATCH_SIZE: usize = 100;
+
+#[derive(Debug, Clone)]
+pub enum ItemRepositorySettings {
+    Mock,
+    Postgres(PostgresSettings),
+}
+
+/// Saved items, every method is scoped to the owning user. Items of other users give
+/// [`Error::NotFound`] like missing ones.
+pub trait ItemRepository {
+    fn get_item(
+        &self,
+        user_id: &Uuid,
+        id: &Uuid,
+    ) -> impl Future<Output = Result<Item, Error>> + Send;
+
+    /// URLs other than absolute `http` and `https` ones give [`Error::InvalidArgument`],
+    /// unknown and deleted users [`Error::NotFound`].
+    ///
+    /// Saving a page the user has already, by its [`canonical::canonicalize`]d URL,
+    /// returns the existing item moved to the top of the list: `added_at` is bumped and
+    /// a missing title or excerpt is filled in.
+    fn create_item(
+        &self,
+        user_id: &Uuid,
+        new_item: NewItem,
+    ) -> impl Future<Output = Result<Item, Error>> + Send;
+
+    /// Saves items moved over from another service in batches of [`IMPORT_BATCH_SIZE`],
+    /// each in its own transaction. Pages the user has saved already are skipped and
+    /// left as they are, entries with an invalid URL or tag are reported as failed.
+    ///
+    /// Unknown and deleted users give [`Error::NotFound`]. A batch failing on the
+    /// database is reported with all its entries as failed and the import goes on with
+    /// the next one, importing the same entries again skips those that were saved.
+    ///
+    /// Archived entries count as read when they were added.
+    fn import_items(
+        &self,
+        user_id: &Uuid,
+        items: Vec<ImportItem>,
+    ) -> impl Future<Output = Result<ImportReport, Error>> + Send;
+
+    /// If `update.expected_updated_at` no longer matches the stored item
+    /// [`Error::Conflict`] is returned.
+    fn update_item(
+        &self,
+        user_id: &Uuid,
+        id: &Uuid,
+        update: UpdateItem,
+    ) -> impl Future<Output = Result<Item, Error>> + Send;
+
+    fn delete_item(
+        &self,
+        user_id: &Uuid,
+        id: &Uuid,
+    ) -> impl Future<Output = Result<Item, Error>> + Send;
+
+    /// Keyset paginated listing, pass `next_cursor` of a page to get the following one.
+    fn list_items(
+        &self,
+        user_id: &Uuid,
+        query: ListItems,
+    ) -> impl Future<Output = Result<Page<Item>, Error>> + Send;
+
+    /// Full-text search over title, excerpt and content, best matches first, paginated
+    /// like [`ItemRepository::list_items`]. Queries without any word give
+    /// [`Error::InvalidArgument`].
+    ///
+    /// The in-memory backend falls back to case-insensitive substring matching.
+    fn search_items(
+        &self,
+        user_id: &Uuid,
+        query: SearchItems,
+    ) -> impl Future<Output = Result<Page<SearchHit>, Error>> + Send;
+
+    /// Tags of an item, sorted by name.
+    fn item_tags(
+        &self,
+        user_id: &Uuid,
+        item_id: &Uuid,
+    ) -> impl Future<Output = Result<Vec<Tag>, Error>> + Send;
+
+    /// Tags the item, creating the user's tags that don't exist yet. Names are
+    /// [`normalize_tag_name`](crate::model::tag::normalize_tag_name)d, invalid ones give
+    /// [`Error::InvalidArgument`] and nothing is added. Returns the tags of the item.
+    fn add_tags(
+        &self,
+        user_id: &Uuid,
+        item_id: &Uuid,
+        names: Vec<String>,
+    ) -> impl Future<Output = Result<Vec<Tag>, Error>> + Send;
+
+    /// Untags the item, names it doesn't carry are ignored. The tags themselves are kept
+    /// even once unused. Returns the remaining tags of the item.
+    fn remove_tags(
+        &self,
+        user_id: &Uuid,
+        item_id: &Uuid,
+        names: Vec<String>,
+    ) -> impl Future<Output = Result<Vec<Tag>, Error>> + Send;
+
+    /// All tags of the user with the number of items carrying them, sorted by name.
+    fn list_tags(
+        &self,
+        user_id: &Uuid,
+    ) -> impl Future<Output = Result<Vec<TagUsage>, Error>> + Send;
+
+    /// Gives [`Error::AlreadyExists`] if the user has a tag called `new_name` already,
+    /// see [`ItemRepository::merge_tags`] for combining them.
+    fn rename_tag(
+        &self,
+        user_id: &Uuid,
+        name: &str,
+        new_name: &str,
+    ) -> impl Future<Output = Result<Tag, Error>> + Send;
+
+    /// Moves the items of tag `source` to tag `target` and deletes `source`, all or
+    /// nothing. Both tags must exist.
+    fn merge_tags(
+        &self,
+        user_id: &Uuid,
+        source: &str,
+        target: &str,
+    ) -> impl Future<Output = Result<TagUsage, Error>> + Send;
+}
+
+/// Checked URL, canonical URL and normalised tags of an entry to import.
+fn prepare_import(item: &ImportItem) -> Result<(Url, String, Vec<String>), Error> {
+    let url = canonical::parse_url(&item.url)?;
+    let canonical_url = canonical::canonicalize(&url);
+    let tags = normalize_tag_names(&item.tags)?;
+
+    Ok((url, canonical_url, tags))
+}
+
+/// [`ItemRepository`] backend picked at runtime from [`ItemRepositorySettings`].
+#[derive(Debug, Clone)]
+pub enum AnyItemRepository {
+    Mock(MockItemRepository),
+    Postgres(PostgresItemRepository),
+}
+
+impl AnyItemRepository {
+    /// The mock backend serves the users of the mock [`AnyUserRepository`], other user
+    /// backends give [`Error::InvalidArgument`].
+    pub async fn from_settings(
+        settings: &ItemRepositorySettings,
+        users: &AnyUserRepository,
+    ) -> Result<Self, Error> {
+        match (settings, users) {
+            (ItemRepositorySettings::Mock, AnyUserRepository::Mock(users)) => {
+                Ok(Self::Mock(MockItemRepository::new(users.clone())))
+            }
+            (ItemRepositorySettings::Mock, _) => Err(Error::InvalidArgument(
+                "mock items need mock users".to_string(),
+            )),
+            (ItemRepositorySettings::Postgres(pg_settings), _) => {
+                let pool = connect(pg_settings).await?;
+                Ok(Self::Postgres(PostgresItemRepository::new(pool)))
+            }
+        }
+    }
+}
+
+impl ItemRepository for AnyItemRepository {
+    async fn get_item(&self, user_id: &Uuid, id: &Uuid) -> Result<Item, Error> {
+        match self {
+            Self::Mock(repo) => repo.get_item(user_id, id).await,
+            Self::Postgres(repo) => repo.get_item(user_id, id).await,
+        }
+    }
+
+    async fn create_item(&self, user_id: &Uuid, new_item: NewItem) -> Result<Item, Error> {
+        match self {
+            Self::Mock(repo) => repo.create_item(user_id, new_item).await,
+            Self::Postgres(repo) => repo.create_item(user_id, new_item).await,
+        }
+    }
+
+    async fn import_items(
+        &self,
+        user_id: &Uuid,
+        items: Vec<ImportItem>,
+    ) -> Result<ImportReport, Error> {
+        match self {
+            Self::Mock(repo) => repo.import_items(user_id, items).await,
+            Self::Postgres(repo) => repo.import_items(user_id, items).await,
+        }
+    }
+
+    async fn update_item(
+        &self,
+        user_id: &Uuid,
+        id: &Uuid,
+        update: UpdateItem,
+    ) -> Result<Item, Error> {
+        match self {
+            Self::Mock(repo) => repo.update_item(user_id, id, update).await,
+            Self::Postgres(repo) => repo.update_item(user_id, id, update).await,
+        }
+    }
+
+    async fn delete_item(&self, user_id: &Uuid, id: &Uuid) -> Result<Item, Error> {
+        match self {
+            Self::Mock(repo) => repo.delete_item(user_id, id).await,
+            Self::Postgres(repo) => repo.delete_item(user_id, id).await,
+        }
+    }
+
+    async fn list_items(&self, user_id: &Uuid, query: ListItems) -> Result<Page<Item>, Error> {
+        match self {
+            Self::Mock(repo) => repo.list_items(user_id, query).await,
+            Self::Postgres(repo) => repo.list_items(user_id, query).await,
+        }
+    }
+
+    async fn search_items(
+        &self,
+        user_id: &Uuid,
+        query: SearchItems,
+    ) -> Result<Page<SearchHit>, Error> {
+        match self {
+            Self::Mock(repo) => repo.search_items(user_id, query).await,
+            Self::Postgres(repo) => repo.search_items(user_id, query).await,
+        }
+    }
+
+    async fn item_tags(&self, user_id: &Uuid, item_id: &Uuid) -> Result<Vec<Tag>, Error> {
+        match self {
+            Self::Mock(repo) => repo.item_tags(user_id, item_id).await,
+            Self::Postgres(repo) => repo.item_tags(user_id, item_id).await,
+        }
+    }
+
+    async fn add_tags(
+        &self,
+        user_id: &Uuid,
+        item_id: &Uuid,
+        names: Vec<String>,
+    ) -> Result<Vec<Tag>, Error> {
+        match self {
+            Self::Mock(repo) => repo.add_tags(user_id, item_id, names).await,
+            Self::Postgres(repo) => repo.add_tags(user_id, item_id, names).await,
+        }
+    }
+
+    async fn remove_tags(
+        &self,
+        user_id: &Uuid,
+        item_id: &Uuid,
+        names: Vec<String>,
+    ) -> Result<Vec<Tag>, Error> {
+        match self {
+            Self::Mock(repo) => repo.remove_tags(user_id, item_id, names).await,
+            Self::Postgres(repo) => repo.remove_tags(user_id, item_id, names).await,
+        }
+    }
+
+    async fn list_tags(&self, user_id: &Uuid) -> Result<Vec<TagUsage>, Error> {
+        match self {
+            Self::Mock(repo) => repo.list_tags(user_id).await,
+            Self::Postgres(repo) => repo.list_tags(user_id).await,
+        }
+    }
+
+    async fn rename_tag(&self, user_id: &Uuid, name: &str, new_name: &str) -> Result<Tag, Error> {
+        match self {
+            Self::Mock(repo) => repo.rename_tag(user_id, name, new_name).await,
+            Self::Postgres(repo) => repo.rename_tag(user_id, name, new_name).await,
+        }
+    }
+
+    async fn merge_tags(
+        &self,
+        user_id: &Uuid,
+        source: &str,
+        target: &str,
+    ) -> Result<TagUsage, Error> {
+        match self {
+            Self::Mock(repo) => repo.merge_tags(user_id, source, target).await,
+            Self::Postgres(repo) => repo.merge_tags(user_id, source, target).await,
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use url::Url;
+
+use crate::Error;
+
+/// Query parameters that only track where a visit came from, dropped from canonical URLs
+/// along with every `utm_*` one.
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "yclid", "mc_cid", "mc_eid",
+    "igshid", "_ga", "_gl", "ref_src", "ref_url",
+];
+
+/// Checks that `url` is an absolute `http` or `https` URL.
+pub fn parse_url(url: &str) -> Result<Url, Error> {
+    let invalid = || Error::InvalidArgument(format!("invalid item url: {url}"));
+
+    let parsed = Url::parse(url.trim()).map_err(|_| invalid())?;
+
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host().is_none() {
+        return Err(invalid());
+    }
+
+    Ok(parsed)
+}
+
+/// Form of `url` saved items are told apart by, it isn't meant to be opened.
+///
+/// `http` becomes `https`, default ports, fragments, tracking parameters and trailing
+/// slashes are dropped, the remaining parameters are sorted. Host case is already
+/// normalised by [`parse_url`].
+pub fn canonicalize(url: &Url) -> String {
+    let mut canonical = url.clone();
+
+    // http and https are the same page for every site worth saving, default ports
+    // are dropped by the url crate already
+    let _ = canonical.set_scheme("https");
+
+    if let Some(host) = canonical.host_str() {
+        if let Some(trimmed) = host.strip_suffix('.') {
+            let trimmed = trimmed.to_string();
+            let _ = canonical.set_host(Some(&trimmed));
+        }
+    }
+
+    canonical.set_fragment(None);
+
+    let mut params: Vec<(String, String)> = canonical
+        .query_pairs()
+        .filter(|(key, _)| !is_tracking_param(key))
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    params.sort();
+
+    if params.is_empty() {
+        canonical.set_query(None);
+    } else {
+        canonical.query_pairs_mut().clear().extend_pairs(&params);
+    }
+
+    let path = canonical.path();
+    if path.len() > 1 && path.ends_with('/') {
+        let trimmed = path.trim_end_matches('/').to_string();
+        canonical.set_path(if trimmed.is_empty() { "/" } else { &trimmed });
+    }
+
+    canonical.into()
+}
+
+fn is_tracking_param(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+
+    key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str())
+}
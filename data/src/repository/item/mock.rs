@@ -0,0 +1,696 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use chrono::{DateTime, SubsecRound, Utc};
+use tokio::sync::RwLock;
+use url::Url;
+use uuid::Uuid;
+
+use crate::{
+    model::{
+        item::{
+            ImportFailure, ImportItem, ImportReport, Item, ItemCursor, ItemFilter, ItemStatus,
+            ListItems, NewItem, SearchCursor, SearchHit, SearchItems, UpdateItem,
+        },
+        page::{page_size, Page},
+        tag::{normalize_tag_name, normalize_tag_names, Tag, TagUsage},
+        user::User,
+    },
+    repository::user::mock::{active_user, MockUserRepository},
+    Error,
+};
+
+use super::{canonical, prepare_import, search, ItemRepository, IMPORT_BATCH_SIZE};
+
+/// In-memory [`ItemRepository`] used where a database is not available (e.g. service tests).
+///
+/// Serves the users of a [`MockUserRepository`], unknown and deleted ones can't save items.
+#[derive(Debug, Clone)]
+pub struct MockItemRepository {
+    users: Arc<RwLock<HashMap<Uuid, User>>>,
+    items: Arc<RwLock<HashMap<Uuid, Item>>>,
+    tags: Arc<RwLock<HashMap<Uuid, Tag>>>,
+    /// `(item_id, tag_id)` pairs. Locks are taken in field order: items, tags, item_tags.
+    item_tags: Arc<RwLock<HashSet<(Uuid, Uuid)>>>,
+}
+
+impl MockItemRepository {
+    pub fn new(users: MockUserRepository) -> Self {
+        Self {
+            users: users.users(),
+            items: Arc::default(),
+            tags: Arc::default(),
+            item_tags: Arc::default(),
+        }
+    }
+
+    /// Inserts an item as is, the in-memory counterpart of a fixture.
+    pub async fn insert_item(&self, item: Item) -> Result<Item, Error> {
+        let mut items = self.items.write().await;
+
+        if items.contains_key(&item.id)
+            || items
+                .values()
+                .any(|other| is_same_page(other, &item.user_id, &item.canonical_url))
+        {
+            return Err(Error::AlreadyExists(item.canonical_url));
+        }
+
+        items.insert(item.id, item.clone());
+
+        Ok(item)
+    }
+}
+
+fn now() -> DateTime<Utc> {
+    // Postgres TIMESTAMPTZ keeps microseconds only
+    Utc::now().trunc_subsecs(6)
+}
+
+fn is_same_page(item: &Item, user_id: &Uuid, canonical_url: &str) -> bool {
+    item.user_id == *user_id && item.canonical_url == canonical_url
+}
+
+fn owns_item(items: &HashMap<Uuid, Item>, user_id: &Uuid, item_id: &Uuid) -> Result<(), Error> {
+    match items.get(item_id) {
+        Some(item) if item.user_id == *user_id => Ok(()),
+        _ => Err(Error::NotFound(item_id.to_string())),
+    }
+}
+
+fn find_tag<'a>(
+    tags: &'a HashMap<Uuid, Tag>,
+    user_id: &Uuid,
+    name: &str,
+) -> Result<&'a Tag, Error> {
+    tags.values()
+        .find(|tag| tag.user_id == *user_id && tag.name == name)
+        .ok_or_else(|| Error::NotFound(name.to_string()))
+}
+
+/// Tags the item with normalised `names`, creating the user's tags that don't exist yet.
+fn tag_item(
+    tags: &mut HashMap<Uuid, Tag>,
+    item_tags: &mut HashSet<(Uuid, Uuid)>,
+    user_id: &Uuid,
+    item_id: &Uuid,
+    names: Vec<String>,
+) {
+    for name in names {
+        let tag_id = match find_tag(tags, user_id, &name) {
+            Ok(tag) => tag.id,
+            Err(_) => {
+                let tag = Tag {
+                    id: Uuid::new_v4(),
+                    user_id: *user_id,
+                    name,
+                    created_at: now(),
+                };
+                let tag_id = tag.id;
+                tags.insert(tag_id, tag);
+                tag_id
+            }
+        };
+
+        item_tags.insert((*item_id, tag_id));
+    }
+}
+
+fn tags_of_item(
+    tags: &HashMap<Uuid, Tag>,
+    item_tags: &HashSet<(Uuid, Uuid)>,
+    item_id: &Uuid,
+) -> Vec<Tag> {
+    let mut result: Vec<Tag> = item_tags
+        .iter()
+        .filter(|(tagged_item_id, _)| tagged_item_id == item_id)
+        .filter_map(|(_, tag_id)| tags.get(tag_id).cloned())
+        .collect();
+
+    result.sort_by(|a, b| a.name.cmp(&b.name));
+
+    result
+}
+
+fn usage_of_tag(tag: &Tag, item_tags: &HashSet<(Uuid, Uuid)>) -> TagUsage {
+    TagUsage {
+        tag: tag.clone(),
+        item_count: item_tags
+            .iter()
+            .filter(|(_, tag_id)| *tag_id == tag.id)
+            .count() as i64,
+    }
+}
+
+/// Ids of the user's items carrying tag `name`.
+fn tagged_items(
+    tags: &HashMap<Uuid, Tag>,
+    item_tags: &HashSet<(Uuid, Uuid)>,
+    user_id: &Uuid,
+    name: &str,
+) -> HashSet<Uuid> {
+    match find_tag(tags, user_id, name) {
+        Ok(tag) => item_tags
+            .iter()
+            .filter(|(_, tag_id)| *tag_id == tag.id)
+            .map(|(item_id, _)| *item_id)
+            .collect(),
+        Err(_) => HashSet::new(),
+    }
+}
+
+fn is_on_domain(item: &Item, domain: &str) -> bool {
+    Url::parse(&item.canonical_url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .is_some_and(|host| host == domain || host.ends_with(&format!(".{domain}")))
+}
+
+/// `filter` is expected to be [`ItemFilter::normalized`], `tagged` the items carrying
+/// its tag.
+fn matches_filter(item: &Item, filter: &ItemFilter, tagged: &HashSet<Uuid>) -> bool {
+    if let Some(status) = filter.status {
+        if item.status != status {
+            return false;
+        }
+    }
+
+    if let Some(favorite) = filter.favorite {
+        if item.favorite != favorite {
+            return false;
+        }
+    }
+
+    if filter.tag.is_some() && !tagged.contains(&item.id) {
+        return false;
+    }
+
+    if let Some(domain) = &filter.domain {
+        if !is_on_domain(item, domain) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Weights of Postgres' `ts_rank` for the title, excerpt and content.
+fn rank(item: &Item, needles: &[String]) -> Option<f32> {
+    let fields = [
+        (item.title.as_deref(), 1.0),
+        (item.excerpt.as_deref(), 0.4),
+        (item.content.as_deref(), 0.2),
+    ]
+    .map(|(field, weight)| (field.unwrap_or_default().to_lowercase(), weight));
+
+    let mut rank = 0.0;
+
+    for needle in needles {
+        let mut found = false;
+        for (field, weight) in &fields {
+            if field.contains(needle.as_str()) {
+                rank += weight;
+                found = true;
+            }
+        }
+        if !found {
+            return None;
+        }
+    }
+
+    Some(rank)
+}
+
+/// Wraps the occurrences of `needles` in `<mark>` tags, word by word like the Postgres
+/// headline.
+fn highlight(text: &str, needles: &[String]) -> String {
+    let lower = text.to_lowercase();
+
+    // offsets into the lowercase text only line up if lowercasing kept the length
+    if lower.len() != text.len() {
+        return text.to_string();
+    }
+
+    let mut marked = vec![false; text.len()];
+    for needle in needles {
+        for (start, _) in lower.match_indices(needle.as_str()) {
+            for (offset, c) in needle.char_indices() {
+                if !c.is_whitespace() {
+                    marked[start + offset..start + offset + c.len_utf8()].fill(true);
+                }
+            }
+        }
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut open = false;
+    for (index, c) in text.char_indices() {
+        if marked[index] != open {
+            result.push_str(if open { "</mark>" } else { "<mark>" });
+            open = marked[index];
+        }
+        result.push(c);
+    }
+    if open {
+        result.push_str("</mark>");
+    }
+
+    result
+}
+
+impl ItemRepository for MockItemRepository {
+    async fn get_item(&self, user_id: &Uuid, id: &Uuid) -> Result<Item, Error> {
+        let items = self.items.read().await;
+
+        items
+            .get(id)
+            .filter(|item| item.user_id == *user_id)
+            .cloned()
+            .ok_or_else(|| Error::NotFound(id.to_string()))
+    }
+
+    async fn create_item(&self, user_id: &Uuid, new_item: NewItem) -> Result<Item, Error> {
+        let url = canonical::parse_url(&new_item.url)?;
+        let canonical_url = canonical::canonicalize(&url);
+
+        // deleted users can't save items
+        active_user(&self.users, user_id)
+            .await
+            .ok_or_else(|| Error::NotFound(user_id.to_string()))?;
+
+        let mut items = self.items.write().await;
+
+        let added_at = now();
+
+        if let Some(existing) = items
+            .values_mut()
+            .find(|item| is_same_page(item, user_id, &canonical_url))
+        {
+            existing.added_at = added_at;
+            existing.updated_at = added_at;
+            existing.title = existing.title.take().or(new_item.title);
+            existing.excerpt = existing.excerpt.take().or(new_item.excerpt);
+            existing.content = existing.content.take().or(new_item.content);
+
+            return Ok(existing.clone());
+        }
+
+        let item = Item {
+            id: Uuid::new_v4(),
+            user_id: *user_id,
+            url: url.into(),
+            canonical_url,
+            title: new_item.title,
+            excerpt: new_item.excerpt,
+            content: new_item.content,
+            status: ItemStatus::Unread,
+            favorite: false,
+            added_at,
+            read_at: None,
+            updated_at: added_at,
+        };
+
+        items.insert(item.id, item.clone());
+
+        Ok(item)
+    }
+
+    async fn import_items(
+        &self,
+        user_id: &Uuid,
+        items: Vec<ImportItem>,
+    ) -> Result<ImportReport, Error> {
+        active_user(&self.users, user_id)
+            .await
+            .ok_or_else(|| Error::NotFound(user_id.to_string()))?;
+
+        let mut report = ImportReport::default();
+
+        for batch in items.chunks(IMPORT_BATCH_SIZE) {
+            let mut items = self.items.write().await;
+            let mut tags = self.tags.write().await;
+            let mut item_tags = self.item_tags.write().await;
+
+            for entry in batch {
+                let (url, canonical_url, names) = match prepare_import(entry) {
+                    Ok(prepared) => prepared,
+                    Err(err) => {
+                        report.failed.push(ImportFailure {
+                            url: entry.url.clone(),
+                            reason: err.to_string(),
+                        });
+                        continue;
+                    }
+                };
+
+                if items
+                    .values()
+                    .any(|item| is_same_page(item, user_id, &canonical_url))
+                {
+                    report.skipped.push(entry.url.clone());
+                    continue;
+                }
+
+                let now = now();
+                let item = Item {
+                    id: Uuid::new_v4(),
+                    user_id: *user_id,
+                    url: url.into(),
+                    canonical_url,
+                    title: entry.title.clone(),
+                    excerpt: None,
+                    content: None,
+                    status: entry.status,
+                    favorite: false,
+                    added_at: entry.added_at.unwrap_or(now),
+                    read_at: (entry.status == ItemStatus::Archived)
+                        .then_some(entry.added_at.unwrap_or(now)),
+                    updated_at: now,
+                };
+
+                tag_item(&mut tags, &mut item_tags, user_id, &item.id, names);
+                items.insert(item.id, item.clone());
+
+                report.created.push(item);
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn update_item(
+        &self,
+        user_id: &Uuid,
+        id: &Uuid,
+        update: UpdateItem,
+    ) -> Result<Item, Error> {
+        let mut items = self.items.write().await;
+
+        let item = items
+            .get_mut(id)
+            .filter(|item| item.user_id == *user_id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        if let Some(expected) = update.expected_updated_at {
+            if expected != item.updated_at {
+                return Err(Error::Conflict(id.to_string()));
+            }
+        }
+
+        let now = now();
+
+        if let Some(title) = update.title {
+            item.title = Some(title);
+        }
+        if let Some(excerpt) = update.excerpt {
+            item.excerpt = Some(excerpt);
+        }
+        if let Some(content) = update.content {
+            item.content = Some(content);
+        }
+        if let Some(favorite) = update.favorite {
+            item.favorite = favorite;
+        }
+
+        if let Some(status) = update.status {
+            match status {
+                ItemStatus::Archived if item.status != ItemStatus::Archived => {
+                    item.read_at = Some(now);
+                }
+                ItemStatus::Unread => item.read_at = None,
+                ItemStatus::Archived => {}
+            }
+            item.status = status;
+        }
+
+        item.updated_at = now;
+
+        Ok(item.clone())
+    }
+
+    async fn delete_item(&self, user_id: &Uuid, id: &Uuid) -> Result<Item, Error> {
+        let mut items = self.items.write().await;
+
+        owns_item(&items, user_id, id)?;
+
+        self.item_tags
+            .write()
+            .await
+            .retain(|(item_id, _)| item_id != id);
+
+        items
+            .remove(id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))
+    }
+
+    async fn list_items(&self, user_id: &Uuid, query: ListItems) -> Result<Page<Item>, Error> {
+        let size = page_size(query.limit) as usize;
+        let filter = query.filter.normalized()?;
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(ItemCursor::decode)
+            .transpose()?;
+
+        let items = self.items.read().await;
+        let tags = self.tags.read().await;
+        let item_tags = self.item_tags.read().await;
+
+        let tagged = match &filter.tag {
+            Some(tag) => tagged_items(&tags, &item_tags, user_id, tag),
+            None => HashSet::new(),
+        };
+
+        let mut result: Vec<Item> = items
+            .values()
+            .filter(|item| item.user_id == *user_id && matches_filter(item, &filter, &tagged))
+            .cloned()
+            .collect();
+
+        let total = query.with_total.then_some(result.len() as i64);
+
+        result.sort_by_key(|item| (item.added_at, item.id));
+        if query.sort.is_desc() {
+            result.reverse();
+        }
+
+        if let Some(cursor) = cursor {
+            let position = (cursor.added_at, cursor.id);
+            result.retain(|item| {
+                if query.sort.is_desc() {
+                    (item.added_at, item.id) < position
+                } else {
+                    (item.added_at, item.id) > position
+                }
+            });
+        }
+
+        let next_cursor = if result.len() > size {
+            result.truncate(size);
+            result.last().map(|item| ItemCursor::after(item).encode())
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items: result,
+            next_cursor,
+            total,
+        })
+    }
+
+    async fn search_items(
+        &self,
+        user_id: &Uuid,
+        query: SearchItems,
+    ) -> Result<Page<SearchHit>, Error> {
+        let needles: Vec<String> = search::parse_query(&query.query)?
+            .iter()
+            .map(search::SearchTerm::text)
+            .collect();
+        let size = page_size(query.limit) as usize;
+        let filter = query.filter.normalized()?;
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(SearchCursor::decode)
+            .transpose()?;
+
+        let items = self.items.read().await;
+        let tags = self.tags.read().await;
+        let item_tags = self.item_tags.read().await;
+
+        let tagged = match &filter.tag {
+            Some(tag) => tagged_items(&tags, &item_tags, user_id, tag),
+            None => HashSet::new(),
+        };
+
+        let mut hits: Vec<SearchHit> = items
+            .values()
+            .filter(|item| item.user_id == *user_id && matches_filter(item, &filter, &tagged))
+            .filter_map(|item| {
+                let rank = rank(item, &needles)?;
+                let text = [item.excerpt.as_deref(), item.content.as_deref()]
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                Some(SearchHit {
+                    item: item.clone(),
+                    rank,
+                    snippet: (!text.is_empty()).then(|| highlight(&text, &needles)),
+                })
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.rank
+                .total_cmp(&a.rank)
+                .then_with(|| b.item.id.cmp(&a.item.id))
+        });
+
+        if let Some(cursor) = cursor {
+            hits.retain(|hit| (hit.rank, hit.item.id) < (cursor.rank, cursor.id));
+        }
+
+        let next_cursor = if hits.len() > size {
+            hits.truncate(size);
+            hits.last().map(|hit| SearchCursor::after(hit).encode())
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items: hits,
+            next_cursor,
+            total: None,
+        })
+    }
+
+    async fn item_tags(&self, user_id: &Uuid, item_id: &Uuid) -> Result<Vec<Tag>, Error> {
+        let items = self.items.read().await;
+        owns_item(&items, user_id, item_id)?;
+
+        let tags = self.tags.read().await;
+        let item_tags = self.item_tags.read().await;
+
+        Ok(tags_of_item(&tags, &item_tags, item_id))
+    }
+
+    async fn add_tags(
+        &self,
+        user_id: &Uuid,
+        item_id: &Uuid,
+        names: Vec<String>,
+    ) -> Result<Vec<Tag>, Error> {
+        let names = normalize_tag_names(&names)?;
+
+        let items = self.items.read().await;
+        owns_item(&items, user_id, item_id)?;
+
+        let mut tags = self.tags.write().await;
+        let mut item_tags = self.item_tags.write().await;
+
+        tag_item(&mut tags, &mut item_tags, user_id, item_id, names);
+
+        Ok(tags_of_item(&tags, &item_tags, item_id))
+    }
+
+    async fn remove_tags(
+        &self,
+        user_id: &Uuid,
+        item_id: &Uuid,
+        names: Vec<String>,
+    ) -> Result<Vec<Tag>, Error> {
+        let names = normalize_tag_names(&names)?;
+
+        let items = self.items.read().await;
+        owns_item(&items, user_id, item_id)?;
+
+        let tags = self.tags.read().await;
+        let mut item_tags = self.item_tags.write().await;
+
+        for name in names {
+            if let Ok(tag) = find_tag(&tags, user_id, &name) {
+                item_tags.remove(&(*item_id, tag.id));
+            }
+        }
+
+        Ok(tags_of_item(&tags, &item_tags, item_id))
+    }
+
+    async fn list_tags(&self, user_id: &Uuid) -> Result<Vec<TagUsage>, Error> {
+        let tags = self.tags.read().await;
+        let item_tags = self.item_tags.read().await;
+
+        let mut result: Vec<TagUsage> = tags
+            .values()
+            .filter(|tag| tag.user_id == *user_id)
+            .map(|tag| usage_of_tag(tag, &item_tags))
+            .collect();
+
+        result.sort_by(|a, b| a.tag.name.cmp(&b.tag.name));
+
+        Ok(result)
+    }
+
+    async fn rename_tag(&self, user_id: &Uuid, name: &str, new_name: &str) -> Result<Tag, Error> {
+        let name = normalize_tag_name(name)?;
+        let new_name = normalize_tag_name(new_name)?;
+
+        let mut tags = self.tags.write().await;
+
+        let tag_id = find_tag(&tags, user_id, &name)?.id;
+
+        if name != new_name && find_tag(&tags, user_id, &new_name).is_ok() {
+            return Err(Error::AlreadyExists(new_name));
+        }
+
+        let tag = tags
+            .get_mut(&tag_id)
+            .ok_or_else(|| Error::NotFound(name.clone()))?;
+        tag.name = new_name;
+
+        Ok(tag.clone())
+    }
+
+    async fn merge_tags(
+        &self,
+        user_id: &Uuid,
+        source: &str,
+        target: &str,
+    ) -> Result<TagUsage, Error> {
+        let source = normalize_tag_name(source)?;
+        let target = normalize_tag_name(target)?;
+
+        if source == target {
+            return Err(Error::InvalidArgument(format!(
+                "cannot merge tag {source} into itself"
+            )));
+        }
+
+        let mut tags = self.tags.write().await;
+        let mut item_tags = self.item_tags.write().await;
+
+        let source_id = find_tag(&tags, user_id, &source)?.id;
+        let target = find_tag(&tags, user_id, &target)?.clone();
+
+        let moved: Vec<(Uuid, Uuid)> = item_tags
+            .iter()
+            .filter(|(_, tag_id)| *tag_id == source_id)
+            .copied()
+            .collect();
+
+        for (item_id, _) in moved {
+            item_tags.remove(&(item_id, source_id));
+            item_tags.insert((item_id, target.id));
+        }
+
+        tags.remove(&source_id);
+
+        Ok(usage_of_tag(&target, &item_tags))
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::DateTime;
+use scraper::{Html, Selector};
+
+use crate::model::item::{ImportItem, ItemStatus};
+
+/// Reads the items of Pocket's `ril_export.html`.
+///
+/// The export is a list of links per section, `Unread` and `Read Archive`, each link
+/// carrying `time_added` (unix seconds) and comma separated `tags` attributes. Nothing
+/// is validated here, [`ItemRepository::import_items`](super::ItemRepository::import_items)
+/// reports broken entries.
+pub fn parse_export(html: &str) -> Vec<ImportItem> {
+    let document = Html::parse_document(html);
+    let selector = Selector::parse("h1, a").expect("valid selector");
+
+    let mut status = ItemStatus::Unread;
+    let mut items = Vec::new();
+
+    for element in document.select(&selector) {
+        let text = element.text().collect::<String>().trim().to_string();
+
+        if element.value().name() == "h1" {
+            status = if text.to_lowercase().contains("archive") {
+                ItemStatus::Archived
+            } else {
+                ItemStatus::Unread
+            };
+            continue;
+        }
+
+        let url = element.value().attr("href").unwrap_or_default().to_string();
+
+        // Pocket falls back to the URL for pages it has no title of
+        let title = (!text.is_empty() && text != url).then_some(text);
+
+        let tags = element
+            .value()
+            .attr("tags")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_string)
+            .collect();
+
+        let added_at = element
+            .value()
+            .attr("time_added")
+            .and_then(|value| value.trim().parse::<i64>().ok())
+            .and_then(|seconds| DateTime::from_timestamp(seconds, 0));
+
+        items.push(ImportItem {
+            url,
+            title,
+            tags,
+            status,
+            added_at,
+        });
+    }
+
+    items
+}
@@ -0,0 +1,967 @@
+use sqlx::{postgres::PgRow, PgConnection, PgPool, Postgres, QueryBuilder, Row};
+use uuid::Uuid;
+
+use crate::{
+    errors::{map_sqlx_error, ErrorExt, ErrorKindExt},
+    model::{
+        item::{
+            ImportFailure, ImportItem, ImportReport, Item, ItemCursor, ItemFilter, ItemSort,
+            ItemStatus, ListItems, NewItem, SearchCursor, SearchHit, SearchItems, UpdateItem,
+        },
+        page::{page_size, Page},
+        tag::{normalize_tag_name, normalize_tag_names, Tag, TagUsage},
+    },
+    Error,
+};
+
+use super::{canonical, prepare_import, search, ItemRepository, IMPORT_BATCH_SIZE};
+
+/// Columns of [`Item`], `SELECT *` would also fetch the search vector.
+const ITEM_COLUMNS: &str = "id, user_id, url, canonical_url, title, excerpt, content, status, \
+                            favorite, added_at, read_at, updated_at";
+
+const HEADLINE_OPTIONS: &str = "StartSel=<mark>, StopSel=</mark>, MaxFragments=2, MaxWords=30, \
+                                MinWords=10";
+
+#[derive(Debug, Clone)]
+pub struct PostgresItemRepository {
+    pub pool: PgPool,
+}
+
+impl PostgresItemRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+fn parse_status(value: &str) -> Result<ItemStatus, Error> {
+    value
+        .parse()
+        .map_err(|_| Error::DataIntegrity(format!("unknown item status {value}")))
+}
+
+fn item_from_row(row: &PgRow) -> Result<Item, Error> {
+    Ok(Item {
+        id: row.get("id"),
+        user_id: row.get("user_id"),
+        url: row.get("url"),
+        canonical_url: row.get("canonical_url"),
+        title: row.get("title"),
+        excerpt: row.get("excerpt"),
+        content: row.get("content"),
+        status: parse_status(row.get("status"))?,
+        favorite: row.get("favorite"),
+        added_at: row.get("added_at"),
+        read_at: row.get("read_at"),
+        updated_at: row.get("updated_at"),
+    })
+}
+
+fn push_item_filters(builder: &mut QueryBuilder<'_, Postgres>, filter: &ItemFilter) {
+    if let Some(status) = filter.status {
+        builder.push(" AND status = ");
+        builder.push_bind(status.as_str());
+    }
+
+    if let Some(favorite) = filter.favorite {
+        builder.push(" AND favorite = ");
+        builder.push_bind(favorite);
+    }
+
+    if let Some(tag) = &filter.tag {
+        builder.push(
+            " AND id IN (SELECT it.item_id FROM item_tags it JOIN tags t ON t.id = it.tag_id \
+             WHERE t.name = ",
+        );
+        builder.push_bind(tag.clone());
+        builder.push(")");
+    }
+
+    if let Some(domain) = &filter.domain {
+        builder.push(" AND (domain = ");
+        builder.push_bind(domain.clone());
+        builder.push(" OR right(domain, char_length(");
+        builder.push_bind(domain.clone());
+        builder.push(") + 1) = '.' || ");
+        builder.push_bind(domain.clone());
+        builder.push(")");
+    }
+}
+
+/// Makes sure the item belongs to the user and keeps it from being deleted meanwhile.
+async fn lock_item(conn: &mut PgConnection, user_id: &Uuid, item_id: &Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        r#"SELECT id FROM items WHERE id = $1 AND user_id = $2 FOR SHARE;"#,
+        item_id,
+        user_id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(|err| map_sqlx_error(err, item_id, Error::ReadError))?;
+
+    Ok(())
+}
+
+/// Locks the user against deletion while items are added, deleted users give
+/// [`Error::NotFound`].
+async fn lock_owner(conn: &mut PgConnection, user_id: &Uuid) -> Result<(), Error> {
+    sqlx::query!(
+        r#"SELECT id FROM users WHERE id = $1 AND deleted_at IS NULL FOR SHARE;"#,
+        user_id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(|err| map_sqlx_error(err, user_id, Error::ReadError))?;
+
+    Ok(())
+}
+
+/// Tags the item with normalised `names`, creating the user's tags that don't exist yet.
+async fn tag_item(
+    conn: &mut PgConnection,
+    user_id: &Uuid,
+    item_id: &Uuid,
+    names: &[String],
+) -> Result<(), Error> {
+    let new_ids: Vec<Uuid> = names.iter().map(|_| Uuid::new_v4()).collect();
+
+    sqlx::query!(
+        r#"
+            INSERT INTO tags ( id, user_id, name )
+            SELECT new_tags.id, $2, new_tags.name
+            FROM UNNEST($1::uuid[], $3::varchar[]) AS new_tags ( id, name )
+            ON CONFLICT ( user_id, name ) DO NOTHING;
+        "#,
+        &new_ids,
+        user_id,
+        names
+    )
+    .execute(&mut *conn)
+    .await
+    .map_err(|err| map_sqlx_error(err, item_id, Error::WriteError))?;
+
+    sqlx::query!(
+        r#"
+            INSERT INTO item_tags ( item_id, tag_id )
+            SELECT $1, id FROM tags WHERE user_id = $2 AND name = ANY($3)
+            ON CONFLICT DO NOTHING;
+        "#,
+        item_id,
+        user_id,
+        names
+    )
+    .execute(conn)
+    .await
+    .map_err(|err| map_sqlx_error(err, item_id, Error::WriteError))?;
+
+    Ok(())
+}
+
+async fn fetch_item_tags(conn: &mut PgConnection, item_id: &Uuid) -> Result<Vec<Tag>, Error> {
+    let result = sqlx::query!(
+        r#"
+            SELECT t.*
+            FROM tags t
+            JOIN item_tags it ON it.tag_id = t.id
+            WHERE it.item_id = $1
+            ORDER BY t.name;
+        "#,
+        item_id
+    )
+    .fetch_all(conn)
+    .await
+    .map_err(|err| map_sqlx_error(err, item_id, Error::ReadError))?;
+
+    let tags = result
+        .into_iter()
+        .map(|value| Tag {
+            id: value.id,
+            user_id: value.user_id,
+            name: value.name,
+            created_at: value.created_at,
+        })
+        .collect();
+
+    Ok(tags)
+}
+
+async fn fetch_tag_usage(conn: &mut PgConnection, tag_id: &Uuid) -> Result<TagUsage, Error> {
+    let result = sqlx::query!(
+        r#"
+            SELECT t.id, t.user_id, t.name, t.created_at, COUNT(it.item_id) AS "item_count!"
+            FROM tags t
+            LEFT JOIN item_tags it ON it.tag_id = t.id
+            WHERE t.id = $1
+            GROUP BY t.id;
+        "#,
+        tag_id
+    )
+    .fetch_one(conn)
+    .await
+    .map_err(|err| map_sqlx_error(err, tag_id, Error::ReadError))?;
+
+    Ok(TagUsage {
+        tag: Tag {
+            id: result.id,
+            user_id: result.user_id,
+            name: result.name,
+            created_at: result.created_at,
+        },
+        item_count: result.item_count,
+    })
+}
+
+impl PostgresItemRepository {
+    /// Imports one batch in its own transaction, on error none of it is kept.
+    async fn import_batch(
+        &self,
+        user_id: &Uuid,
+        batch: &[ImportItem],
+    ) -> Result<ImportReport, Error> {
+        let mut report = ImportReport::default();
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        lock_owner(&mut tx, user_id).await?;
+
+        for entry in batch {
+            let (url, canonical_url, tags) = match prepare_import(entry) {
+                Ok(prepared) => prepared,
+                Err(err) => {
+                    report.failed.push(ImportFailure {
+                        url: entry.url.clone(),
+                        reason: err.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            let result = sqlx::query!(
+                r#"
+                    INSERT INTO items (
+                        id, user_id, url, canonical_url, title, status, added_at, read_at
+                    )
+                    VALUES (
+                        $1,
+                        $2,
+                        $3,
+                        $4,
+                        $5,
+                        $6,
+                        COALESCE($7, NOW()),
+                        CASE WHEN $6::varchar = 'archived' THEN COALESCE($7, NOW()) END
+                    )
+                    ON CONFLICT ( user_id, canonical_url ) DO NOTHING
+                    RETURNING
+                        id, user_id, url, canonical_url, title, excerpt, content,
+                        status, favorite, added_at, read_at, updated_at;
+                "#,
+                Uuid::new_v4(),
+                user_id,
+                url.as_str(),
+                canonical_url,
+                entry.title,
+                entry.status.as_str(),
+                entry.added_at
+            )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|err| match err.kind_ext() {
+                ErrorKindExt::ForeignKeyViolation => Error::NotFound(user_id.to_string()),
+                _ => map_sqlx_error(err, &canonical_url, Error::WriteError),
+            })?;
+
+            let Some(result) = result else {
+                report.skipped.push(entry.url.clone());
+                continue;
+            };
+
+            tag_item(&mut tx, user_id, &result.id, &tags).await?;
+
+            report.created.push(Item {
+                id: result.id,
+                user_id: result.user_id,
+                url: result.url,
+                canonical_url: result.canonical_url,
+                title: result.title,
+                excerpt: result.excerpt,
+                content: result.content,
+                status: parse_status(&result.status)?,
+                favorite: result.favorite,
+                added_at: result.added_at,
+                read_at: result.read_at,
+                updated_at: result.updated_at,
+            });
+        }
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(report)
+    }
+}
+
+impl ItemRepository for PostgresItemRepository {
+    async fn get_item(&self, user_id: &Uuid, id: &Uuid) -> Result<Item, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                SELECT
+                    id, user_id, url, canonical_url, title, excerpt, content,
+                    status, favorite, added_at, read_at, updated_at
+                FROM items
+                WHERE id = $1 AND user_id = $2;
+            "#,
+            id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(Item {
+            id: result.id,
+            user_id: result.user_id,
+            url: result.url,
+            canonical_url: result.canonical_url,
+            title: result.title,
+            excerpt: result.excerpt,
+            content: result.content,
+            status: parse_status(&result.status)?,
+            favorite: result.favorite,
+            added_at: result.added_at,
+            read_at: result.read_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn create_item(&self, user_id: &Uuid, new_item: NewItem) -> Result<Item, Error> {
+        let url = canonical::parse_url(&new_item.url)?;
+        let canonical_url = canonical::canonicalize(&url);
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                INSERT INTO items ( id, user_id, url, canonical_url, title, excerpt, content )
+                SELECT $1, id, $3, $4, $5, $6, $7
+                FROM users
+                WHERE id = $2 AND deleted_at IS NULL
+                ON CONFLICT ( user_id, canonical_url ) DO UPDATE
+                SET added_at = NOW(),
+                    title = COALESCE(items.title, EXCLUDED.title),
+                    excerpt = COALESCE(items.excerpt, EXCLUDED.excerpt),
+                    content = COALESCE(items.content, EXCLUDED.content)
+                RETURNING
+                    id, user_id, url, canonical_url, title, excerpt, content,
+                    status, favorite, added_at, read_at, updated_at;
+            "#,
+            Uuid::new_v4(),
+            user_id,
+            url.as_str(),
+            canonical_url,
+            new_item.title,
+            new_item.excerpt,
+            new_item.content
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| match err.kind_ext() {
+            ErrorKindExt::ForeignKeyViolation => Error::NotFound(user_id.to_string()),
+            _ => map_sqlx_error(err, &canonical_url, Error::WriteError),
+        })?
+        // deleted users can't save items
+        .ok_or_else(|| Error::NotFound(user_id.to_string()))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(Item {
+            id: result.id,
+            user_id: result.user_id,
+            url: result.url,
+            canonical_url: result.canonical_url,
+            title: result.title,
+            excerpt: result.excerpt,
+            content: result.content,
+            status: parse_status(&result.status)?,
+            favorite: result.favorite,
+            added_at: result.added_at,
+            read_at: result.read_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn update_item(
+        &self,
+        user_id: &Uuid,
+        id: &Uuid,
+        update: UpdateItem,
+    ) -> Result<Item, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let current = sqlx::query!(
+            r#"SELECT updated_at FROM items WHERE id = $1 AND user_id = $2 FOR UPDATE;"#,
+            id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        if let Some(expected) = update.expected_updated_at {
+            if expected != current.updated_at {
+                return Err(Error::Conflict(id.to_string()));
+            }
+        }
+
+        // SET sees the old row, so read_at is only stamped when the item gets archived
+        let result = sqlx::query!(
+            r#"
+                UPDATE items
+                SET title = COALESCE($2, title),
+                    excerpt = COALESCE($3, excerpt),
+                    content = COALESCE($6, content),
+                    status = COALESCE($4, status),
+                    favorite = COALESCE($5, favorite),
+                    read_at = CASE
+                        WHEN $4 = 'archived' AND status <> 'archived' THEN NOW()
+                        WHEN $4 = 'unread' THEN NULL
+                        ELSE read_at
+                    END
+                WHERE id = $1
+                RETURNING
+                    id, user_id, url, canonical_url, title, excerpt, content,
+                    status, favorite, added_at, read_at, updated_at;
+            "#,
+            id,
+            update.title,
+            update.excerpt,
+            update.status.map(|status| status.as_str()),
+            update.favorite,
+            update.content
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(Item {
+            id: result.id,
+            user_id: result.user_id,
+            url: result.url,
+            canonical_url: result.canonical_url,
+            title: result.title,
+            excerpt: result.excerpt,
+            content: result.content,
+            status: parse_status(&result.status)?,
+            favorite: result.favorite,
+            added_at: result.added_at,
+            read_at: result.read_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn delete_item(&self, user_id: &Uuid, id: &Uuid) -> Result<Item, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                DELETE FROM items
+                WHERE id = $1 AND user_id = $2
+                RETURNING
+                    id, user_id, url, canonical_url, title, excerpt, content,
+                    status, favorite, added_at, read_at, updated_at;
+            "#,
+            id,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(Item {
+            id: result.id,
+            user_id: result.user_id,
+            url: result.url,
+            canonical_url: result.canonical_url,
+            title: result.title,
+            excerpt: result.excerpt,
+            content: result.content,
+            status: parse_status(&result.status)?,
+            favorite: result.favorite,
+            added_at: result.added_at,
+            read_at: result.read_at,
+            updated_at: result.updated_at,
+        })
+    }
+
+    async fn list_items(&self, user_id: &Uuid, query: ListItems) -> Result<Page<Item>, Error> {
+        let size = page_size(query.limit);
+        let filter = query.filter.normalized()?;
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(ItemCursor::decode)
+            .transpose()?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let mut builder =
+            QueryBuilder::new(format!("SELECT {ITEM_COLUMNS} FROM items WHERE user_id = "));
+        builder.push_bind(user_id);
+
+        push_item_filters(&mut builder, &filter);
+
+        if let Some(cursor) = cursor {
+            builder.push(" AND (added_at, id)");
+            builder.push(if query.sort.is_desc() { " < " } else { " > " });
+            builder.push("(");
+            builder.push_bind(cursor.added_at);
+            builder.push(", ");
+            builder.push_bind(cursor.id);
+            builder.push(")");
+        }
+
+        builder.push(match query.sort {
+            ItemSort::AddedAtDesc => " ORDER BY added_at DESC, id DESC",
+            ItemSort::AddedAtAsc => " ORDER BY added_at ASC, id ASC",
+        });
+
+        // one extra row tells if there is a next page
+        builder.push(" LIMIT ");
+        builder.push_bind(i64::from(size) + 1);
+
+        let rows = builder
+            .build()
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, user_id, Error::ReadError))?;
+
+        let total = if query.with_total {
+            let mut builder = QueryBuilder::new("SELECT COUNT(*) FROM items WHERE user_id = ");
+            builder.push_bind(user_id);
+
+            push_item_filters(&mut builder, &filter);
+
+            let row = builder
+                .build()
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|err| map_sqlx_error(err, user_id, Error::ReadError))?;
+
+            Some(row.get::<i64, _>(0))
+        } else {
+            None
+        };
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        let mut items = rows
+            .iter()
+            .map(item_from_row)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let next_cursor = if items.len() > size as usize {
+            items.truncate(size as usize);
+            items.last().map(|item| ItemCursor::after(item).encode())
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items,
+            next_cursor,
+            total,
+        })
+    }
+
+    async fn search_items(
+        &self,
+        user_id: &Uuid,
+        query: SearchItems,
+    ) -> Result<Page<SearchHit>, Error> {
+        let terms = search::parse_query(&query.query)?;
+        let size = page_size(query.limit);
+        let filter = query.filter.normalized()?;
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(SearchCursor::decode)
+            .transpose()?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        // headlines are slow, so they are only made for the rows of the page
+        let mut builder = QueryBuilder::new(format!(
+            "SELECT {ITEM_COLUMNS}, rank, \
+             NULLIF(ts_headline('english'::regconfig, concat_ws(' ', excerpt, content), query, \
+             '{HEADLINE_OPTIONS}'), '') AS snippet \
+             FROM (SELECT {ITEM_COLUMNS}, ts_rank(search_vector, query) AS rank, query \
+             FROM items, to_tsquery('english'::regconfig, "
+        ));
+        builder.push_bind(search::to_tsquery(&terms));
+        builder.push(") AS query WHERE search_vector @@ query AND user_id = ");
+        builder.push_bind(user_id);
+
+        push_item_filters(&mut builder, &filter);
+
+        if let Some(cursor) = cursor {
+            builder.push(" AND (ts_rank(search_vector, query), id) < (");
+            builder.push_bind(cursor.rank);
+            builder.push(", ");
+            builder.push_bind(cursor.id);
+            builder.push(")");
+        }
+
+        // one extra row tells if there is a next page
+        builder.push(" ORDER BY rank DESC, id DESC LIMIT ");
+        builder.push_bind(i64::from(size) + 1);
+        builder.push(") AS hits ORDER BY rank DESC, id DESC");
+
+        let rows = builder
+            .build()
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, user_id, Error::ReadError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        let mut hits = rows
+            .iter()
+            .map(|row| {
+                Ok(SearchHit {
+                    item: item_from_row(row)?,
+                    rank: row.get("rank"),
+                    snippet: row.get("snippet"),
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let next_cursor = if hits.len() > size as usize {
+            hits.truncate(size as usize);
+            hits.last().map(|hit| SearchCursor::after(hit).encode())
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items: hits,
+            next_cursor,
+            total: None,
+        })
+    }
+
+    async fn import_items(
+        &self,
+        user_id: &Uuid,
+        items: Vec<ImportItem>,
+    ) -> Result<ImportReport, Error> {
+        // unknown and deleted users fail the whole import, later batches only report it
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        lock_owner(&mut tx, user_id).await?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        let mut report = ImportReport::default();
+
+        for batch in items.chunks(IMPORT_BATCH_SIZE) {
+            match self.import_batch(user_id, batch).await {
+                Ok(batch_report) => {
+                    report.created.extend(batch_report.created);
+                    report.skipped.extend(batch_report.skipped);
+                    report.failed.extend(batch_report.failed);
+                }
+                // nothing of the batch was kept, the next ones may still go through
+                Err(err) => {
+                    let reason = err.to_string();
+
+                    report.failed.extend(batch.iter().map(|entry| {
+                        ImportFailure {
+                            url: entry.url.clone(),
+                            reason: prepare_import(entry)
+                                .err()
+                                .map_or_else(|| reason.clone(), |err| err.to_string()),
+                        }
+                    }));
+                }
+            }
+        }
+
+        Ok(report)
+    }
+
+    async fn item_tags(&self, user_id: &Uuid, item_id: &Uuid) -> Result<Vec<Tag>, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        lock_item(&mut tx, user_id, item_id).await?;
+        let tags = fetch_item_tags(&mut tx, item_id).await?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(tags)
+    }
+
+    async fn add_tags(
+        &self,
+        user_id: &Uuid,
+        item_id: &Uuid,
+        names: Vec<String>,
+    ) -> Result<Vec<Tag>, Error> {
+        let names = normalize_tag_names(&names)?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        lock_item(&mut tx, user_id, item_id).await?;
+        tag_item(&mut tx, user_id, item_id, &names).await?;
+
+        let tags = fetch_item_tags(&mut tx, item_id).await?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(tags)
+    }
+
+    async fn remove_tags(
+        &self,
+        user_id: &Uuid,
+        item_id: &Uuid,
+        names: Vec<String>,
+    ) -> Result<Vec<Tag>, Error> {
+        let names = normalize_tag_names(&names)?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        lock_item(&mut tx, user_id, item_id).await?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM item_tags it
+                USING tags t
+                WHERE it.tag_id = t.id AND it.item_id = $1 AND t.user_id = $2 AND t.name = ANY($3);
+            "#,
+            item_id,
+            user_id,
+            &names
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, item_id, Error::WriteError))?;
+
+        let tags = fetch_item_tags(&mut tx, item_id).await?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(tags)
+    }
+
+    async fn list_tags(&self, user_id: &Uuid) -> Result<Vec<TagUsage>, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                SELECT t.id, t.user_id, t.name, t.created_at, COUNT(it.item_id) AS "item_count!"
+                FROM tags t
+                LEFT JOIN item_tags it ON it.tag_id = t.id
+                WHERE t.user_id = $1
+                GROUP BY t.id
+                ORDER BY t.name;
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user_id, Error::ReadError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        let tags = result
+            .into_iter()
+            .map(|value| TagUsage {
+                tag: Tag {
+                    id: value.id,
+                    user_id: value.user_id,
+                    name: value.name,
+                    created_at: value.created_at,
+                },
+                item_count: value.item_count,
+            })
+            .collect();
+
+        Ok(tags)
+    }
+
+    async fn rename_tag(&self, user_id: &Uuid, name: &str, new_name: &str) -> Result<Tag, Error> {
+        let name = normalize_tag_name(name)?;
+        let new_name = normalize_tag_name(new_name)?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE tags
+                SET name = $3
+                WHERE user_id = $1 AND name = $2
+                RETURNING *;
+            "#,
+            user_id,
+            name,
+            new_name
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, &new_name, Error::WriteError))?
+        .ok_or_else(|| Error::NotFound(name.clone()))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(Tag {
+            id: result.id,
+            user_id: result.user_id,
+            name: result.name,
+            created_at: result.created_at,
+        })
+    }
+
+    async fn merge_tags(
+        &self,
+        user_id: &Uuid,
+        source: &str,
+        target: &str,
+    ) -> Result<TagUsage, Error> {
+        let source = normalize_tag_name(source)?;
+        let target = normalize_tag_name(target)?;
+
+        if source == target {
+            return Err(Error::InvalidArgument(format!(
+                "cannot merge tag {source} into itself"
+            )));
+        }
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        // locked in a stable order so concurrent merges of the same pair can't deadlock
+        let result = sqlx::query!(
+            r#"
+                SELECT id, name FROM tags
+                WHERE user_id = $1 AND name = ANY($2)
+                ORDER BY id
+                FOR UPDATE;
+            "#,
+            user_id,
+            &[source.clone(), target.clone()]
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, &source, Error::ReadError))?;
+
+        let find = |name: &str| {
+            result
+                .iter()
+                .find(|value| value.name == name)
+                .map(|value| value.id)
+                .ok_or_else(|| Error::NotFound(name.to_string()))
+        };
+        let source_id = find(&source)?;
+        let target_id = find(&target)?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO item_tags ( item_id, tag_id )
+                SELECT item_id, $2 FROM item_tags WHERE tag_id = $1
+                ON CONFLICT DO NOTHING;
+            "#,
+            source_id,
+            target_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, &target, Error::WriteError))?;
+
+        sqlx::query!(r#"DELETE FROM tags WHERE id = $1;"#, source_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, &source, Error::WriteError))?;
+
+        let usage = fetch_tag_usage(&mut tx, &target_id).await?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(usage)
+    }
+}
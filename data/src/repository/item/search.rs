@@ -0,0 +1,109 @@
+use crate::Error;
+
+/// Part of a parsed search query, every term must match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchTerm {
+    /// `rust`, or `rus*` for words starting with it.
+    Word { text: String, prefix: bool },
+    /// `"async rust"`, the words next to each other in this order.
+    Phrase(Vec<String>),
+}
+
+impl SearchTerm {
+    /// Text the term stands for, what the in-memory backend looks for.
+    pub fn text(&self) -> String {
+        match self {
+            Self::Word { text, .. } => text.clone(),
+            Self::Phrase(words) => words.join(" "),
+        }
+    }
+}
+
+/// Postgres' `english` stop words (`tsearch_data/english.stop`), which it leaves out of
+/// queries, so the in-memory backend ignores them too.
+const STOP_WORDS: &str = "i me my myself we our ours ourselves you your yours yourself yourselves he him \
+                          his himself she her hers herself it its itself they them their theirs \
+                          themselves what which who whom this that these those am is are was were be been \
+                          being have has had having do does did doing a an the and but if or because as \
+                          until while of at by for with about against between into through during before \
+                          after above below to from up down in out on off over under again further then \
+                          once here there when where why how all any both each few more most other some \
+                          such no nor not only own same so than too very s t can will just don should now";
+
+fn is_stop_word(word: &str) -> bool {
+    STOP_WORDS
+        .split_whitespace()
+        .any(|stop_word| stop_word == word)
+}
+
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Parses a search box query: words, `"quoted phrases"` and `prefix*` words.
+///
+/// Only letters and digits are kept, so nothing a user types can break the tsquery
+/// built by [`to_tsquery`]. Stop words like "the" are dropped unless part of a phrase, a
+/// query left without any word gives [`Error::InvalidArgument`].
+pub fn parse_query(query: &str) -> Result<Vec<SearchTerm>, Error> {
+    let mut terms = Vec::new();
+
+    // odd parts are quoted, an unclosed quote runs to the end
+    for (index, part) in query.split('"').enumerate() {
+        if index % 2 == 1 {
+            let mut phrase = words(part);
+            match phrase.len() {
+                _ if phrase.iter().all(|word| is_stop_word(word)) => {}
+                1 => terms.push(SearchTerm::Word {
+                    text: phrase.remove(0),
+                    prefix: false,
+                }),
+                _ => terms.push(SearchTerm::Phrase(phrase)),
+            }
+            continue;
+        }
+
+        for token in part.split_whitespace() {
+            let token_words = words(token);
+            let last = token_words.len().saturating_sub(1);
+
+            for (position, text) in token_words.into_iter().enumerate() {
+                if is_stop_word(&text) {
+                    continue;
+                }
+
+                terms.push(SearchTerm::Word {
+                    text,
+                    prefix: position == last && token.ends_with('*'),
+                });
+            }
+        }
+    }
+
+    if terms.is_empty() {
+        return Err(Error::InvalidArgument(format!(
+            "empty search query: {query}"
+        )));
+    }
+
+    Ok(terms)
+}
+
+/// Postgres `tsquery` text matching all `terms`, to pass to `to_tsquery`.
+pub fn to_tsquery(terms: &[SearchTerm]) -> String {
+    terms
+        .iter()
+        .map(|term| match term {
+            SearchTerm::Word { text, prefix: true } => format!("{text}:*"),
+            SearchTerm::Word {
+                text,
+                prefix: false,
+            } => text.clone(),
+            SearchTerm::Phrase(words) => format!("({})", words.join(" <-> ")),
+        })
+        .collect::<Vec<_>>()
+        .join(" & ")
+}
@@ -0,0 +1,128 @@
+use std::future::Future;
+
+use uuid::Uuid;
+
+use crate::{
+    connect,
+    model::{role::Role, user::User},
+    settings::PostgresSettings,
+    Error,
+};
+
+use self::{mock::MockRoleRepository, postgres::PostgresRoleRepository};
+use super::user::AnyUserRepository;
+
+pub mod mock;
+pub mod postgres;
+
+#[derive(Debug, Clone)]
+pub enum RoleRepositorySettings {
+    Mock,
+    Postgres(PostgresSettings),
+}
+
+pub trait RoleRepository {
+    /// All roles with their permissions, by name.
+    fn list_roles(&self) -> impl Future<Output = Result<Vec<Role>, Error>> + Send;
+
+    fn user_roles(&self, user_id: &Uuid) -> impl Future<Output = Result<Vec<Role>, Error>> + Send;
+
+    /// Users holding the role, oldest first, deleted users are left out.
+    fn users_with_role(&self, role: &str) -> impl Future<Output = Result<Vec<User>, Error>> + Send;
+
+    /// Unknown and deleted users and unknown roles give [`Error::NotFound`], roles the user
+    /// holds already [`Error::AlreadyExists`].
+    fn assign_role(
+        &self,
+        user_id: &Uuid,
+        role: &str,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Revoking the admin role of the last active admin gives [`Error::InvalidArgument`],
+    /// someone has to be left to assign roles.
+    fn revoke_role(
+        &self,
+        user_id: &Uuid,
+        role: &str,
+    ) -> impl Future<Output = Result<(), Error>> + Send;
+
+    /// Whether any role of the user grants `permission`, unknown and deleted users have none.
+    fn has_permission(
+        &self,
+        user_id: &Uuid,
+        permission: &str,
+    ) -> impl Future<Output = Result<bool, Error>> + Send;
+}
+
+/// [`RoleRepository`] backend picked at runtime from [`RoleRepositorySettings`].
+#[derive(Debug, Clone)]
+pub enum AnyRoleRepository {
+    Mock(MockRoleRepository),
+    Postgres(PostgresRoleRepository),
+}
+
+impl AnyRoleRepository {
+    /// The mock backend serves the users of the mock [`AnyUserRepository`], other user
+    /// backends give [`Error::InvalidArgument`].
+    pub async fn from_settings(
+        settings: &RoleRepositorySettings,
+        users: &AnyUserRepository,
+    ) -> Result<Self, Error> {
+        match (settings, users) {
+            (RoleRepositorySettings::Mock, AnyUserRepository::Mock(users)) => {
+                Ok(Self::Mock(MockRoleRepository::new(users.clone())))
+            }
+            (RoleRepositorySettings::Mock, _) => Err(Error::InvalidArgument(
+                "mock roles need mock users".to_string(),
+            )),
+            (RoleRepositorySettings::Postgres(pg_settings), _) => {
+                let pool = connect(pg_settings).await?;
+                Ok(Self::Postgres(PostgresRoleRepository::new(pool)))
+            }
+        }
+    }
+}
+
+impl RoleRepository for AnyRoleRepository {
+    async fn list_roles(&self) -> Result<Vec<Role>, Error> {
+        match self {
+            Self::Mock(repo) => repo.list_roles().await,
+            Self::Postgres(repo) => repo.list_roles().await,
+        }
+    }
+
+    async fn user_roles(&self, user_id: &Uuid) -> Result<Vec<Role>, Error> {
+        match self {
+            Self::Mock(repo) => repo.user_roles(user_id).await,
+            Self::Postgres(repo) => repo.user_roles(user_id).await,
+        }
+    }
+
+    async fn users_with_role(&self, role: &str) -> Result<Vec<User>, Error> {
+        match self {
+            Self::Mock(repo) => repo.users_with_role(role).await,
+            Self::Postgres(repo) => repo.users_with_role(role).await,
+        }
+    }
+
+    async fn assign_role(&self, user_id: &Uuid, role: &str) -> Result<(), Error> {
+        match self {
+            Self::Mock(repo) => repo.assign_role(user_id, role).await,
+            Self::Postgres(repo) => repo.assign_role(user_id, role).await,
+        }
+    }
+
+    async fn revoke_role(&self, user_id: &Uuid, role: &str) -> Result<(), Error> {
+        match self {
+            Self::Mock(repo) => repo.revoke_role(user_id, role).await,
+            Self::Postgres(repo) => repo.revoke_role(user_id, role).await,
+        }
+    }
+
+    async fn has_permission(&self, user_id: &Uuid, permission: &str) -> Result<bool, Error> {
+        match self {
+            Self::Mock(repo) => repo.has_permission(user_id, permission).await,
+            Self::Postgres(repo) => repo.has_permission(user_id, permission).await,
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, LazyLock},
+};
+
+use chrono::{SubsecRound, Utc};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    model::{
+        role::{permission, Role, ADMIN_ROLE},
+        user::User,
+    },
+    repository::user::mock::{active_user, MockUserRepository},
+    Error,
+};
+
+use super::RoleRepository;
+
+/// Roles and permissions seeded by the migrations, by name.
+static ROLES: LazyLock<Vec<Role>> = LazyLock::new(|| {
+    let created_at = Utc::now().trunc_subsecs(6);
+    let role = |name: &str, description: &str, permissions: &[&str]| {
+        let mut permissions: Vec<String> = permissions.iter().map(|p| p.to_string()).collect();
+        permissions.sort();
+
+        Role {
+            name: name.to_string(),
+            description: description.to_string(),
+            permissions,
+            created_at,
+        }
+    };
+
+    vec![
+        role(
+            ADMIN_ROLE,
+            "Everything",
+            &[
+                permission::USERS_READ,
+                permission::USERS_WRITE,
+                permission::USERS_DELETE,
+                permission::ROLES_ASSIGN,
+                permission::ITEMS_MODERATE,
+                permission::AUDIT_READ,
+            ],
+        ),
+        role(
+            "auditor",
+            "Read-only access for reviews",
+            &[permission::USERS_READ, permission::AUDIT_READ],
+        ),
+        role(
+            "moderator",
+            "Keeps content in check",
+            &[permission::USERS_READ, permission::ITEMS_MODERATE],
+        ),
+        role(
+            "support",
+            "Helps users with their accounts",
+            &[permission::USERS_READ, permission::USERS_WRITE],
+        ),
+    ]
+});
+
+pub(crate) fn find_role(name: &str) -> Option<&'static Role> {
+    ROLES.iter().find(|role| role.name == name)
+}
+
+/// Whether `user_id` is the only active user holding [`ADMIN_ROLE`].
+pub(crate) fn is_last_admin(
+    users: &HashMap<Uuid, User>,
+    user_roles: &HashSet<(Uuid, String)>,
+    user_id: &Uuid,
+) -> bool {
+    let admins: Vec<&Uuid> = user_roles
+        .iter()
+        .filter(|(_, name)| name == ADMIN_ROLE)
+        .map(|(id, _)| id)
+        .filter(|id| users.get(id).is_some_and(|user| user.deleted_at.is_none()))
+        .collect();
+
+    admins.len() == 1 && admins[0] == user_id
+}
+
+/// In-memory [`RoleRepository`] used where a database is not available (e.g. service tests).
+///
+/// Knows the roles the migrations seed and shares the role assignments with a
+/// [`MockUserRepository`], so its users can be listed and imported by role.
+#[derive(Debug, Clone)]
+pub struct MockRoleRepository {
+    users: Arc<RwLock<HashMap<Uuid, User>>>,
+    user_roles: Arc<RwLock<HashSet<(Uuid, String)>>>,
+}
+
+impl MockRoleRepository {
+    pub fn new(users: MockUserRepository) -> Self {
+        Self {
+            user_roles: users.user_roles(),
+            users: users.users(),
+        }
+    }
+}
+
+impl RoleRepository for MockRoleRepository {
+    async fn list_roles(&self) -> Result<Vec<Role>, Error> {
+        Ok(ROLES.clone())
+    }
+
+    async fn user_roles(&self, user_id: &Uuid) -> Result<Vec<Role>, Error> {
+        let user_roles = self.user_roles.read().await;
+
+        Ok(ROLES
+            .iter()
+            .filter(|role| user_roles.contains(&(*user_id, role.name.clone())))
+            .cloned()
+            .collect())
+    }
+
+    async fn users_with_role(&self, role: &str) -> Result<Vec<User>, Error> {
+        let users = self.users.read().await;
+        let user_roles = self.user_roles.read().await;
+
+        let mut result: Vec<User> = user_roles
+            .iter()
+            .filter(|(_, name)| name == role)
+            .filter_map(|(user_id, _)| users.get(user_id))
+            .filter(|user| user.deleted_at.is_none())
+            .cloned()
+            .collect();
+
+        result.sort_by_key(|user| (user.created_at, user.id));
+
+        Ok(result)
+    }
+
+    async fn assign_role(&self, user_id: &Uuid, role: &str) -> Result<(), Error> {
+        find_role(role).ok_or_else(|| Error::NotFound(role.to_string()))?;
+
+        // deleted users don't get new roles
+        let users = self.users.read().await;
+
+        if users
+            .get(user_id)
+            .is_none_or(|user| user.deleted_at.is_some())
+        {
+            return Err(Error::NotFound(user_id.to_string()));
+        }
+
+        if !self
+            .user_roles
+            .write()
+            .await
+            .insert((*user_id, role.to_string()))
+        {
+            return Err(Error::AlreadyExists(format!("{role} of {user_id}")));
+        }
+
+        Ok(())
+    }
+
+    async fn revoke_role(&self, user_id: &Uuid, role: &str) -> Result<(), Error> {
+        let users = self.users.read().await;
+        let mut user_roles = self.user_roles.write().await;
+
+        if role == ADMIN_ROLE && is_last_admin(&users, &user_roles, user_id) {
+            return Err(Error::InvalidArgument(format!(
+                "{user_id} is the last {ADMIN_ROLE}"
+            )));
+        }
+
+        if !user_roles.remove(&(*user_id, role.to_string())) {
+            return Err(Error::NotFound(format!("{role} of {user_id}")));
+        }
+
+        Ok(())
+    }
+
+    async fn has_permission(&self, user_id: &Uuid, permission: &str) -> Result<bool, Error> {
+        if active_user(&self.users, user_id).await.is_none() {
+            return Ok(false);
+        }
+
+        let user_roles = self.user_roles.read().await;
+
+        Ok(ROLES.iter().any(|role| {
+            user_roles.contains(&(*user_id, role.name.clone()))
+                && role.permissions.iter().any(|granted| granted == permission)
+        }))
+    }
+}
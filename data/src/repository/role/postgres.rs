@@ -0,0 +1,280 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    errors::{map_sqlx_error, ErrorExt, ErrorKindExt},
+    model::{
+        role::{Role, ADMIN_ROLE},
+        user::User,
+    },
+    Error,
+};
+
+use super::RoleRepository;
+
+#[derive(Debug, Clone)]
+pub struct PostgresRoleRepository {
+    pub pool: PgPool,
+}
+
+impl PostgresRoleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+impl RoleRepository for PostgresRoleRepository {
+    async fn list_roles(&self) -> Result<Vec<Role>, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                SELECT
+                    r.name, r.description, r.created_at,
+                    COALESCE(
+                        ARRAY_AGG(rp.permission ORDER BY rp.permission)
+                            FILTER (WHERE rp.permission IS NOT NULL),
+                        '{}'
+                    ) AS "permissions!"
+                FROM roles r
+                LEFT JOIN role_permissions rp ON rp.role = r.name
+                GROUP BY r.name
+                ORDER BY r.name;
+            "#
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "roles", Error::ReadError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        let roles: Vec<Role> = result
+            .into_iter()
+            .map(|value| Role {
+                name: value.name,
+                description: value.description,
+                permissions: value.permissions,
+                created_at: value.created_at,
+            })
+            .collect();
+
+        Ok(roles)
+    }
+
+    async fn user_roles(&self, user_id: &Uuid) -> Result<Vec<Role>, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                SELECT
+                    r.name, r.description, r.created_at,
+                    COALESCE(
+                        ARRAY_AGG(rp.permission ORDER BY rp.permission)
+                            FILTER (WHERE rp.permission IS NOT NULL),
+                        '{}'
+                    ) AS "permissions!"
+                FROM user_roles ur
+                JOIN roles r ON r.name = ur.role
+                LEFT JOIN role_permissions rp ON rp.role = r.name
+                WHERE ur.user_id = $1
+                GROUP BY r.name
+                ORDER BY r.name;
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user_id, Error::ReadError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        let roles: Vec<Role> = result
+            .into_iter()
+            .map(|value| Role {
+                name: value.name,
+                description: value.description,
+                permissions: value.permissions,
+                created_at: value.created_at,
+            })
+            .collect();
+
+        Ok(roles)
+    }
+
+    async fn users_with_role(&self, role: &str) -> Result<Vec<User>, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                SELECT u.*
+                FROM user_roles ur
+                JOIN users u ON u.id = ur.user_id
+                WHERE ur.role = $1 AND u.deleted_at IS NULL
+                ORDER BY u.created_at, u.id;
+            "#,
+            role
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, role, Error::ReadError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        let users: Vec<User> = result
+            .into_iter()
+            .map(|value| User {
+                id: value.id,
+                email: value.email,
+                hash: value.hash,
+                created_at: value.created_at,
+                updated_at: value.updated_at,
+                email_verified_at: value.email_verified_at,
+                deleted_at: value.deleted_at,
+            })
+            .collect();
+
+        Ok(users)
+    }
+
+    async fn assign_role(&self, user_id: &Uuid, role: &str) -> Result<(), Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        // both references are foreign keys, look the role up to tell which one is missing
+        let role_exists = sqlx::query_scalar!(
+            r#"SELECT EXISTS (SELECT 1 FROM roles WHERE name = $1) AS "exists!";"#,
+            role
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, role, Error::ReadError))?;
+
+        if !role_exists {
+            return Err(Error::NotFound(role.to_string()));
+        }
+
+        let result = sqlx::query!(
+            r#"
+                INSERT INTO user_roles ( user_id, role )
+                SELECT id, $2
+                FROM users
+                WHERE id = $1 AND deleted_at IS NULL;
+            "#,
+            user_id,
+            role
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| match err.kind_ext() {
+            ErrorKindExt::ForeignKeyViolation => Error::NotFound(user_id.to_string()),
+            _ => map_sqlx_error(err, format!("{role} of {user_id}"), Error::WriteError),
+        })?;
+
+        // deleted users don't get new roles
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound(user_id.to_string()));
+        }
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn revoke_role(&self, user_id: &Uuid, role: &str) -> Result<(), Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        if role == ADMIN_ROLE {
+            // locking every admin row keeps two admins from revoking each other at once
+            let admins = sqlx::query_scalar!(
+                r#"
+                    SELECT ur.user_id
+                    FROM user_roles ur
+                    JOIN users u ON u.id = ur.user_id
+                    WHERE ur.role = $1 AND u.deleted_at IS NULL
+                    FOR UPDATE OF ur;
+                "#,
+                ADMIN_ROLE
+            )
+            .fetch_all(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, ADMIN_ROLE, Error::ReadError))?;
+
+            if admins.len() == 1 && admins[0] == *user_id {
+                return Err(Error::InvalidArgument(format!(
+                    "{user_id} is the last {ADMIN_ROLE}"
+                )));
+            }
+        }
+
+        let result = sqlx::query!(
+            r#"DELETE FROM user_roles WHERE user_id = $1 AND role = $2;"#,
+            user_id,
+            role
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user_id, Error::WriteError))?;
+
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFound(format!("{role} of {user_id}")));
+        }
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn has_permission(&self, user_id: &Uuid, permission: &str) -> Result<bool, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query_scalar!(
+            r#"
+                SELECT EXISTS (
+                    SELECT 1
+                    FROM user_roles ur
+                    JOIN users u ON u.id = ur.user_id
+                    JOIN role_permissions rp ON rp.role = ur.role
+                    WHERE ur.user_id = $1 AND rp.permission = $2 AND u.deleted_at IS NULL
+                ) AS "exists!";
+            "#,
+            user_id,
+            permission
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user_id, Error::ReadError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(result)
+    }
+}
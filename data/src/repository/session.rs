@@ -0,0 +1,168 @@
+use std::future::Future;
+
+use chrono::Duration;
+use uuid::Uuid;
+
+use crate::{
+    connect,
+    model::session::{IssuedSession, Session},
+    settings::PostgresSettings,
+    Error,
+};
+
+use self::{mock::MockSessionRepository, postgres::PostgresSessionRepository};
+use super::user::{AnyUserRepository, UserRepository};
+
+pub mod mock;
+pub mod postgres;
+
+#[derive(Debug, Clone)]
+pub enum SessionRepositorySettings {
+    Mock,
+    Postgres(PostgresSettings),
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionSettings {
+    /// Session expires when not used for this long.
+    pub idle_ttl: Duration,
+    /// Session expires this long after login, no matter how often it's used.
+    pub absolute_ttl: Duration,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            idle_ttl: Duration::days(14),
+            absolute_ttl: Duration::days(90),
+        }
+    }
+}
+
+pub trait SessionRepository {
+    /// Starts a session for a user whose credentials were already verified. Unknown and
+    /// deleted users give [`Error::NotFound`].
+    fn create_session(
+        &self,
+        user_id: &Uuid,
+    ) -> impl Future<Output = Result<IssuedSession, Error>> + Send;
+
+    /// Verifies the credentials with `users` and starts a session on success.
+    fn login<U>(
+        &self,
+        users: &U,
+        email: &str,
+        password: &str,
+    ) -> impl Future<Output = Result<IssuedSession, Error>> + Send
+    where
+        Self: Sync,
+        U: UserRepository + Sync,
+    {
+        async move {
+            let user = users.verify_user_password(email, password).await?;
+            self.create_session(&user.id).await
+        }
+    }
+
+    /// Resolves a session token and marks the session as used.
+    ///
+    /// Unknown, revoked and expired sessions give [`Error::InvalidToken`].
+    fn validate_session(&self, token: &str) -> impl Future<Output = Result<Session, Error>> + Send;
+
+    /// Exchanges a refresh token for a new pair of tokens, the old ones stop working.
+    ///
+    /// Presenting an already used refresh token revokes the whole session and gives
+    /// [`Error::TokenReuse`].
+    fn refresh_session(
+        &self,
+        refresh_token: &str,
+    ) -> impl Future<Output = Result<IssuedSession, Error>> + Send;
+
+    fn revoke_session(&self, id: &Uuid) -> impl Future<Output = Result<Session, Error>> + Send;
+
+    /// Revokes every active session of a user, returns how many were revoked.
+    fn revoke_user_sessions(
+        &self,
+        user_id: &Uuid,
+    ) -> impl Future<Output = Result<u64, Error>> + Send;
+
+    /// Removes revoked and expired sessions, returns how many were removed.
+    fn delete_expired_sessions(&self) -> impl Future<Output = Result<u64, Error>> + Send;
+}
+
+/// [`SessionRepository`] backend picked at runtime from [`SessionRepositorySettings`].
+#[derive(Debug, Clone)]
+pub enum AnySessionRepository {
+    Mock(MockSessionRepository),
+    Postgres(PostgresSessionRepository),
+}
+
+impl AnySessionRepository {
+    /// The mock backend serves the users of the mock [`AnyUserRepository`], other user
+    /// backends give [`Error::InvalidArgument`].
+    pub async fn from_settings(
+        settings: &SessionRepositorySettings,
+        users: &AnyUserRepository,
+        session_settings: SessionSettings,
+    ) -> Result<Self, Error> {
+        match (settings, users) {
+            (SessionRepositorySettings::Mock, AnyUserRepository::Mock(users)) => Ok(Self::Mock(
+                MockSessionRepository::new(users.clone(), session_settings),
+            )),
+            (SessionRepositorySettings::Mock, _) => Err(Error::InvalidArgument(
+                "mock sessions need mock users".to_string(),
+            )),
+            (SessionRepositorySettings::Postgres(pg_settings), _) => {
+                let pool = connect(pg_settings).await?;
+                Ok(Self::Postgres(PostgresSessionRepository::new(
+                    pool,
+                    session_settings,
+                )))
+            }
+        }
+    }
+}
+
+impl SessionRepository for AnySessionRepository {
+    async fn create_session(&self, user_id: &Uuid) -> Result<IssuedSession, Error> {
+        match self {
+            Self::Mock(repo) => repo.create_session(user_id).await,
+            Self::Postgres(repo) => repo.create_session(user_id).await,
+        }
+    }
+
+    async fn validate_session(&self, token: &str) -> Result<Session, Error> {
+        match self {
+            Self::Mock(repo) => repo.validate_session(token).await,
+            Self::Postgres(repo) => repo.validate_session(token).await,
+        }
+    }
+
+    async fn refresh_session(&self, refresh_token: &str) -> Result<IssuedSession, Error> {
+        match self {
+            Self::Mock(repo) => repo.refresh_session(refresh_token).await,
+            Self::Postgres(repo) => repo.refresh_session(refresh_token).await,
+        }
+    }
+
+    async fn revoke_session(&self, id: &Uuid) -> Result<Session, Error> {
+        match self {
+            Self::Mock(repo) => repo.revoke_session(id).await,
+            Self::Postgres(repo) => repo.revoke_session(id).await,
+        }
+    }
+
+    async fn revoke_user_sessions(&self, user_id: &Uuid) -> Result<u64, Error> {
+        match self {
+            Self::Mock(repo) => repo.revoke_user_sessions(user_id).await,
+            Self::Postgres(repo) => repo.revoke_user_sessions(user_id).await,
+        }
+    }
+
+    async fn delete_expired_sessions(&self) -> Result<u64, Error> {
+        match self {
+            Self::Mock(repo) => repo.delete_expired_sessions().await,
+            Self::Postgres(repo) => repo.delete_expired_sessions().await,
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use std::{collections::HashMap, sync::Arc};
+
+use chrono::{DateTime, SubsecRound, Utc};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    model::session::{IssuedSession, Session},
+    model::user::User,
+    repository::user::mock::{active_user, MockUserRepository},
+    token::{generate_token, hash_token},
+    Error,
+};
+
+use super::{SessionRepository, SessionSettings};
+
+/// In-memory [`SessionRepository`] used where a database is not available (e.g. service tests).
+///
+/// Serves the users of a [`MockUserRepository`] and shares its sessions with it: unknown
+/// and deleted users get no session, password resets and deletions revoke them like the
+/// Postgres backends do.
+#[derive(Debug, Clone)]
+pub struct MockSessionRepository {
+    users: Arc<RwLock<HashMap<Uuid, User>>>,
+    sessions: Arc<RwLock<HashMap<Uuid, MockSession>>>,
+    settings: SessionSettings,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct MockSession {
+    pub(crate) session: Session,
+    token_hash: String,
+    /// Refresh token hashes and when they were used, like `session_refresh_tokens`.
+    refresh_tokens: HashMap<String, Option<DateTime<Utc>>>,
+}
+
+impl MockSessionRepository {
+    pub fn new(users: MockUserRepository, settings: SessionSettings) -> Self {
+        Self {
+            sessions: users.sessions(),
+            users: users.users(),
+            settings,
+        }
+    }
+
+    fn is_usable(&self, session: &Session, now: DateTime<Utc>) -> bool {
+        session.revoked_at.is_none()
+            && session.expires_at > now
+            && session.last_seen_at > now - self.settings.idle_ttl
+    }
+}
+
+fn now() -> DateTime<Utc> {
+    // Postgres TIMESTAMPTZ keeps microseconds only
+    Utc::now().trunc_subsecs(6)
+}
+
+impl SessionRepository for MockSessionRepository {
+    async fn create_session(&self, user_id: &Uuid) -> Result<IssuedSession, Error> {
+        // deleted users can't sign in again
+        active_user(&self.users, user_id)
+            .await
+            .ok_or_else(|| Error::NotFound(user_id.to_string()))?;
+
+        let token = generate_token();
+        let refresh_token = generate_token();
+        let now = now();
+
+        let session = Session {
+            id: Uuid::new_v4(),
+            user_id: *user_id,
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now + self.settings.absolute_ttl,
+            revoked_at: None,
+        };
+
+        self.sessions.write().await.insert(
+            session.id,
+            MockSession {
+                session: session.clone(),
+                token_hash: hash_token(&token),
+                refresh_tokens: HashMap::from([(hash_token(&refresh_token), None)]),
+            },
+        );
+
+        Ok(IssuedSession {
+            session,
+            token,
+            refresh_token,
+        })
+    }
+
+    async fn validate_session(&self, token: &str) -> Result<Session, Error> {
+        let mut sessions = self.sessions.write().await;
+
+        let token_hash = hash_token(token);
+        let now = now();
+
+        let stored = sessions
+            .values_mut()
+            .find(|stored| stored.token_hash == token_hash)
+            .filter(|stored| self.is_usable(&stored.session, now))
+            .ok_or(Error::InvalidToken)?;
+
+        stored.session.last_seen_at = now;
+
+        Ok(stored.session.clone())
+    }
+
+    async fn refresh_session(&self, refresh_token: &str) -> Result<IssuedSession, Error> {
+        let mut sessions = self.sessions.write().await;
+
+        let refresh_hash = hash_token(refresh_token);
+        let now = now();
+
+        let stored = sessions
+            .values_mut()
+            .find(|stored| stored.refresh_tokens.contains_key(&refresh_hash))
+            .ok_or(Error::InvalidToken)?;
+
+        if stored.refresh_tokens[&refresh_hash].is_some() {
+            stored.session.revoked_at.get_or_insert(now);
+
+            return Err(Error::TokenReuse);
+        }
+
+        if !self.is_usable(&stored.session, now) {
+            return Err(Error::InvalidToken);
+        }
+
+        let token = generate_token();
+        let new_refresh_token = generate_token();
+
+        stored.token_hash = hash_token(&token);
+        stored.session.last_seen_at = now;
+        stored.refresh_tokens.insert(refresh_hash, Some(now));
+        stored
+            .refresh_tokens
+            .insert(hash_token(&new_refresh_token), None);
+
+        Ok(IssuedSession {
+            session: stored.session.clone(),
+            token,
+            refresh_token: new_refresh_token,
+        })
+    }
+
+    async fn revoke_session(&self, id: &Uuid) -> Result<Session, Error> {
+        let mut sessions = self.sessions.write().await;
+
+        let stored = sessions
+            .get_mut(id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        stored.session.revoked_at.get_or_insert(now());
+
+        Ok(stored.session.clone())
+    }
+
+    async fn revoke_user_sessions(&self, user_id: &Uuid) -> Result<u64, Error> {
+        Ok(revoke_user_sessions(
+            &mut *self.sessions.write().await,
+            user_id,
+            now(),
+        ))
+    }
+
+    async fn delete_expired_sessions(&self) -> Result<u64, Error> {
+        let mut sessions = self.sessions.write().await;
+
+        let now = now();
+        let before = sessions.len();
+
+        sessions.retain(|_, stored| self.is_usable(&stored.session, now));
+
+        Ok((before - sessions.len()) as u64)
+    }
+}
+
+/// Revokes the active sessions of a user, what the Postgres backends do with an `UPDATE`.
+pub(crate) fn revoke_user_sessions(
+    sessions: &mut HashMap<Uuid, MockSession>,
+    user_id: &Uuid,
+    now: DateTime<Utc>,
+) -> u64 {
+    let mut revoked = 0;
+
+    for stored in sessions.values_mut() {
+        if stored.session.user_id == *user_id && stored.session.revoked_at.is_none() {
+            stored.session.revoked_at = Some(now);
+            revoked += 1;
+        }
+    }
+
+    revoked
+}
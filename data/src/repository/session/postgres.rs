@@ -0,0 +1,314 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    errors::{map_sqlx_error, ErrorExt, ErrorKindExt},
+    model::session::{IssuedSession, Session},
+    token::{generate_token, hash_token},
+    Error,
+};
+
+use super::{SessionRepository, SessionSettings};
+
+#[derive(Debug, Clone)]
+pub struct PostgresSessionRepository {
+    pub pool: PgPool,
+    settings: SessionSettings,
+}
+
+impl PostgresSessionRepository {
+    pub fn new(pool: PgPool, settings: SessionSettings) -> Self {
+        Self { pool, settings }
+    }
+}
+
+impl SessionRepository for PostgresSessionRepository {
+    async fn create_session(&self, user_id: &Uuid) -> Result<IssuedSession, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let new_id = Uuid::new_v4();
+        let token = generate_token();
+        let refresh_token = generate_token();
+
+        let result = sqlx::query!(
+            r#"
+                INSERT INTO sessions ( id, user_id, token_hash, expires_at )
+                SELECT $1, id, $3, NOW() + $4::interval
+                FROM users
+                WHERE id = $2 AND deleted_at IS NULL
+                RETURNING *;
+            "#,
+            new_id,
+            user_id,
+            hash_token(&token),
+            self.settings.absolute_ttl as _
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| match err.kind_ext() {
+            ErrorKindExt::ForeignKeyViolation => Error::NotFound(user_id.to_string()),
+            _ => map_sqlx_error(err, user_id, Error::WriteError),
+        })?
+        // deleted users can't sign in again
+        .ok_or_else(|| Error::NotFound(user_id.to_string()))?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO session_refresh_tokens ( token_hash, session_id )
+                VALUES ( $1, $2 );
+            "#,
+            hash_token(&refresh_token),
+            new_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, new_id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(IssuedSession {
+            session: Session {
+                id: result.id,
+                user_id: result.user_id,
+                created_at: result.created_at,
+                last_seen_at: result.last_seen_at,
+                expires_at: result.expires_at,
+                revoked_at: result.revoked_at,
+            },
+            token,
+            refresh_token,
+        })
+    }
+
+    async fn validate_session(&self, token: &str) -> Result<Session, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE sessions
+                SET last_seen_at = NOW()
+                WHERE token_hash = $1
+                    AND revoked_at IS NULL
+                    AND expires_at > NOW()
+                    AND last_seen_at > NOW() - $2::interval
+                RETURNING *;
+            "#,
+            hash_token(token),
+            self.settings.idle_ttl as _
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "session", Error::WriteError))?
+        .ok_or(Error::InvalidToken)?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(Session {
+            id: result.id,
+            user_id: result.user_id,
+            created_at: result.created_at,
+            last_seen_at: result.last_seen_at,
+            expires_at: result.expires_at,
+            revoked_at: result.revoked_at,
+        })
+    }
+
+    async fn refresh_session(&self, refresh_token: &str) -> Result<IssuedSession, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let refresh_hash = hash_token(refresh_token);
+
+        // row lock makes concurrent refreshes with the same token count as reuse
+        let stored = sqlx::query!(
+            r#"
+                SELECT session_id, used_at
+                FROM session_refresh_tokens
+                WHERE token_hash = $1
+                FOR UPDATE;
+            "#,
+            refresh_hash
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "refresh token", Error::ReadError))?
+        .ok_or(Error::InvalidToken)?;
+
+        if stored.used_at.is_some() {
+            sqlx::query!(
+                r#"
+                    UPDATE sessions
+                    SET revoked_at = COALESCE(revoked_at, NOW())
+                    WHERE id = $1;
+                "#,
+                stored.session_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, stored.session_id, Error::WriteError))?;
+
+            tx.commit().await.map_err(Error::TransactionError)?;
+
+            return Err(Error::TokenReuse);
+        }
+
+        let token = generate_token();
+        let new_refresh_token = generate_token();
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE sessions
+                SET token_hash = $2, last_seen_at = NOW()
+                WHERE id = $1
+                    AND revoked_at IS NULL
+                    AND expires_at > NOW()
+                    AND last_seen_at > NOW() - $3::interval
+                RETURNING *;
+            "#,
+            stored.session_id,
+            hash_token(&token),
+            self.settings.idle_ttl as _
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, stored.session_id, Error::WriteError))?
+        .ok_or(Error::InvalidToken)?;
+
+        sqlx::query!(
+            r#"
+                UPDATE session_refresh_tokens
+                SET used_at = NOW()
+                WHERE token_hash = $1;
+            "#,
+            refresh_hash
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "refresh token", Error::WriteError))?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO session_refresh_tokens ( token_hash, session_id )
+                VALUES ( $1, $2 );
+            "#,
+            hash_token(&new_refresh_token),
+            result.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, result.id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(IssuedSession {
+            session: Session {
+                id: result.id,
+                user_id: result.user_id,
+                created_at: result.created_at,
+                last_seen_at: result.last_seen_at,
+                expires_at: result.expires_at,
+                revoked_at: result.revoked_at,
+            },
+            token,
+            refresh_token: new_refresh_token,
+        })
+    }
+
+    async fn revoke_session(&self, id: &Uuid) -> Result<Session, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE sessions
+                SET revoked_at = COALESCE(revoked_at, NOW())
+                WHERE id = $1
+                RETURNING *;
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(Session {
+            id: result.id,
+            user_id: result.user_id,
+            created_at: result.created_at,
+            last_seen_at: result.last_seen_at,
+            expires_at: result.expires_at,
+            revoked_at: result.revoked_at,
+        })
+    }
+
+    async fn revoke_user_sessions(&self, user_id: &Uuid) -> Result<u64, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE sessions
+                SET revoked_at = NOW()
+                WHERE user_id = $1 AND revoked_at IS NULL;
+            "#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user_id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_expired_sessions(&self) -> Result<u64, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                DELETE FROM sessions
+                WHERE revoked_at IS NOT NULL
+                    OR expires_at <= NOW()
+                    OR last_seen_at <= NOW() - $1::interval;
+            "#,
+            self.settings.idle_ttl as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "sessions", Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(result.rows_affected())
+    }
+}
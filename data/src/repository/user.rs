@@ -1,42 +1,381 @@
 use std::future::Future;
 
+use argon2::Argon2;
+use chrono::{DateTime, Duration, Utc};
 use uuid::Uuid;
 
 use crate::{
-    model::user::{UpdateUser, User},
+    connect,
+    model::{
+        page::Page,
+        user::{
+            EmailChange, EmailChanged, HashReport, ImportUser, ListUsers, TotpEnrollment,
+            UpdateUser, User,
+        },
+    },
+    settings::PostgresSettings,
     Error,
 };
 
+use self::{
+    mock::MockUserRepository, pepper::PepperKeyring, policy::PasswordPolicy,
+    postgres::PostgresUserRepository, totp::TotpSettings,
+};
+
+mod hashing;
+pub mod mock;
+pub mod pepper;
+pub mod policy;
 pub mod postgres;
+pub mod totp;
 
+#[derive(Debug, Clone)]
 pub enum UserRepositorySettings {
     Mock,
-    Postgres,
+    Postgres(PostgresSettings),
+}
+
+/// Account rules shared by all backends.
+#[derive(Debug, Clone)]
+pub struct AccountSettings {
+    /// Reject logins until the email address is verified.
+    pub require_verified_email: bool,
+    pub email_verification_ttl: Duration,
+    pub password_reset_ttl: Duration,
+    pub email_change_ttl: Duration,
+    /// Deleted users can be restored for this long, afterwards
+    /// [`UserRepository::purge_deleted_users`] removes them for good.
+    pub deletion_grace_period: Duration,
+    pub lockout: LockoutSettings,
+    pub password_policy: PasswordPolicy,
+    /// Second factor users can enroll in, `None` disables enrollment.
+    pub totp: Option<TotpSettings>,
+    /// Logins waiting for the second factor can be finished for this long.
+    pub second_factor_ttl: Duration,
+    /// Wrong codes a login waiting for the second factor takes before it is dropped.
+    pub max_second_factor_attempts: u32,
+}
+
+impl AccountSettings {
+    pub(crate) fn totp(&self) -> Result<&TotpSettings, Error> {
+        self.totp
+            .as_ref()
+            .ok_or_else(|| Error::InvalidArgument("TOTP is not configured".to_string()))
+    }
+}
+
+impl Default for AccountSettings {
+    fn default() -> Self {
+        Self {
+            require_verified_email: false,
+            email_verification_ttl: Duration::days(2),
+            password_reset_ttl: Duration::hours(1),
+            email_change_ttl: Duration::days(1),
+            deletion_grace_period: Duration::days(30),
+            lockout: LockoutSettings::default(),
+            password_policy: PasswordPolicy::default(),
+            totp: None,
+            second_factor_ttl: Duration::minutes(5),
+            max_second_factor_attempts: 5,
+        }
+    }
+}
+
+/// Failed login tracking, per account and per source of the attempts.
+///
+/// Once the limit is reached every further failure locks for twice as long as
+/// the previous one, starting at `lockout` and capped at `max_lockout`.
+#[derive(Debug, Clone)]
+pub struct LockoutSettings {
+    pub max_attempts: u32,
+    pub max_source_attempts: u32,
+    pub lockout: Duration,
+    pub max_lockout: Duration,
+    /// Failures are forgotten once nothing failed for this long.
+    pub window: Duration,
+}
+
+impl Default for LockoutSettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            max_source_attempts: 50,
+            lockout: Duration::minutes(1),
+            max_lockout: Duration::hours(1),
+            window: Duration::days(1),
+        }
+    }
+}
+
+impl LockoutSettings {
+    pub(crate) fn max_attempts_for(&self, scope: ThrottleScope) -> u32 {
+        match scope {
+            ThrottleScope::Account => self.max_attempts,
+            ThrottleScope::Source => self.max_source_attempts,
+        }
+    }
+
+    /// Lock duration after `failed_attempts` failures in a row, if any.
+    pub(crate) fn lockout_for(
+        &self,
+        scope: ThrottleScope,
+        failed_attempts: i32,
+    ) -> Option<Duration> {
+        let over = i64::from(failed_attempts) - i64::from(self.max_attempts_for(scope));
+
+        if over < 0 {
+            return None;
+        }
+
+        let lockout = self
+            .lockout
+            .checked_mul(1 << over.min(20))
+            .unwrap_or(self.max_lockout);
+
+        Some(lockout.min(self.max_lockout))
+    }
+}
+
+/// What failed logins are counted against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum ThrottleScope {
+    Account,
+    Source,
+}
+
+impl ThrottleScope {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::Account => "account",
+            Self::Source => "source",
+        }
+    }
+}
+
+/// What a row in `user_tokens` can be used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenPurpose {
+    EmailVerification,
+    PasswordReset,
+    EmailChange,
+}
+
+impl TokenPurpose {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Self::EmailVerification => "email_verification",
+            Self::PasswordReset => "password_reset",
+            Self::EmailChange => "email_change",
+        }
+    }
 }
 
 pub trait UserRepository {
     fn get_user(&self, id: &Uuid) -> impl Future<Output = Result<User, Error>> + Send;
 
+    /// Emails are parsed as [`Email`](crate::model::email::Email) and unique regardless
+    /// of case, invalid ones give [`Error::InvalidArgument`], same for every method
+    /// storing an email.
+    ///
+    /// Passwords breaking [`AccountSettings::password_policy`] give [`Error::WeakPassword`],
+    /// same for every method setting a new password.
     fn create_user(
         &self,
         email: &str,
         password: &str,
     ) -> impl Future<Output = Result<User, Error>> + Send;
 
+    /// Inserts users with their existing password hashes, all of them or none.
+    ///
+    /// Hashes that can't be verified give [`Error::InvalidArgument`], unknown roles
+    /// [`Error::NotFound`]. Legacy hashes are replaced with argon2 on the user's first
+    /// login.
+    fn import_users(
+        &self,
+        users: Vec<ImportUser>,
+    ) -> impl Future<Output = Result<Vec<User>, Error>> + Send;
+
+    /// Changing the password requires `old_password` to match the stored hash,
+    /// otherwise [`Error::PasswordMismatch`] is returned and nothing is updated.
+    ///
+    /// If `update.expected_updated_at` no longer matches the stored user
+    /// [`Error::Conflict`] is returned.
     fn update_user(
         &self,
         id: &Uuid,
         update: UpdateUser,
     ) -> impl Future<Output = Result<User, Error>> + Send;
 
-    fn delete_user(&self, id: &Uuid) -> impl Future<Output = Result<User, Error>> + Send;
+    /// Sets a new password without checking the old one.
+    ///
+    /// Admin-only path for forced resets, authorising the caller is up to the service layer.
+    fn reset_user_password(
+        &self,
+        id: &Uuid,
+        new_password: &str,
+    ) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Soft deletes the user: it is left out of lookups, listings and logins, its sessions
+    /// and outstanding tokens are revoked. Its email stays taken until the user is purged.
+    ///
+    /// With `expected_updated_at` set the user is deleted only if it wasn't changed since
+    /// then, otherwise [`Error::Conflict`] is returned. Deleting the last active admin gives
+    /// [`Error::InvalidArgument`], like revoking its role.
+    fn delete_user(
+        &self,
+        id: &Uuid,
+        expected_updated_at: Option<DateTime<Utc>>,
+    ) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Undoes [`UserRepository::delete_user`] within
+    /// [`AccountSettings::deletion_grace_period`]. Users that aren't deleted or were
+    /// deleted longer ago give [`Error::NotFound`].
+    fn restore_user(&self, id: &Uuid) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Removes users deleted longer ago than [`AccountSettings::deletion_grace_period`]
+    /// with everything referencing them, returns how many. Meant to run periodically.
+    fn purge_deleted_users(&self) -> impl Future<Output = Result<u64, Error>> + Send;
+
+    /// Keyset paginated listing, pass `next_cursor` of a page to get the following one.
+    fn list_users(
+        &self,
+        query: ListUsers,
+    ) -> impl Future<Output = Result<Page<User>, Error>> + Send;
+
+    /// Issues a single-use token proving ownership of the user's current email,
+    /// previously issued verification tokens stop working.
+    fn issue_email_verification(
+        &self,
+        id: &Uuid,
+    ) -> impl Future<Output = Result<String, Error>> + Send;
+
+    /// Consumes a verification token and marks the email it was issued for as verified.
+    ///
+    /// Unknown, used and expired tokens, or tokens for a no longer current email,
+    /// give [`Error::InvalidToken`].
+    fn verify_email(&self, token: &str) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Starts changing the login email to `new_email`, the current email stays in use
+    /// until the returned token is confirmed. Previously requested changes are dropped.
+    ///
+    /// `password` has to match, so a stolen session alone can't take over the account.
+    /// An address already in use gives [`Error::AlreadyExists`].
+    fn request_email_change(
+        &self,
+        id: &Uuid,
+        new_email: &str,
+        password: &str,
+    ) -> impl Future<Output = Result<EmailChange, Error>> + Send;
+
+    /// Consumes an email change token and swaps in the new address, which counts as
+    /// verified. Outstanding tokens sent to the previous address stop working.
+    ///
+    /// Unknown, used and expired tokens give [`Error::InvalidToken`].
+    fn confirm_email_change(
+        &self,
+        token: &str,
+    ) -> impl Future<Output = Result<EmailChanged, Error>> + Send;
+
+    /// Drops pending email changes of the user, e.g. when the current address
+    /// reports one it didn't ask for.
+    fn cancel_email_change(&self, id: &Uuid) -> impl Future<Output = Result<(), Error>> + Send;
 
-    fn list_users(&self) -> impl Future<Output = Result<Vec<User>, Error>> + Send;
+    /// Issues a single-use password reset token for the user owning `email`,
+    /// previously issued reset tokens stop working.
+    ///
+    /// Unknown emails give `None` rather than an error, so callers can answer
+    /// the same way whether the account exists or not.
+    fn issue_password_reset(
+        &self,
+        email: &str,
+    ) -> impl Future<Output = Result<Option<String>, Error>> + Send;
 
+    /// Consumes a reset token and sets `new_password`.
+    ///
+    /// All outstanding tokens of the user are dropped and, where the backend
+    /// stores them, all sessions are revoked. Invalid tokens give [`Error::InvalidToken`].
+    fn reset_password(
+        &self,
+        token: &str,
+        new_password: &str,
+    ) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Returns the user owning `email` if `password` matches its hash.
+    ///
+    /// Hashes made with other settings or another pepper than the current one are
+    /// replaced by a fresh hash of `password`.
+    ///
+    /// Failures count towards [`AccountSettings::lockout`], while the account is locked
+    /// [`Error::LockedOut`] is returned without checking the password. Unknown emails are
+    /// checked against a dummy hash and give [`Error::PasswordMismatch`] as well, so they
+    /// can't be told apart from wrong passwords.
+    ///
+    /// With [`AccountSettings::require_verified_email`] unverified users get
+    /// [`Error::EmailNotVerified`]. Users with a confirmed TOTP get
+    /// [`Error::SecondFactorRequired`] instead of the user, with a challenge the login is
+    /// finished by in [`UserRepository::verify_totp`] or
+    /// [`UserRepository::verify_recovery_code`]. Only its hash is stored.
     fn verify_user_password(
         &self,
         email: &str,
         password: &str,
+    ) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Same as [`UserRepository::verify_user_password`], failures are also counted
+    /// against `source` (e.g. the client IP).
+    fn verify_user_password_from(
+        &self,
+        email: &str,
+        password: &str,
+        source: &str,
+    ) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Counts users whose hash would be replaced on their next login.
+    fn outdated_hash_report(&self) -> impl Future<Output = Result<HashReport, Error>> + Send;
+
+    /// Clears failed logins of the user and lifts its lockout.
+    fn unlock_user(&self, id: &Uuid) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Generates a new TOTP secret for the user, replacing an unconfirmed one. It is
+    /// asked for on login once [`UserRepository::confirm_totp`] succeeded.
+    ///
+    /// Without [`AccountSettings::totp`], or with a confirmed TOTP already, gives
+    /// [`Error::InvalidArgument`].
+    fn enroll_totp(&self, id: &Uuid) -> impl Future<Output = Result<TotpEnrollment, Error>> + Send;
+
+    /// Enables the enrolled TOTP if `code` is valid and returns the recovery codes,
+    /// only their hashes are kept. Wrong codes give [`Error::InvalidCode`].
+    fn confirm_totp(
+        &self,
+        id: &Uuid,
+        code: &str,
+    ) -> impl Future<Output = Result<Vec<String>, Error>> + Send;
+
+    /// Finishes a login that gave [`Error::SecondFactorRequired`] with the challenge it
+    /// carries. A challenge finishes one login within [`AccountSettings::second_factor_ttl`],
+    /// unknown, used and expired ones give [`Error::InvalidToken`].
+    ///
+    /// Each code is accepted once, wrong and replayed codes give [`Error::InvalidCode`]
+    /// and count towards [`AccountSettings::lockout`] like wrong passwords. After
+    /// [`AccountSettings::max_second_factor_attempts`] of them the challenge is dropped.
+    fn verify_totp(
+        &self,
+        challenge: &str,
+        code: &str,
+    ) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Same as [`UserRepository::verify_totp`] with one of the recovery codes instead.
+    fn verify_recovery_code(
+        &self,
+        challenge: &str,
+        code: &str,
+    ) -> impl Future<Output = Result<User, Error>> + Send;
+
+    /// Removes the TOTP and its recovery codes, `password` has to match.
+    fn disable_totp(
+        &self,
+        id: &Uuid,
+        password: &str,
     ) -> impl Future<Output = Result<(), Error>> + Send;
 
     fn verify_password(
@@ -47,3 +386,250 @@ pub trait UserRepository {
 
     fn hash_password(&self, password: &str) -> impl Future<Output = Result<String, Error>> + Send;
 }
+
+/// [`UserRepository`] backend picked at runtime from [`UserRepositorySettings`].
+#[derive(Debug, Clone)]
+pub enum AnyUserRepository {
+    Mock(MockUserRepository),
+    Postgres(PostgresUserRepository),
+}
+
+impl AnyUserRepository {
+    pub fn with_account_settings(self, settings: AccountSettings) -> Self {
+        match self {
+            Self::Mock(repo) => Self::Mock(repo.with_account_settings(settings)),
+            Self::Postgres(repo) => Self::Postgres(repo.with_account_settings(settings)),
+        }
+    }
+
+    pub fn with_pepper_keyring(self, keyring: PepperKeyring) -> Self {
+        match self {
+            Self::Mock(repo) => Self::Mock(repo.with_pepper_keyring(keyring)),
+            Self::Postgres(repo) => Self::Postgres(repo.with_pepper_keyring(keyring)),
+        }
+    }
+
+    pub async fn from_settings(
+        settings: &UserRepositorySettings,
+        argon: Argon2<'static>,
+    ) -> Result<Self, Error> {
+        match settings {
+            UserRepositorySettings::Mock => Ok(Self::Mock(MockUserRepository::new(argon))),
+            UserRepositorySettings::Postgres(pg_settings) => {
+                let pool = connect(pg_settings).await?;
+                Ok(Self::Postgres(PostgresUserRepository::new(pool, argon)))
+            }
+        }
+    }
+}
+
+impl UserRepository for AnyUserRepository {
+    async fn get_user(&self, id: &Uuid) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.get_user(id).await,
+            Self::Postgres(repo) => repo.get_user(id).await,
+        }
+    }
+
+    async fn create_user(&self, email: &str, password: &str) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.create_user(email, password).await,
+            Self::Postgres(repo) => repo.create_user(email, password).await,
+        }
+    }
+
+    async fn import_users(&self, users: Vec<ImportUser>) -> Result<Vec<User>, Error> {
+        match self {
+            Self::Mock(repo) => repo.import_users(users).await,
+            Self::Postgres(repo) => repo.import_users(users).await,
+        }
+    }
+
+    async fn update_user(&self, id: &Uuid, update: UpdateUser) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.update_user(id, update).await,
+            Self::Postgres(repo) => repo.update_user(id, update).await,
+        }
+    }
+
+    async fn reset_user_password(&self, id: &Uuid, new_password: &str) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.reset_user_password(id, new_password).await,
+            Self::Postgres(repo) => repo.reset_user_password(id, new_password).await,
+        }
+    }
+
+    async fn delete_user(
+        &self,
+        id: &Uuid,
+        expected_updated_at: Option<DateTime<Utc>>,
+    ) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.delete_user(id, expected_updated_at).await,
+            Self::Postgres(repo) => repo.delete_user(id, expected_updated_at).await,
+        }
+    }
+
+    async fn restore_user(&self, id: &Uuid) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.restore_user(id).await,
+            Self::Postgres(repo) => repo.restore_user(id).await,
+        }
+    }
+
+    async fn purge_deleted_users(&self) -> Result<u64, Error> {
+        match self {
+            Self::Mock(repo) => repo.purge_deleted_users().await,
+            Self::Postgres(repo) => repo.purge_deleted_users().await,
+        }
+    }
+
+    async fn list_users(&self, query: ListUsers) -> Result<Page<User>, Error> {
+        match self {
+            Self::Mock(repo) => repo.list_users(query).await,
+            Self::Postgres(repo) => repo.list_users(query).await,
+        }
+    }
+
+    async fn issue_email_verification(&self, id: &Uuid) -> Result<String, Error> {
+        match self {
+            Self::Mock(repo) => repo.issue_email_verification(id).await,
+            Self::Postgres(repo) => repo.issue_email_verification(id).await,
+        }
+    }
+
+    async fn verify_email(&self, token: &str) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.verify_email(token).await,
+            Self::Postgres(repo) => repo.verify_email(token).await,
+        }
+    }
+
+    async fn request_email_change(
+        &self,
+        id: &Uuid,
+        new_email: &str,
+        password: &str,
+    ) -> Result<EmailChange, Error> {
+        match self {
+            Self::Mock(repo) => repo.request_email_change(id, new_email, password).await,
+            Self::Postgres(repo) => repo.request_email_change(id, new_email, password).await,
+        }
+    }
+
+    async fn confirm_email_change(&self, token: &str) -> Result<EmailChanged, Error> {
+        match self {
+            Self::Mock(repo) => repo.confirm_email_change(token).await,
+            Self::Postgres(repo) => repo.confirm_email_change(token).await,
+        }
+    }
+
+    async fn cancel_email_change(&self, id: &Uuid) -> Result<(), Error> {
+        match self {
+            Self::Mock(repo) => repo.cancel_email_change(id).await,
+            Self::Postgres(repo) => repo.cancel_email_change(id).await,
+        }
+    }
+
+    async fn issue_password_reset(&self, email: &str) -> Result<Option<String>, Error> {
+        match self {
+            Self::Mock(repo) => repo.issue_password_reset(email).await,
+            Self::Postgres(repo) => repo.issue_password_reset(email).await,
+        }
+    }
+
+    async fn reset_password(&self, token: &str, new_password: &str) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.reset_password(token, new_password).await,
+            Self::Postgres(repo) => repo.reset_password(token, new_password).await,
+        }
+    }
+
+    async fn verify_user_password(&self, email: &str, password: &str) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.verify_user_password(email, password).await,
+            Self::Postgres(repo) => repo.verify_user_password(email, password).await,
+        }
+    }
+
+    async fn verify_user_password_from(
+        &self,
+        email: &str,
+        password: &str,
+        source: &str,
+    ) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => {
+                repo.verify_user_password_from(email, password, source)
+                    .await
+            }
+            Self::Postgres(repo) => {
+                repo.verify_user_password_from(email, password, source)
+                    .await
+            }
+        }
+    }
+
+    async fn outdated_hash_report(&self) -> Result<HashReport, Error> {
+        match self {
+            Self::Mock(repo) => repo.outdated_hash_report().await,
+            Self::Postgres(repo) => repo.outdated_hash_report().await,
+        }
+    }
+
+    async fn unlock_user(&self, id: &Uuid) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.unlock_user(id).await,
+            Self::Postgres(repo) => repo.unlock_user(id).await,
+        }
+    }
+
+    async fn enroll_totp(&self, id: &Uuid) -> Result<TotpEnrollment, Error> {
+        match self {
+            Self::Mock(repo) => repo.enroll_totp(id).await,
+            Self::Postgres(repo) => repo.enroll_totp(id).await,
+        }
+    }
+
+    async fn confirm_totp(&self, id: &Uuid, code: &str) -> Result<Vec<String>, Error> {
+        match self {
+            Self::Mock(repo) => repo.confirm_totp(id, code).await,
+            Self::Postgres(repo) => repo.confirm_totp(id, code).await,
+        }
+    }
+
+    async fn verify_totp(&self, challenge: &str, code: &str) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.verify_totp(challenge, code).await,
+            Self::Postgres(repo) => repo.verify_totp(challenge, code).await,
+        }
+    }
+
+    async fn verify_recovery_code(&self, challenge: &str, code: &str) -> Result<User, Error> {
+        match self {
+            Self::Mock(repo) => repo.verify_recovery_code(challenge, code).await,
+            Self::Postgres(repo) => repo.verify_recovery_code(challenge, code).await,
+        }
+    }
+
+    async fn disable_totp(&self, id: &Uuid, password: &str) -> Result<(), Error> {
+        match self {
+            Self::Mock(repo) => repo.disable_totp(id, password).await,
+            Self::Postgres(repo) => repo.disable_totp(id, password).await,
+        }
+    }
+
+    async fn verify_password(&self, password: &str, hash: &str) -> Result<(), Error> {
+        match self {
+            Self::Mock(repo) => repo.verify_password(password, hash).await,
+            Self::Postgres(repo) => repo.verify_password(password, hash).await,
+        }
+    }
+
+    async fn hash_password(&self, password: &str) -> Result<String, Error> {
+        match self {
+            Self::Mock(repo) => repo.hash_password(password).await,
+            Self::Postgres(repo) => repo.hash_password(password).await,
+        }
+    }
+}
@@ -0,0 +1,144 @@
+use std::sync::Arc;
+
+use argon2::password_hash::{
+    self, rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+use tokio::{sync::OnceCell, task};
+
+use crate::Error;
+
+use super::pepper::PepperKeyring;
+
+pub(crate) async fn hash_password(
+    keyring: Arc<PepperKeyring>,
+    password: &str,
+) -> Result<String, Error> {
+    let pass = password.to_owned();
+
+    task::spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+
+        keyring
+            .current()
+            .hash_password(pass.as_bytes(), &salt)
+            .map(|value| value.to_string())
+            .map_err(|_| Error::Hash)
+    })
+    .await
+    .map_err(|_| Error::SpawnTask)?
+}
+
+/// Verifies argon2 hashes with the pepper they name, legacy hashes of imported
+/// users (scrypt, PBKDF2, bcrypt) as they are.
+pub(crate) async fn verify_password(
+    keyring: Arc<PepperKeyring>,
+    password: &str,
+    hash: &str,
+) -> Result<(), Error> {
+    let password = password.to_owned();
+    let hash = hash.to_owned();
+
+    task::spawn_blocking(move || {
+        if is_bcrypt(&hash) {
+            return match bcrypt::verify(password.as_bytes(), &hash) {
+                Ok(true) => Ok(()),
+                Ok(false) => Err(Error::PasswordMismatch),
+                Err(_) => Err(Error::Hash),
+            };
+        }
+
+        let parsed_hash = PasswordHash::new(&hash).map_err(|_| Error::Hash)?;
+
+        let verified = match parsed_hash.algorithm.as_str() {
+            "pbkdf2" | "pbkdf2-sha256" | "pbkdf2-sha512" => {
+                Pbkdf2.verify_password(password.as_bytes(), &parsed_hash)
+            }
+            "scrypt" => Scrypt.verify_password(password.as_bytes(), &parsed_hash),
+            _ => keyring
+                .for_hash(&parsed_hash)
+                .ok_or(Error::Hash)?
+                .verify_password(password.as_bytes(), &parsed_hash),
+        };
+
+        verified.map_err(|err| match err {
+            password_hash::Error::Password => Error::PasswordMismatch,
+            _ => Error::Hash,
+        })
+    })
+    .await
+    .map_err(|_| Error::SpawnTask)?
+}
+
+/// Whether `verify_password` can check passwords against `hash`.
+pub(crate) fn is_supported_hash(hash: &str) -> bool {
+    if is_bcrypt(hash) {
+        return hash.parse::<bcrypt::HashParts>().is_ok();
+    }
+
+    match PasswordHash::new(hash) {
+        Ok(parsed_hash) => matches!(
+            parsed_hash.algorithm.as_str(),
+            "argon2d"
+                | "argon2i"
+                | "argon2id"
+                | "pbkdf2"
+                | "pbkdf2-sha256"
+                | "pbkdf2-sha512"
+                | "scrypt"
+        ),
+        Err(_) => false,
+    }
+}
+
+fn is_bcrypt(hash: &str) -> bool {
+    ["$2a$", "$2b$", "$2x$", "$2y$"]
+        .iter()
+        .any(|prefix| hash.starts_with(prefix))
+}
+
+/// Hash of a throwaway password made with the current pepper, computed on first use.
+///
+/// Serves as the dummy for unknown users and as the reference for current hash settings.
+pub(crate) async fn reference_hash(
+    keyring: Arc<PepperKeyring>,
+    reference: &OnceCell<String>,
+) -> Result<&String, Error> {
+    reference
+        .get_or_try_init(|| hash_password(keyring, "reference password"))
+        .await
+}
+
+/// Verifies against the reference hash, so a lookup miss costs as much as a hit.
+pub(crate) async fn verify_dummy_password(
+    keyring: Arc<PepperKeyring>,
+    reference: &OnceCell<String>,
+    password: &str,
+) -> Result<(), Error> {
+    let hash = reference_hash(keyring.clone(), reference).await?;
+
+    match verify_password(keyring, password, hash).await {
+        Ok(()) | Err(Error::PasswordMismatch) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Algorithm, version and params part of a PHC string, e.g. `$argon2id$v=19$m=19456,t=2,p=1$`.
+pub(crate) fn hash_settings(hash: &str) -> Option<&str> {
+    let mut parts = hash.rsplitn(3, '$');
+    let _output = parts.next()?;
+    let salt = parts.next()?;
+    let settings = parts.next()?;
+
+    if salt.is_empty() || settings.is_empty() {
+        return None;
+    }
+
+    Some(&hash[..=settings.len()])
+}
+
+/// Whether `hash` was made with other settings than `reference`.
+pub(crate) fn needs_rehash(reference: &str, hash: &str) -> bool {
+    hash_settings(reference) != hash_settings(hash)
+}
@@ -0,0 +1,1214 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use argon2::Argon2;
+use chrono::{DateTime, SubsecRound, Utc};
+use tokio::sync::{OnceCell, RwLock};
+use uuid::Uuid;
+
+use crate::{
+    model::{
+        email::Email,
+        page::{page_size, Page},
+        role::ADMIN_ROLE,
+        user::{
+            EmailChange, EmailChanged, HashReport, ImportUser, ListUsers, TotpEnrollment,
+            UpdateUser, User, UserCursor, UserFilter, UserSort,
+        },
+    },
+    repository::{
+        api_key::mock::MockApiKey,
+        role::mock::{find_role, is_last_admin},
+        session::mock::{revoke_user_sessions, MockSession},
+    },
+    token::{generate_token, hash_token},
+    Error,
+};
+
+use super::{
+    hashing,
+    pepper::PepperKeyring,
+    totp::{self, TotpSettings},
+    AccountSettings, ThrottleScope, TokenPurpose, UserRepository,
+};
+
+/// In-memory [`UserRepository`] used where a database is not available (e.g. service tests).
+///
+/// Mirrors the Postgres backend: emails are unique regardless of case, timestamps are kept with microsecond
+/// precision and failures are reported with the same [`Error`] variants.
+#[derive(Debug, Clone)]
+pub struct MockUserRepository {
+    users: Arc<RwLock<HashMap<Uuid, User>>>,
+    // keyed by token hash, like `user_tokens`
+    tokens: Arc<RwLock<HashMap<String, MockToken>>>,
+    throttle: Arc<RwLock<HashMap<(ThrottleScope, String), MockThrottle>>>,
+    totp: Arc<RwLock<HashMap<Uuid, MockTotp>>>,
+    // keyed by challenge hash, like `login_challenges`, locked after `totp`
+    challenges: Arc<RwLock<HashMap<String, MockChallenge>>>,
+    // kept here so user changes reach the session, key and role mocks built on this
+    // repository, locked after `users`
+    sessions: Arc<RwLock<HashMap<Uuid, MockSession>>>,
+    api_keys: Arc<RwLock<HashMap<Uuid, MockApiKey>>>,
+    user_roles: Arc<RwLock<HashSet<(Uuid, String)>>>,
+    keyring: Arc<PepperKeyring>,
+    reference_hash: Arc<OnceCell<String>>,
+    settings: AccountSettings,
+}
+
+#[derive(Debug, Clone)]
+struct MockThrottle {
+    failed_attempts: i32,
+    last_failed_at: DateTime<Utc>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone)]
+struct MockTotp {
+    /// Encrypted like in Postgres, so a wrong key fails the same way.
+    secret: Vec<u8>,
+    last_used_step: Option<i64>,
+    confirmed_at: Option<DateTime<Utc>>,
+    /// Hashed recovery codes and when they were used.
+    recovery_codes: HashMap<String, Option<DateTime<Utc>>>,
+}
+
+#[derive(Debug, Clone)]
+struct MockChallenge {
+    user_id: Uuid,
+    failed_attempts: u32,
+    expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct MockToken {
+    user_id: Uuid,
+    purpose: TokenPurpose,
+    email: String,
+    expires_at: DateTime<Utc>,
+    consumed_at: Option<DateTime<Utc>>,
+}
+
+impl MockUserRepository {
+    pub fn new(argon: Argon2<'static>) -> Self {
+        Self {
+            users: Arc::new(RwLock::new(HashMap::new())),
+            tokens: Arc::new(RwLock::new(HashMap::new())),
+            throttle: Arc::new(RwLock::new(HashMap::new())),
+            totp: Arc::new(RwLock::new(HashMap::new())),
+            challenges: Arc::new(RwLock::new(HashMap::new())),
+            sessions: Arc::new(RwLock::new(HashMap::new())),
+            api_keys: Arc::new(RwLock::new(HashMap::new())),
+            user_roles: Arc::new(RwLock::new(HashSet::new())),
+            keyring: Arc::new(PepperKeyring::unkeyed(argon)),
+            reference_hash: Arc::new(OnceCell::new()),
+            settings: AccountSettings::default(),
+        }
+    }
+
+    pub fn with_account_settings(mut self, settings: AccountSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_pepper_keyring(mut self, keyring: PepperKeyring) -> Self {
+        self.keyring = Arc::new(keyring);
+        self.reference_hash = Arc::new(OnceCell::new());
+        self
+    }
+
+    pub(crate) fn users(&self) -> Arc<RwLock<HashMap<Uuid, User>>> {
+        self.users.clone()
+    }
+
+    pub(crate) fn sessions(&self) -> Arc<RwLock<HashMap<Uuid, MockSession>>> {
+        self.sessions.clone()
+    }
+
+    pub(crate) fn api_keys(&self) -> Arc<RwLock<HashMap<Uuid, MockApiKey>>> {
+        self.api_keys.clone()
+    }
+
+    pub(crate) fn user_roles(&self) -> Arc<RwLock<HashSet<(Uuid, String)>>> {
+        self.user_roles.clone()
+    }
+
+    /// Inserts an already hashed user as is, the in-memory counterpart of a fixture.
+    pub async fn insert_user(&self, user: User) -> Result<User, Error> {
+        let mut users = self.users.write().await;
+
+        if users.contains_key(&user.id) || email_taken(&users, &user.email, None) {
+            return Err(Error::AlreadyExists(user.email));
+        }
+
+        users.insert(user.id, user.clone());
+
+        Ok(user)
+    }
+
+    async fn verify_login(
+        &self,
+        email: &str,
+        password: &str,
+        source: Option<&str>,
+    ) -> Result<User, Error> {
+        let email_key = Email::lookup_key(email);
+        let mut subjects = vec![(ThrottleScope::Account, email_key.as_str())];
+        if let Some(source) = source {
+            subjects.push((ThrottleScope::Source, source));
+        }
+
+        {
+            let throttle = self.throttle.read().await;
+
+            for (scope, subject) in &subjects {
+                ensure_not_locked(&throttle, *scope, subject)?;
+            }
+        }
+
+        let user = {
+            let users = self.users.read().await;
+
+            users
+                .values()
+                .find(|user| is_active(user) && same_email(&user.email, email))
+                .cloned()
+        };
+
+        let verified = match &user {
+            Some(user) => self.verify_password(password, &user.hash).await,
+            None => {
+                hashing::verify_dummy_password(
+                    self.keyring.clone(),
+                    &self.reference_hash,
+                    password,
+                )
+                .await?;
+                // same answer as a wrong password, so logins don't reveal which emails exist
+                Err(Error::PasswordMismatch)
+            }
+        };
+
+        let mut throttle = self.throttle.write().await;
+
+        let second_factor = match verified {
+            Ok(()) => {
+                let second_factor = match &user {
+                    Some(user) => self
+                        .totp
+                        .read()
+                        .await
+                        .get(&user.id)
+                        .is_some_and(|totp| totp.confirmed_at.is_some()),
+                    None => false,
+                };
+
+                // failures are kept until the second factor is passed as well
+                if !second_factor {
+                    throttle.remove(&(ThrottleScope::Account, email_key.clone()));
+                }
+
+                second_factor
+            }
+            Err(err @ Error::PasswordMismatch) => {
+                for (scope, subject) in subjects {
+                    self.record_failed_login(&mut throttle, scope, subject);
+                }
+
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        };
+
+        drop(throttle);
+
+        let mut user = user.ok_or_else(|| Error::NotFound(email.to_string()))?;
+
+        let reference = hashing::reference_hash(self.keyring.clone(), &self.reference_hash).await?;
+
+        if hashing::needs_rehash(reference, &user.hash) {
+            let hash = self.hash_password(password).await?;
+
+            let mut users = self.users.write().await;
+
+            if let Some(stored) = users.get_mut(&user.id) {
+                stored.hash = hash;
+                stored.updated_at = now();
+                user = stored.clone();
+            }
+        }
+
+        if self.settings.require_verified_email && user.email_verified_at.is_none() {
+            return Err(Error::EmailNotVerified);
+        }
+
+        if second_factor {
+            return Err(Error::SecondFactorRequired(
+                self.create_login_challenge(&user.id).await,
+            ));
+        }
+
+        Ok(user)
+    }
+
+    fn record_failed_login(
+        &self,
+        throttle: &mut HashMap<(ThrottleScope, String), MockThrottle>,
+        scope: ThrottleScope,
+        subject: &str,
+    ) {
+        let lockout = &self.settings.lockout;
+        let now = now();
+
+        let stored = throttle
+            .entry((scope, subject.to_string()))
+            .or_insert(MockThrottle {
+                failed_attempts: 0,
+                last_failed_at: now,
+                locked_until: None,
+            });
+
+        if stored.last_failed_at <= now - lockout.window {
+            stored.failed_attempts = 0;
+        }
+
+        stored.failed_attempts += 1;
+        stored.last_failed_at = now;
+
+        if let Some(duration) = lockout.lockout_for(scope, stored.failed_attempts) {
+            stored.locked_until = Some(now + duration);
+        }
+    }
+
+    /// Challenge a login waiting for the second factor is finished with, expired ones are
+    /// dropped on the way.
+    async fn create_login_challenge(&self, user_id: &Uuid) -> String {
+        let mut challenges = self.challenges.write().await;
+        let now = now();
+
+        challenges.retain(|_, stored| stored.expires_at > now);
+
+        let challenge = generate_token();
+
+        challenges.insert(
+            hash_token(&challenge),
+            MockChallenge {
+                user_id: *user_id,
+                failed_attempts: 0,
+                expires_at: now + self.settings.second_factor_ttl,
+            },
+        );
+
+        challenge
+    }
+
+    /// Second login step, failures count towards the account lockout and the challenge.
+    async fn verify_second_factor(
+        &self,
+        challenge: &str,
+        factor: SecondFactor<'_>,
+    ) -> Result<User, Error> {
+        let challenge_hash = hash_token(challenge);
+
+        let id = self
+            .challenges
+            .read()
+            .await
+            .get(&challenge_hash)
+            .filter(|stored| stored.expires_at > now())
+            .map(|stored| stored.user_id)
+            .ok_or(Error::InvalidToken)?;
+
+        let user = self.get_user(&id).await.map_err(|_| Error::InvalidToken)?;
+        let email_key = Email::lookup_key(&user.email);
+
+        let mut throttle = self.throttle.write().await;
+
+        ensure_not_locked(&throttle, ThrottleScope::Account, &email_key)?;
+
+        let mut totps = self.totp.write().await;
+        let mut challenges = self.challenges.write().await;
+
+        // looked up again under the lock, it may have been used meanwhile
+        let login = challenges
+            .get_mut(&challenge_hash)
+            .filter(|stored| stored.expires_at > now())
+            .ok_or(Error::InvalidToken)?;
+
+        let totp = totps
+            .get_mut(&id)
+            .filter(|totp| totp.confirmed_at.is_some())
+            .ok_or_else(|| Error::InvalidArgument(format!("user {id} has no TOTP enabled")))?;
+
+        let accepted = match factor {
+            SecondFactor::Totp(code) => {
+                let settings = self.settings.totp()?;
+                let secret = settings.decrypt(&id, &totp.secret)?;
+
+                // a code is only good once, and so are the ones before it
+                let step = settings
+                    .matching_step(secret, code)?
+                    .filter(|step| totp.last_used_step.is_none_or(|last| *step > last));
+
+                if step.is_some() {
+                    totp.last_used_step = step;
+                }
+
+                step.is_some()
+            }
+            SecondFactor::RecoveryCode(code) => {
+                let used_at = totp
+                    .recovery_codes
+                    .get_mut(&hash_token(&totp::normalize_recovery_code(code)))
+                    .filter(|used_at| used_at.is_none());
+
+                match used_at {
+                    Some(used_at) => {
+                        *used_at = Some(now());
+                        true
+                    }
+                    None => false,
+                }
+            }
+        };
+
+        if !accepted {
+            self.record_failed_login(&mut throttle, ThrottleScope::Account, &email_key);
+
+            // guessing is limited per challenge too, not only by the account lockout
+            login.failed_attempts += 1;
+            if login.failed_attempts >= self.settings.max_second_factor_attempts {
+                challenges.remove(&challenge_hash);
+            }
+
+            return Err(Error::InvalidCode);
+        }
+
+        // a challenge finishes one login
+        challenges.remove(&challenge_hash);
+
+        throttle.remove(&(ThrottleScope::Account, email_key));
+
+        Ok(user)
+    }
+}
+
+enum SecondFactor<'a> {
+    Totp(&'a str),
+    RecoveryCode(&'a str),
+}
+
+fn ensure_not_locked(
+    throttle: &HashMap<(ThrottleScope, String), MockThrottle>,
+    scope: ThrottleScope,
+    subject: &str,
+) -> Result<(), Error> {
+    let locked_until = throttle
+        .get(&(scope, subject.to_string()))
+        .and_then(|stored| stored.locked_until)
+        .filter(|locked_until| *locked_until > now());
+
+    match locked_until {
+        Some(locked_until) => Err(Error::LockedOut(locked_until)),
+        None => Ok(()),
+    }
+}
+
+fn now() -> DateTime<Utc> {
+    // Postgres TIMESTAMPTZ keeps microseconds only
+    Utc::now().trunc_subsecs(6)
+}
+
+/// User that is not deleted, what the other mocks check owners against.
+pub(crate) async fn active_user(users: &RwLock<HashMap<Uuid, User>>, id: &Uuid) -> Option<User> {
+    users
+        .read()
+        .await
+        .get(id)
+        .filter(|user| is_active(user))
+        .cloned()
+}
+
+fn is_active(user: &User) -> bool {
+    user.deleted_at.is_none()
+}
+
+fn same_email(a: &str, b: &str) -> bool {
+    Email::lookup_key(a) == Email::lookup_key(b)
+}
+
+fn email_taken(users: &HashMap<Uuid, User>, email: &str, except: Option<&Uuid>) -> bool {
+    users
+        .values()
+        .any(|user| same_email(&user.email, email) && Some(&user.id) != except)
+}
+
+fn matches_filter(user: &User, filter: &UserFilter, user_roles: &HashSet<(Uuid, String)>) -> bool {
+    if !filter.include_deleted && !is_active(user) {
+        return false;
+    }
+
+    if let Some(email) = &filter.email_contains {
+        if !user.email.to_lowercase().contains(&email.to_lowercase()) {
+            return false;
+        }
+    }
+
+    if let Some(role) = &filter.role {
+        if !user_roles.contains(&(user.id, role.clone())) {
+            return false;
+        }
+    }
+
+    if let Some(created_after) = filter.created_after {
+        if user.created_at < created_after {
+            return false;
+        }
+    }
+
+    if let Some(created_before) = filter.created_before {
+        if user.created_at >= created_before {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn compare_users(a: &User, b: &User, sort: UserSort) -> Ordering {
+    let ordering = match sort {
+        UserSort::CreatedAtAsc | UserSort::CreatedAtDesc => a.created_at.cmp(&b.created_at),
+        UserSort::EmailAsc | UserSort::EmailDesc => a.email.cmp(&b.email),
+    }
+    .then(a.id.cmp(&b.id));
+
+    if sort.is_desc() {
+        ordering.reverse()
+    } else {
+        ordering
+    }
+}
+
+fn is_after_cursor(user: &User, cursor: &UserCursor, sort: UserSort) -> bool {
+    let ordering = match cursor {
+        UserCursor::CreatedAt(created_at, id) => {
+            (user.created_at, user.id).cmp(&(*created_at, *id))
+        }
+        UserCursor::Email(email, id) => (user.email.as_str(), user.id).cmp(&(email.as_str(), *id)),
+    };
+
+    if sort.is_desc() {
+        ordering == Ordering::Less
+    } else {
+        ordering == Ordering::Greater
+    }
+}
+
+impl UserRepository for MockUserRepository {
+    async fn get_user(&self, id: &Uuid) -> Result<User, Error> {
+        let users = self.users.read().await;
+
+        users
+            .get(id)
+            .filter(|user| is_active(user))
+            .cloned()
+            .ok_or_else(|| Error::NotFound(id.to_string()))
+    }
+
+    async fn list_users(&self, query: ListUsers) -> Result<Page<User>, Error> {
+        let size = page_size(query.limit) as usize;
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(|cursor| UserCursor::decode(cursor, query.sort))
+            .transpose()?;
+
+        let users = self.users.read().await;
+        let user_roles = self.user_roles.read().await;
+
+        let mut result: Vec<User> = users
+            .values()
+            .filter(|user| matches_filter(user, &query.filter, &user_roles))
+            .cloned()
+            .collect();
+
+        let total = query.with_total.then_some(result.len() as i64);
+
+        result.sort_by(|a, b| compare_users(a, b, query.sort));
+
+        if let Some(cursor) = cursor {
+            result.retain(|user| is_after_cursor(user, &cursor, query.sort));
+        }
+
+        let next_cursor = if result.len() > size {
+            result.truncate(size);
+            result
+                .last()
+                .map(|user| UserCursor::after(user, query.sort).encode())
+        } else {
+            None
+        };
+
+        Ok(Page {
+            items: result,
+            next_cursor,
+            total,
+        })
+    }
+
+    async fn create_user(&self, email: &str, password: &str) -> Result<User, Error> {
+        let email = Email::parse(email)?;
+        self.settings
+            .password_policy
+            .check(password, email.as_str())?;
+
+        let hash = self.hash_password(password).await?;
+
+        let mut users = self.users.write().await;
+
+        if email_taken(&users, email.as_str(), None) {
+            return Err(Error::AlreadyExists(email.to_string()));
+        }
+
+        let created_at = now();
+        let user = User {
+            id: Uuid::new_v4(),
+            email: email.into(),
+            hash,
+            created_at,
+            updated_at: created_at,
+            email_verified_at: None,
+            deleted_at: None,
+        };
+
+        users.insert(user.id, user.clone());
+
+        Ok(user)
+    }
+
+    async fn import_users(&self, users: Vec<ImportUser>) -> Result<Vec<User>, Error> {
+        if let Some(user) = users
+            .iter()
+            .find(|user| !hashing::is_supported_hash(&user.hash))
+        {
+            return Err(Error::InvalidArgument(format!(
+                "unsupported password hash of {}",
+                user.email
+            )));
+        }
+
+        let emails = users
+            .iter()
+            .map(|user| Email::parse(&user.email))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(unknown) = users
+            .iter()
+            .flat_map(|user| &user.roles)
+            .find(|name| find_role(name).is_none())
+        {
+            return Err(Error::NotFound(unknown.clone()));
+        }
+
+        let mut stored = self.users.write().await;
+
+        // check everything first, nothing is inserted on failure
+        let mut seen = HashSet::new();
+
+        for email in &emails {
+            if email_taken(&stored, email.as_str(), None)
+                || !seen.insert(Email::lookup_key(email.as_str()))
+            {
+                return Err(Error::AlreadyExists(email.to_string()));
+            }
+        }
+
+        let now = now();
+        let mut user_roles = self.user_roles.write().await;
+        let mut imported = Vec::with_capacity(users.len());
+
+        for (user, email) in users.into_iter().zip(emails) {
+            let id = Uuid::new_v4();
+
+            for role in user.roles {
+                user_roles.insert((id, role));
+            }
+
+            imported.push(User {
+                id,
+                email: email.into(),
+                hash: user.hash,
+                created_at: user.created_at.unwrap_or(now),
+                updated_at: now,
+                email_verified_at: user.email_verified_at,
+                deleted_at: None,
+            });
+        }
+
+        for user in &imported {
+            stored.insert(user.id, user.clone());
+        }
+
+        Ok(imported)
+    }
+
+    async fn update_user(&self, id: &Uuid, update: UpdateUser) -> Result<User, Error> {
+        // write lock is held for the whole update, like the row lock in Postgres
+        let mut users = self.users.write().await;
+
+        let current = users
+            .get(id)
+            .filter(|user| is_active(user))
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        if let Some(expected) = update.expected_updated_at {
+            if expected != current.updated_at {
+                return Err(Error::Conflict(id.to_string()));
+            }
+        }
+
+        let Some(password_set) = update.password else {
+            return Ok(current.clone());
+        };
+
+        let current_hash = current.hash.clone();
+        let current_email = current.email.clone();
+
+        self.verify_password(&password_set.old_password, &current_hash)
+            .await?;
+        self.settings
+            .password_policy
+            .check(&password_set.new_password, &current_email)?;
+        let hash = self.hash_password(&password_set.new_password).await?;
+
+        let user = users
+            .get_mut(id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        user.hash = hash;
+        user.updated_at = now();
+
+        Ok(user.clone())
+    }
+
+    async fn reset_user_password(&self, id: &Uuid, new_password: &str) -> Result<User, Error> {
+        let email = self.get_user(id).await?.email;
+
+        self.settings.password_policy.check(new_password, &email)?;
+
+        let hash = self.hash_password(new_password).await?;
+
+        let mut users = self.users.write().await;
+
+        let user = users
+            .get_mut(id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        user.hash = hash;
+        user.updated_at = now();
+
+        Ok(user.clone())
+    }
+
+    async fn delete_user(
+        &self,
+        id: &Uuid,
+        expected_updated_at: Option<DateTime<Utc>>,
+    ) -> Result<User, Error> {
+        let mut users = self.users.write().await;
+        let last_admin = is_last_admin(&users, &*self.user_roles.read().await, id);
+
+        let user = users
+            .get_mut(id)
+            .filter(|user| is_active(user))
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        if let Some(expected) = expected_updated_at {
+            if expected != user.updated_at {
+                return Err(Error::Conflict(id.to_string()));
+            }
+        }
+
+        // same check as revoking the role, a deleted admin doesn't count
+        if last_admin {
+            return Err(Error::InvalidArgument(format!(
+                "{id} is the last {ADMIN_ROLE}"
+            )));
+        }
+
+        let now = now();
+        user.deleted_at = Some(now);
+        user.updated_at = now;
+
+        self.tokens
+            .write()
+            .await
+            .retain(|_, stored| stored.user_id != *id || stored.consumed_at.is_some());
+        revoke_user_sessions(&mut *self.sessions.write().await, id, now);
+
+        Ok(user.clone())
+    }
+
+    async fn restore_user(&self, id: &Uuid) -> Result<User, Error> {
+        let mut users = self.users.write().await;
+
+        let restorable_since = now() - self.settings.deletion_grace_period;
+
+        let user = users
+            .get_mut(id)
+            .filter(|user| {
+                user.deleted_at
+                    .is_some_and(|deleted_at| deleted_at > restorable_since)
+            })
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        user.deleted_at = None;
+        user.updated_at = now();
+
+        Ok(user.clone())
+    }
+
+    async fn purge_deleted_users(&self) -> Result<u64, Error> {
+        let mut users = self.users.write().await;
+
+        let purge_before = now() - self.settings.deletion_grace_period;
+
+        let purged: HashSet<Uuid> = users
+            .values()
+            .filter(|user| {
+                user.deleted_at
+                    .is_some_and(|deleted_at| deleted_at <= purge_before)
+            })
+            .map(|user| user.id)
+            .collect();
+
+        users.retain(|id, _| !purged.contains(id));
+
+        // what Postgres removes by ON DELETE CASCADE
+        self.tokens
+            .write()
+            .await
+            .retain(|_, stored| !purged.contains(&stored.user_id));
+        self.totp.write().await.retain(|id, _| !purged.contains(id));
+        self.challenges
+            .write()
+            .await
+            .retain(|_, stored| !purged.contains(&stored.user_id));
+        self.sessions
+            .write()
+            .await
+            .retain(|_, stored| !purged.contains(&stored.session.user_id));
+        self.api_keys
+            .write()
+            .await
+            .retain(|_, stored| !purged.contains(&stored.api_key.user_id));
+        self.user_roles
+            .write()
+            .await
+            .retain(|(user_id, _)| !purged.contains(user_id));
+
+        Ok(purged.len() as u64)
+    }
+
+    async fn issue_email_verification(&self, id: &Uuid) -> Result<String, Error> {
+        let users = self.users.read().await;
+
+        let user = users
+            .get(id)
+            .filter(|user| is_active(user))
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        if user.email_verified_at.is_some() {
+            return Err(Error::InvalidArgument(format!(
+                "email {} is already verified",
+                user.email
+            )));
+        }
+
+        let mut tokens = self.tokens.write().await;
+
+        tokens.retain(|_, stored| {
+            stored.user_id != *id
+                || stored.purpose != TokenPurpose::EmailVerification
+                || stored.consumed_at.is_some()
+        });
+
+        let token = generate_token();
+
+        tokens.insert(
+            hash_token(&token),
+            MockToken {
+                user_id: *id,
+                purpose: TokenPurpose::EmailVerification,
+                email: user.email.clone(),
+                expires_at: now() + self.settings.email_verification_ttl,
+                consumed_at: None,
+            },
+        );
+
+        Ok(token)
+    }
+
+    async fn verify_email(&self, token: &str) -> Result<User, Error> {
+        let mut users = self.users.write().await;
+        let mut tokens = self.tokens.write().await;
+
+        let now = now();
+
+        let stored = tokens
+            .get_mut(&hash_token(token))
+            .filter(|stored| {
+                stored.purpose == TokenPurpose::EmailVerification
+                    && stored.consumed_at.is_none()
+                    && stored.expires_at > now
+            })
+            .ok_or(Error::InvalidToken)?;
+
+        stored.consumed_at = Some(now);
+
+        // the token proves ownership of the address it was sent to only
+        let user = users
+            .get_mut(&stored.user_id)
+            .filter(|user| same_email(&user.email, &stored.email))
+            .ok_or(Error::InvalidToken)?;
+
+        if user.email_verified_at.is_none() {
+            user.email_verified_at = Some(now);
+            user.updated_at = now;
+        }
+
+        Ok(user.clone())
+    }
+
+    async fn request_email_change(
+        &self,
+        id: &Uuid,
+        new_email: &str,
+        password: &str,
+    ) -> Result<EmailChange, Error> {
+        let new_email = Email::parse(new_email)?;
+
+        let users = self.users.read().await;
+
+        let user = users
+            .get(id)
+            .filter(|user| is_active(user))
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        self.verify_password(password, &user.hash).await?;
+
+        if same_email(&user.email, new_email.as_str()) {
+            return Err(Error::InvalidArgument(format!(
+                "email {new_email} is already the current one"
+            )));
+        }
+
+        if email_taken(&users, new_email.as_str(), None) {
+            return Err(Error::AlreadyExists(new_email.to_string()));
+        }
+
+        let mut tokens = self.tokens.write().await;
+
+        tokens.retain(|_, stored| {
+            stored.user_id != *id
+                || stored.purpose != TokenPurpose::EmailChange
+                || stored.consumed_at.is_some()
+        });
+
+        let token = generate_token();
+        let expires_at = now() + self.settings.email_change_ttl;
+
+        tokens.insert(
+            hash_token(&token),
+            MockToken {
+                user_id: *id,
+                purpose: TokenPurpose::EmailChange,
+                email: new_email.to_string(),
+                expires_at,
+                consumed_at: None,
+            },
+        );
+
+        Ok(EmailChange {
+            user_id: *id,
+            current_email: user.email.clone(),
+            new_email: new_email.into(),
+            token,
+            expires_at,
+        })
+    }
+
+    async fn confirm_email_change(&self, token: &str) -> Result<EmailChanged, Error> {
+        let mut users = self.users.write().await;
+        let mut tokens = self.tokens.write().await;
+
+        let now = now();
+
+        let stored = tokens
+            .get(&hash_token(token))
+            .filter(|stored| {
+                stored.purpose == TokenPurpose::EmailChange
+                    && stored.consumed_at.is_none()
+                    && stored.expires_at > now
+            })
+            .cloned()
+            .ok_or(Error::InvalidToken)?;
+
+        // the address may have been taken since the request, the token stays unused then
+        if email_taken(&users, &stored.email, Some(&stored.user_id)) {
+            return Err(Error::AlreadyExists(stored.email));
+        }
+
+        let user = users
+            .get_mut(&stored.user_id)
+            .ok_or_else(|| Error::NotFound(stored.user_id.to_string()))?;
+
+        let previous_email = std::mem::replace(&mut user.email, stored.email);
+        user.email_verified_at = Some(now);
+        user.updated_at = now;
+
+        // tokens sent to the previous address don't prove anything anymore
+        tokens.retain(|_, other| other.user_id != user.id || other.consumed_at.is_some());
+
+        if let Some(consumed) = tokens.get_mut(&hash_token(token)) {
+            consumed.consumed_at = Some(now);
+        }
+
+        Ok(EmailChanged {
+            user: user.clone(),
+            previous_email,
+        })
+    }
+
+    async fn cancel_email_change(&self, id: &Uuid) -> Result<(), Error> {
+        let users = self.users.read().await;
+
+        if !users.get(id).is_some_and(is_active) {
+            return Err(Error::NotFound(id.to_string()));
+        }
+
+        let mut tokens = self.tokens.write().await;
+
+        tokens.retain(|_, stored| {
+            stored.user_id != *id
+                || stored.purpose != TokenPurpose::EmailChange
+                || stored.consumed_at.is_some()
+        });
+
+        Ok(())
+    }
+
+    async fn issue_password_reset(&self, email: &str) -> Result<Option<String>, Error> {
+        let users = self.users.read().await;
+
+        let Some(user) = users
+            .values()
+            .find(|user| is_active(user) && same_email(&user.email, email))
+        else {
+            return Ok(None);
+        };
+
+        let mut tokens = self.tokens.write().await;
+
+        tokens.retain(|_, stored| {
+            stored.user_id != user.id
+                || stored.purpose != TokenPurpose::PasswordReset
+                || stored.consumed_at.is_some()
+        });
+
+        let token = generate_token();
+
+        tokens.insert(
+            hash_token(&token),
+            MockToken {
+                user_id: user.id,
+                purpose: TokenPurpose::PasswordReset,
+                email: user.email.clone(),
+                expires_at: now() + self.settings.password_reset_ttl,
+                consumed_at: None,
+            },
+        );
+
+        Ok(Some(token))
+    }
+
+    async fn reset_password(&self, token: &str, new_password: &str) -> Result<User, Error> {
+        let email = self
+            .tokens
+            .read()
+            .await
+            .get(&hash_token(token))
+            .map(|stored| stored.email.clone())
+            .ok_or(Error::InvalidToken)?;
+
+        self.settings.password_policy.check(new_password, &email)?;
+
+        let hash = self.hash_password(new_password).await?;
+
+        let mut users = self.users.write().await;
+        let mut tokens = self.tokens.write().await;
+
+        let now = now();
+
+        let stored = tokens
+            .get_mut(&hash_token(token))
+            .filter(|stored| {
+                stored.purpose == TokenPurpose::PasswordReset
+                    && stored.consumed_at.is_none()
+                    && stored.expires_at > now
+            })
+            .ok_or(Error::InvalidToken)?;
+
+        stored.consumed_at = Some(now);
+
+        // the address may have changed since the token was sent
+        let user = users
+            .get_mut(&stored.user_id)
+            .filter(|user| same_email(&user.email, &stored.email))
+            .ok_or(Error::InvalidToken)?;
+
+        user.hash = hash;
+        user.updated_at = now;
+
+        tokens.retain(|_, stored| stored.user_id != user.id || stored.consumed_at.is_some());
+        revoke_user_sessions(&mut *self.sessions.write().await, &user.id, now);
+
+        Ok(user.clone())
+    }
+
+    async fn verify_user_password(&self, email: &str, password: &str) -> Result<User, Error> {
+        self.verify_login(email, password, None).await
+    }
+
+    async fn verify_user_password_from(
+        &self,
+        email: &str,
+        password: &str,
+        source: &str,
+    ) -> Result<User, Error> {
+        self.verify_login(email, password, Some(source)).await
+    }
+
+    async fn outdated_hash_report(&self) -> Result<HashReport, Error> {
+        let reference = hashing::reference_hash(self.keyring.clone(), &self.reference_hash).await?;
+
+        let users = self.users.read().await;
+
+        let active: Vec<&User> = users.values().filter(|user| is_active(user)).collect();
+
+        let outdated = active
+            .iter()
+            .filter(|user| hashing::needs_rehash(reference, &user.hash))
+            .count();
+
+        Ok(HashReport {
+            total: active.len() as i64,
+            outdated: outdated as i64,
+        })
+    }
+
+    async fn unlock_user(&self, id: &Uuid) -> Result<User, Error> {
+        let user = self.get_user(id).await?;
+
+        let mut throttle = self.throttle.write().await;
+
+        throttle.remove(&(ThrottleScope::Account, Email::lookup_key(&user.email)));
+
+        Ok(user)
+    }
+
+    async fn enroll_totp(&self, id: &Uuid) -> Result<TotpEnrollment, Error> {
+        let settings = self.settings.totp()?;
+
+        let user = self.get_user(id).await?;
+
+        let mut totps = self.totp.write().await;
+
+        if totps
+            .get(id)
+            .is_some_and(|totp| totp.confirmed_at.is_some())
+        {
+            return Err(Error::InvalidArgument(format!(
+                "user {id} has TOTP enabled already"
+            )));
+        }
+
+        let secret = TotpSettings::generate_secret();
+        let enrollment = settings.enrollment(secret.clone(), &user.email)?;
+
+        totps.insert(
+            *id,
+            MockTotp {
+                secret: settings.encrypt(id, &secret)?,
+                last_used_step: None,
+                confirmed_at: None,
+                recovery_codes: HashMap::new(),
+            },
+        );
+
+        Ok(enrollment)
+    }
+
+    async fn confirm_totp(&self, id: &Uuid, code: &str) -> Result<Vec<String>, Error> {
+        let settings = self.settings.totp()?;
+
+        self.get_user(id).await?;
+
+        let mut totps = self.totp.write().await;
+
+        let totp = totps
+            .get_mut(id)
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+
+        if totp.confirmed_at.is_some() {
+            return Err(Error::InvalidArgument(format!(
+                "user {id} has TOTP enabled already"
+            )));
+        }
+
+        let step = settings
+            .matching_step(settings.decrypt(id, &totp.secret)?, code)?
+            .ok_or(Error::InvalidCode)?;
+
+        let codes = totp::generate_recovery_codes();
+
+        totp.confirmed_at = Some(now());
+        totp.last_used_step = Some(step);
+        totp.recovery_codes = codes
+            .iter()
+            .map(|code| (hash_token(&totp::normalize_recovery_code(code)), None))
+            .collect();
+
+        Ok(codes)
+    }
+
+    async fn verify_totp(&self, challenge: &str, code: &str) -> Result<User, Error> {
+        self.verify_second_factor(challenge, SecondFactor::Totp(code))
+            .await
+    }
+
+    async fn verify_recovery_code(&self, challenge: &str, code: &str) -> Result<User, Error> {
+        self.verify_second_factor(challenge, SecondFactor::RecoveryCode(code))
+            .await
+    }
+
+    async fn disable_totp(&self, id: &Uuid, password: &str) -> Result<(), Error> {
+        let user = self.get_user(id).await?;
+
+        self.verify_password(password, &user.hash).await?;
+
+        self.totp.write().await.remove(id);
+
+        Ok(())
+    }
+
+    async fn hash_password(&self, password: &str) -> Result<String, Error> {
+        hashing::hash_password(self.keyring.clone(), password).await
+    }
+
+    async fn verify_password(&self, password: &str, hash: &str) -> Result<(), Error> {
+        hashing::verify_password(self.keyring.clone(), password, hash).await
+    }
+}
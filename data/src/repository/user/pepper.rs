@@ -0,0 +1,112 @@
+use std::{collections::HashMap, sync::Arc};
+
+use argon2::{
+    password_hash::PasswordHash, Algorithm, Argon2, KeyId, Params, ParamsBuilder, Version,
+};
+
+use crate::Error;
+
+/// Argon2 contexts by pepper (argon2 secret) id.
+///
+/// New hashes are made with the current pepper and carry its id in the PHC `keyid`
+/// param, so each hash is verified with the pepper it names. Hashes without `keyid`
+/// are verified with the unkeyed context, if there is one.
+#[derive(Debug, Clone)]
+pub struct PepperKeyring {
+    current: Arc<Argon2<'static>>,
+    keys: HashMap<Vec<u8>, Arc<Argon2<'static>>>,
+    unkeyed: Option<Arc<Argon2<'static>>>,
+}
+
+impl PepperKeyring {
+    /// Single context making hashes without key id, same as using `argon` directly.
+    pub fn unkeyed(argon: Argon2<'static>) -> Self {
+        let argon = Arc::new(argon);
+
+        Self {
+            current: argon.clone(),
+            keys: HashMap::new(),
+            unkeyed: Some(argon),
+        }
+    }
+
+    /// New hashes are made with `secret` and tagged with `id`, at most 8 bytes long.
+    pub fn new(
+        id: &str,
+        secret: &'static [u8],
+        algorithm: Algorithm,
+        version: Version,
+        params: Params,
+    ) -> Result<Self, Error> {
+        let key_id = KeyId::new(id.as_bytes()).map_err(|_| invalid_key_id(id))?;
+
+        let mut builder = ParamsBuilder::new();
+        builder
+            .m_cost(params.m_cost())
+            .t_cost(params.t_cost())
+            .p_cost(params.p_cost())
+            .keyid(key_id);
+        if let Some(output_len) = params.output_len() {
+            builder.output_len(output_len);
+        }
+        let params = builder.build().map_err(|_| invalid_key_id(id))?;
+
+        let argon = Arc::new(
+            Argon2::new_with_secret(secret, algorithm, version, params)
+                .map_err(|err| Error::InvalidArgument(err.to_string()))?,
+        );
+
+        Ok(Self {
+            current: argon.clone(),
+            keys: HashMap::from([(id.as_bytes().to_vec(), argon)]),
+            unkeyed: None,
+        })
+    }
+
+    /// Keeps a retired pepper for verification, its hashes are replaced on the next login.
+    pub fn with_retired(mut self, id: &str, secret: &'static [u8]) -> Result<Self, Error> {
+        KeyId::new(id.as_bytes()).map_err(|_| invalid_key_id(id))?;
+
+        if self.keys.contains_key(id.as_bytes()) {
+            return Err(Error::AlreadyExists(id.to_string()));
+        }
+
+        // algorithm, version and params are taken from the verified hash
+        let argon = Argon2::new_with_secret(
+            secret,
+            Algorithm::default(),
+            Version::default(),
+            Params::default(),
+        )
+        .map_err(|err| Error::InvalidArgument(err.to_string()))?;
+
+        self.keys.insert(id.as_bytes().to_vec(), Arc::new(argon));
+
+        Ok(self)
+    }
+
+    /// Verifies hashes made before peppers had ids with `argon`.
+    pub fn with_unkeyed(mut self, argon: Argon2<'static>) -> Self {
+        self.unkeyed = Some(Arc::new(argon));
+        self
+    }
+
+    pub(crate) fn current(&self) -> &Argon2<'static> {
+        &self.current
+    }
+
+    /// Context for the pepper `hash` was made with, `None` if it isn't known.
+    pub(crate) fn for_hash(&self, hash: &PasswordHash) -> Option<&Argon2<'static>> {
+        let params = Params::try_from(hash).ok()?;
+
+        if params.keyid().is_empty() {
+            self.unkeyed.as_deref()
+        } else {
+            self.keys.get(params.keyid()).map(|argon| argon.as_ref())
+        }
+    }
+}
+
+fn invalid_key_id(id: &str) -> Error {
+    Error::InvalidArgument(format!("pepper id {id} is longer than 8 bytes"))
+}
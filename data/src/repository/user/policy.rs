@@ -0,0 +1,193 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+use sha2::{Digest, Sha256};
+
+use crate::{model::user::PasswordViolation, Error};
+
+/// Rules new passwords are checked against, violations are reported all at once
+/// with [`Error::WeakPassword`].
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    /// In characters, not bytes.
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_lowercase: bool,
+    pub require_uppercase: bool,
+    pub require_digit: bool,
+    pub require_symbol: bool,
+    /// Reject the email, or its part before `@`, as password.
+    pub disallow_email: bool,
+    pub breached: Option<Arc<BreachedPasswords>>,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            disallow_email: true,
+            breached: None,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// Lists every rule `password` of the user with `email` breaks.
+    pub fn violations(&self, password: &str, email: &str) -> Vec<PasswordViolation> {
+        let mut violations = Vec::new();
+
+        let length = password.chars().count();
+
+        if length < self.min_length {
+            violations.push(PasswordViolation::TooShort {
+                min: self.min_length,
+            });
+        }
+
+        if length > self.max_length {
+            violations.push(PasswordViolation::TooLong {
+                max: self.max_length,
+            });
+        }
+
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            violations.push(PasswordViolation::MissingLowercase);
+        }
+
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            violations.push(PasswordViolation::MissingUppercase);
+        }
+
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push(PasswordViolation::MissingDigit);
+        }
+
+        if self.require_symbol && password.chars().all(char::is_alphanumeric) {
+            violations.push(PasswordViolation::MissingSymbol);
+        }
+
+        if self.disallow_email {
+            let password = password.to_lowercase();
+            let email = email.to_lowercase();
+            let local_part = email.split('@').next().unwrap_or_default();
+
+            if password == email || password == local_part {
+                violations.push(PasswordViolation::ContainsEmail);
+            }
+        }
+
+        if let Some(breached) = &self.breached {
+            if breached.contains(password) {
+                violations.push(PasswordViolation::Breached);
+            }
+        }
+
+        violations
+    }
+
+    pub(crate) fn check(&self, password: &str, email: &str) -> Result<(), Error> {
+        let violations = self.violations(password, email);
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::WeakPassword(violations))
+        }
+    }
+}
+
+/// Bloom filter of known breached passwords.
+///
+/// Lookups can give false positives (about 1 in 100), never false negatives.
+pub struct BreachedPasswords {
+    bits: Vec<u64>,
+    hashes: u32,
+}
+
+impl BreachedPasswords {
+    const FALSE_POSITIVE_RATE: f64 = 0.01;
+
+    pub fn from_passwords<I, S>(passwords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let digests: Vec<[u64; 2]> = passwords
+            .into_iter()
+            .map(|password| digest(password.as_ref()))
+            .collect();
+
+        // optimal size and hash count for the expected false positive rate
+        let count = digests.len().max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-count * Self::FALSE_POSITIVE_RATE.ln() / (ln2 * ln2)).ceil() as usize;
+        let hashes = ((bit_count as f64 / count) * ln2).round().max(1.0) as u32;
+
+        let mut filter = Self {
+            bits: vec![0; bit_count.div_ceil(64)],
+            hashes,
+        };
+
+        for digest in digests {
+            for bit in filter.bit_positions(digest) {
+                filter.bits[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+
+        filter
+    }
+
+    /// Reads one password per line, e.g. a breach corpus dump.
+    pub fn from_file(path: impl AsRef<Path>) -> io::Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let passwords = reader.lines().collect::<io::Result<Vec<String>>>()?;
+
+        Ok(Self::from_passwords(
+            passwords.iter().filter(|password| !password.is_empty()),
+        ))
+    }
+
+    pub fn contains(&self, password: &str) -> bool {
+        self.bit_positions(digest(password))
+            .all(|bit| self.bits[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    fn bit_positions(&self, [a, b]: [u64; 2]) -> impl Iterator<Item = usize> {
+        let bit_count = (self.bits.len() * 64) as u64;
+
+        // double hashing, odd step so positions don't repeat early
+        let step = b | 1;
+
+        (0..u64::from(self.hashes))
+            .map(move |i| (a.wrapping_add(i.wrapping_mul(step)) % bit_count) as usize)
+    }
+}
+
+impl fmt::Debug for BreachedPasswords {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BreachedPasswords")
+            .field("bits", &(self.bits.len() * 64))
+            .field("hashes", &self.hashes)
+            .finish()
+    }
+}
+
+fn digest(password: &str) -> [u64; 2] {
+    let digest = Sha256::digest(password.as_bytes());
+    let (a, b) = digest.split_at(8);
+
+    [
+        u64::from_le_bytes(a.try_into().unwrap_or_default()),
+        u64::from_le_bytes(b[..8].try_into().unwrap_or_default()),
+    ]
+}
@@ -1,32 +1,521 @@
-use std::sync::Arc;
+use std::{collections::HashSet, sync::Arc};
 
-use argon2::{
-    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
-};
-use sqlx::{PgPool, QueryBuilder, Row};
-use tokio::task;
+use argon2::Argon2;
+use chrono::{DateTime, Utc};
+use sqlx::{postgres::PgRow, PgPool, Postgres, QueryBuilder, Row, Transaction};
+use tokio::sync::OnceCell;
 use uuid::Uuid;
 
 use crate::{
-    model::user::{UpdateUser, User},
+    errors::map_sqlx_error,
+    model::{
+        email::Email,
+        page::{page_size, Page},
+        role::ADMIN_ROLE,
+        user::{
+            EmailChange, EmailChanged, HashReport, ImportUser, ListUsers, TotpEnrollment,
+            UpdateUser, User, UserCursor, UserFilter, UserSort,
+        },
+    },
+    token::{generate_token, hash_token},
     Error,
 };
 
-use super::UserRepository;
+use super::{
+    hashing,
+    pepper::PepperKeyring,
+    totp::{self, TotpSettings},
+    AccountSettings, ThrottleScope, TokenPurpose, UserRepository,
+};
 
 #[derive(Debug, Clone)]
 pub struct PostgresUserRepository {
     pub pool: PgPool,
-    argon: Arc<Argon2<'static>>,
+    keyring: Arc<PepperKeyring>,
+    reference_hash: Arc<OnceCell<String>>,
+    settings: AccountSettings,
 }
 
 impl PostgresUserRepository {
     pub fn new(pool: PgPool, argon: Argon2<'static>) -> Self {
         Self {
             pool,
-            argon: Arc::new(argon),
+            keyring: Arc::new(PepperKeyring::unkeyed(argon)),
+            reference_hash: Arc::new(OnceCell::new()),
+            settings: AccountSettings::default(),
+        }
+    }
+
+    pub fn with_account_settings(mut self, settings: AccountSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+
+    pub fn with_pepper_keyring(mut self, keyring: PepperKeyring) -> Self {
+        self.keyring = Arc::new(keyring);
+        self.reference_hash = Arc::new(OnceCell::new());
+        self
+    }
+
+    async fn verify_login(
+        &self,
+        email: &str,
+        password: &str,
+        source: Option<&str>,
+    ) -> Result<User, Error> {
+        // throttled by the address however it is typed
+        let email_key = Email::lookup_key(email);
+        let mut subjects = vec![(ThrottleScope::Account, email_key.as_str())];
+        if let Some(source) = source {
+            subjects.push((ThrottleScope::Source, source));
+        }
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        for (scope, subject) in &subjects {
+            self.ensure_not_locked(&mut tx, *scope, subject).await?;
+        }
+
+        let result = sqlx::query!(
+            r#"SELECT * FROM users WHERE lower(email COLLATE "C") = lower($1 COLLATE "C") AND deleted_at IS NULL;"#,
+            email.trim()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, email, Error::ReadError))?;
+
+        let verified = match &result {
+            Some(user) => self.verify_password(password, &user.hash).await,
+            None => {
+                hashing::verify_dummy_password(
+                    self.keyring.clone(),
+                    &self.reference_hash,
+                    password,
+                )
+                .await?;
+                // same answer as a wrong password, so logins don't reveal which emails exist
+                Err(Error::PasswordMismatch)
+            }
+        };
+
+        let second_factor = match verified {
+            Ok(()) => {
+                let second_factor = sqlx::query_scalar!(
+                    r#"
+                        SELECT EXISTS (
+                            SELECT 1 FROM user_totp
+                            WHERE user_id = $1 AND confirmed_at IS NOT NULL
+                        ) AS "exists!";
+                    "#,
+                    result.as_ref().map(|user| user.id)
+                )
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|err| map_sqlx_error(err, email, Error::ReadError))?;
+
+                // failures are kept until the second factor is passed as well
+                if !second_factor {
+                    self.clear_failed_logins(&mut tx, &email_key).await?;
+                }
+
+                second_factor
+            }
+            Err(err @ Error::PasswordMismatch) => {
+                for (scope, subject) in subjects {
+                    self.record_failed_login(&mut tx, scope, subject).await?;
+                }
+
+                tx.commit().await.map_err(Error::TransactionError)?;
+
+                return Err(err);
+            }
+            Err(err) => return Err(err),
+        };
+
+        let mut result = result.ok_or_else(|| Error::NotFound(email.to_string()))?;
+
+        // the plain password is only at hand here, so weak hashes are upgraded on login
+        let reference = hashing::reference_hash(self.keyring.clone(), &self.reference_hash).await?;
+
+        if hashing::needs_rehash(reference, &result.hash) {
+            let hash = self.hash_password(password).await?;
+
+            let rehashed = sqlx::query!(
+                r#"UPDATE users SET hash = $2 WHERE id = $1 RETURNING hash, updated_at;"#,
+                result.id,
+                hash
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, result.id, Error::WriteError))?;
+
+            result.hash = rehashed.hash;
+            result.updated_at = rehashed.updated_at;
+        }
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        if self.settings.require_verified_email && result.email_verified_at.is_none() {
+            return Err(Error::EmailNotVerified);
+        }
+
+        if second_factor {
+            return Err(Error::SecondFactorRequired(
+                self.create_login_challenge(&result.id).await?,
+            ));
+        }
+
+        Ok(User {
+            id: result.id,
+            email: result.email,
+            hash: result.hash,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
+        })
+    }
+
+    async fn ensure_not_locked(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        scope: ThrottleScope,
+        subject: &str,
+    ) -> Result<(), Error> {
+        let locked_until = sqlx::query_scalar!(
+            r#"
+                SELECT locked_until AS "locked_until!"
+                FROM login_throttle
+                WHERE scope = $1 AND subject = $2 AND locked_until > NOW();
+            "#,
+            scope.as_str(),
+            throttle_key(subject)
+        )
+        .fetch_optional(&mut **tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, subject, Error::ReadError))?;
+
+        match locked_until {
+            Some(locked_until) => Err(Error::LockedOut(locked_until)),
+            None => Ok(()),
+        }
+    }
+
+    async fn clear_failed_logins(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        email_key: &str,
+    ) -> Result<(), Error> {
+        sqlx::query!(
+            r#"DELETE FROM login_throttle WHERE scope = $1 AND subject = $2;"#,
+            ThrottleScope::Account.as_str(),
+            throttle_key(email_key)
+        )
+        .execute(&mut **tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, email_key, Error::WriteError))?;
+
+        Ok(())
+    }
+
+    /// Challenge a login waiting for the second factor is finished with, expired ones of
+    /// the user are dropped on the way.
+    async fn create_login_challenge(&self, user_id: &Uuid) -> Result<String, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        sqlx::query!(
+            r#"DELETE FROM login_challenges WHERE user_id = $1 AND expires_at <= NOW();"#,
+            user_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user_id, Error::WriteError))?;
+
+        let challenge = generate_token();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO login_challenges ( token_hash, user_id, expires_at )
+                VALUES ( $1, $2, NOW() + $3::interval );
+            "#,
+            hash_token(&challenge),
+            user_id,
+            self.settings.second_factor_ttl as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user_id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(challenge)
+    }
+
+    /// Second login step, failures count towards the account lockout and the challenge.
+    async fn verify_second_factor(
+        &self,
+        challenge: &str,
+        factor: SecondFactor<'_>,
+    ) -> Result<User, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let challenge_hash = hash_token(challenge);
+
+        let login = sqlx::query!(
+            r#"
+                SELECT user_id, failed_attempts
+                FROM login_challenges
+                WHERE token_hash = $1 AND expires_at > NOW()
+                FOR UPDATE;
+            "#,
+            challenge_hash
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "login challenge", Error::ReadError))?
+        .ok_or(Error::InvalidToken)?;
+
+        let id = &login.user_id;
+
+        let result = sqlx::query!(
+            r#"SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE;"#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?
+        .ok_or(Error::InvalidToken)?;
+
+        let email_key = Email::lookup_key(&result.email);
+
+        self.ensure_not_locked(&mut tx, ThrottleScope::Account, &email_key)
+            .await?;
+
+        let totp = sqlx::query!(
+            r#"
+                SELECT secret, last_used_step
+                FROM user_totp
+                WHERE user_id = $1 AND confirmed_at IS NOT NULL
+                FOR UPDATE;
+            "#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?
+        .ok_or_else(|| Error::InvalidArgument(format!("user {id} has no TOTP enabled")))?;
+
+        let accepted = match factor {
+            SecondFactor::Totp(code) => {
+                let settings = self.settings.totp()?;
+                let secret = settings.decrypt(id, &totp.secret)?;
+
+                // a code is only good once, and so are the ones before it
+                let step = settings
+                    .matching_step(secret, code)?
+                    .filter(|step| totp.last_used_step.is_none_or(|last| *step > last));
+
+                if let Some(step) = step {
+                    sqlx::query!(
+                        r#"UPDATE user_totp SET last_used_step = $2 WHERE user_id = $1;"#,
+                        id,
+                        step
+                    )
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+                }
+
+                step.is_some()
+            }
+            SecondFactor::RecoveryCode(code) => sqlx::query_scalar!(
+                r#"
+                    UPDATE user_recovery_codes
+                    SET used_at = NOW()
+                    WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+                    RETURNING user_id;
+                "#,
+                id,
+                hash_token(&totp::normalize_recovery_code(code))
+            )
+            .fetch_optional(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?
+            .is_some(),
+        };
+
+        if !accepted {
+            self.record_failed_login(&mut tx, ThrottleScope::Account, &email_key)
+                .await?;
+
+            // guessing is limited per challenge too, not only by the account lockout
+            if login.failed_attempts + 1 >= self.settings.max_second_factor_attempts as i32 {
+                sqlx::query!(
+                    r#"DELETE FROM login_challenges WHERE token_hash = $1;"#,
+                    challenge_hash
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+            } else {
+                sqlx::query!(
+                    r#"
+                        UPDATE login_challenges
+                        SET failed_attempts = failed_attempts + 1
+                        WHERE token_hash = $1;
+                    "#,
+                    challenge_hash
+                )
+                .execute(&mut *tx)
+                .await
+                .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+            }
+
+            tx.commit().await.map_err(Error::TransactionError)?;
+
+            return Err(Error::InvalidCode);
+        }
+
+        // a challenge finishes one login
+        sqlx::query!(
+            r#"DELETE FROM login_challenges WHERE token_hash = $1;"#,
+            challenge_hash
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        self.clear_failed_logins(&mut tx, &email_key).await?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(User {
+            id: result.id,
+            email: result.email,
+            hash: result.hash,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
+        })
+    }
+
+    async fn record_failed_login(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        scope: ThrottleScope,
+        subject: &str,
+    ) -> Result<(), Error> {
+        let lockout = &self.settings.lockout;
+        let key = throttle_key(subject);
+
+        let failed_attempts = sqlx::query_scalar!(
+            r#"
+                INSERT INTO login_throttle ( scope, subject, failed_attempts )
+                VALUES ( $1, $2, 1 )
+                ON CONFLICT ( scope, subject ) DO UPDATE
+                SET failed_attempts = CASE
+                        WHEN login_throttle.last_failed_at <= NOW() - $3::interval THEN 1
+                        ELSE login_throttle.failed_attempts + 1
+                    END,
+                    last_failed_at = NOW()
+                RETURNING failed_attempts;
+            "#,
+            scope.as_str(),
+            key,
+            lockout.window as _
+        )
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, subject, Error::WriteError))?;
+
+        if let Some(duration) = lockout.lockout_for(scope, failed_attempts) {
+            sqlx::query!(
+                r#"
+                    UPDATE login_throttle
+                    SET locked_until = NOW() + $3::interval
+                    WHERE scope = $1 AND subject = $2;
+                "#,
+                scope.as_str(),
+                key,
+                duration as _
+            )
+            .execute(&mut **tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, subject, Error::WriteError))?;
         }
+
+        Ok(())
+    }
+}
+
+enum SecondFactor<'a> {
+    Totp(&'a str),
+    RecoveryCode(&'a str),
+}
+
+fn user_from_row(row: &PgRow) -> User {
+    User {
+        id: row.get("id"),
+        email: row.get("email"),
+        hash: row.get("hash"),
+        created_at: row.get("created_at"),
+        updated_at: row.get("updated_at"),
+        email_verified_at: row.get("email_verified_at"),
+        deleted_at: row.get("deleted_at"),
+    }
+}
+
+/// Emails and sources are stored by digest, so a throttled subject of any length fits.
+fn throttle_key(subject: &str) -> String {
+    hash_token(subject)
+}
+
+fn push_user_filters(builder: &mut QueryBuilder<'_, Postgres>, filter: &UserFilter) {
+    if !filter.include_deleted {
+        builder.push(" AND deleted_at IS NULL");
+    }
+
+    if let Some(email) = &filter.email_contains {
+        let escaped = email
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        builder.push(" AND email ILIKE ");
+        builder.push_bind(format!("%{escaped}%"));
+    }
+
+    if let Some(role) = &filter.role {
+        builder.push(
+            " AND EXISTS (SELECT 1 FROM user_roles ur WHERE ur.user_id = users.id AND ur.role = ",
+        );
+        builder.push_bind(role.clone());
+        builder.push(")");
+    }
+
+    if let Some(created_after) = filter.created_after {
+        builder.push(" AND created_at >= ");
+        builder.push_bind(created_after);
+    }
+
+    if let Some(created_before) = filter.created_before {
+        builder.push(" AND created_at < ");
+        builder.push_bind(created_before);
     }
 }
 
@@ -39,10 +528,13 @@ impl UserRepository for PostgresUserRepository {
             .await
             .map_err(Error::TransactionError)?;
 
-        let result = sqlx::query!(r#"SELECT * FROM users WHERE id = $1;"#, id)
-            .fetch_one(&mut *tx)
-            .await
-            .map_err(Error::ReadError)?;
+        let result = sqlx::query!(
+            r#"SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL;"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
 
         tx.commit().await.map_err(Error::TransactionError)?;
 
@@ -50,13 +542,21 @@ impl UserRepository for PostgresUserRepository {
             id: result.id,
             email: result.email,
             hash: result.hash,
-            is_admin: result.is_admin,
             created_at: result.created_at,
             updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
         })
     }
 
-    async fn list_users(&self) -> Result<Vec<User>, Error> {
+    async fn list_users(&self, query: ListUsers) -> Result<Page<User>, Error> {
+        let size = page_size(query.limit);
+        let cursor = query
+            .cursor
+            .as_deref()
+            .map(|cursor| UserCursor::decode(cursor, query.sort))
+            .transpose()?;
+
         let mut tx = self
             .pool
             .clone()
@@ -64,29 +564,94 @@ impl UserRepository for PostgresUserRepository {
             .await
             .map_err(Error::TransactionError)?;
 
-        let result = sqlx::query!(r#"SELECT * FROM users;"#)
+        let mut builder = QueryBuilder::new("SELECT * FROM users WHERE TRUE");
+
+        push_user_filters(&mut builder, &query.filter);
+
+        let comparison = if query.sort.is_desc() { " < " } else { " > " };
+
+        match cursor {
+            Some(UserCursor::CreatedAt(created_at, id)) => {
+                builder.push(" AND (created_at, id)");
+                builder.push(comparison);
+                builder.push("(");
+                builder.push_bind(created_at);
+                builder.push(", ");
+                builder.push_bind(id);
+                builder.push(")");
+            }
+            Some(UserCursor::Email(email, id)) => {
+                // byte order like the mock, whatever the database's default collation
+                builder.push(r#" AND (email COLLATE "C", id)"#);
+                builder.push(comparison);
+                builder.push("(");
+                builder.push_bind(email);
+                builder.push(r#" COLLATE "C", "#);
+                builder.push_bind(id);
+                builder.push(")");
+            }
+            None => {}
+        }
+
+        builder.push(match query.sort {
+            UserSort::CreatedAtAsc => " ORDER BY created_at ASC, id ASC",
+            UserSort::CreatedAtDesc => " ORDER BY created_at DESC, id DESC",
+            UserSort::EmailAsc => r#" ORDER BY email COLLATE "C" ASC, id ASC"#,
+            UserSort::EmailDesc => r#" ORDER BY email COLLATE "C" DESC, id DESC"#,
+        });
+
+        // one extra row tells if there is a next page
+        builder.push(" LIMIT ");
+        builder.push_bind(i64::from(size) + 1);
+
+        let rows = builder
+            .build()
             .fetch_all(&mut *tx)
             .await
-            .map_err(Error::ReadError)?;
+            .map_err(|err| map_sqlx_error(err, "users", Error::ReadError))?;
+
+        let total = if query.with_total {
+            let mut builder = QueryBuilder::new("SELECT COUNT(*) FROM users WHERE TRUE");
+
+            push_user_filters(&mut builder, &query.filter);
+
+            let row = builder
+                .build()
+                .fetch_one(&mut *tx)
+                .await
+                .map_err(|err| map_sqlx_error(err, "users", Error::ReadError))?;
+
+            Some(row.get::<i64, _>(0))
+        } else {
+            None
+        };
 
         tx.commit().await.map_err(Error::TransactionError)?;
 
-        let users: Vec<User> = result
-            .into_iter()
-            .map(|value| User {
-                id: value.id,
-                email: value.email,
-                hash: value.hash,
-                is_admin: value.is_admin,
-                created_at: value.created_at,
-                updated_at: value.updated_at,
-            })
-            .collect();
+        let mut users: Vec<User> = rows.iter().map(user_from_row).collect();
+
+        let next_cursor = if users.len() > size as usize {
+            users.truncate(size as usize);
+            users
+                .last()
+                .map(|user| UserCursor::after(user, query.sort).encode())
+        } else {
+            None
+        };
 
-        Ok(users)
+        Ok(Page {
+            items: users,
+            next_cursor,
+            total,
+        })
     }
 
     async fn create_user(&self, email: &str, password: &str) -> Result<User, Error> {
+        let email = Email::parse(email)?;
+        self.settings
+            .password_policy
+            .check(password, email.as_str())?;
+
         let mut tx = self
             .pool
             .clone()
@@ -109,12 +674,12 @@ impl UserRepository for PostgresUserRepository {
                 RETURNING *;
             "#,
             new_id,
-            email,
+            email.as_str(),
             hash
         )
         .fetch_one(&mut *tx)
         .await
-        .map_err(Error::ReadError)?;
+        .map_err(|err| map_sqlx_error(err, &email, Error::WriteError))?;
 
         tx.commit().await.map_err(Error::TransactionError)?;
 
@@ -122,13 +687,29 @@ impl UserRepository for PostgresUserRepository {
             id: result.id,
             email: result.email,
             hash: result.hash,
-            is_admin: result.is_admin,
             created_at: result.created_at,
             updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
         })
     }
 
-    async fn update_user(&self, id: &Uuid, update: UpdateUser) -> Result<User, Error> {
+    async fn import_users(&self, users: Vec<ImportUser>) -> Result<Vec<User>, Error> {
+        if let Some(user) = users
+            .iter()
+            .find(|user| !hashing::is_supported_hash(&user.hash))
+        {
+            return Err(Error::InvalidArgument(format!(
+                "unsupported password hash of {}",
+                user.email
+            )));
+        }
+
+        let emails = users
+            .iter()
+            .map(|user| Email::parse(&user.email))
+            .collect::<Result<Vec<_>, _>>()?;
+
         let mut tx = self
             .pool
             .clone()
@@ -136,47 +717,145 @@ impl UserRepository for PostgresUserRepository {
             .await
             .map_err(Error::TransactionError)?;
 
-        let mut builder = QueryBuilder::new("UPDATE users SET ");
+        let role_names: Vec<String> = users
+            .iter()
+            .flat_map(|user| user.roles.iter().cloned())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        let known_roles = sqlx::query_scalar!(
+            r#"SELECT name FROM roles WHERE name = ANY($1);"#,
+            &role_names
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "roles", Error::ReadError))?;
+
+        if let Some(unknown) = role_names.iter().find(|name| !known_roles.contains(name)) {
+            return Err(Error::NotFound(unknown.clone()));
+        }
+
+        let mut imported = Vec::with_capacity(users.len());
 
-        let mut has_update = false;
+        for (user, email) in users.into_iter().zip(emails) {
+            let result = sqlx::query!(
+                r#"
+                    INSERT INTO users ( id, email, hash, created_at, email_verified_at )
+                    VALUES (
+                        $1,
+                        $2,
+                        $3,
+                        COALESCE($4, NOW()),
+                        $5
+                    )
+                    RETURNING *;
+                "#,
+                Uuid::new_v4(),
+                email.as_str(),
+                user.hash,
+                user.created_at,
+                user.email_verified_at
+            )
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, &email, Error::WriteError))?;
+
+            sqlx::query!(
+                r#"
+                    INSERT INTO user_roles ( user_id, role )
+                    SELECT $1, role FROM UNNEST($2::varchar[]) AS role
+                    ON CONFLICT DO NOTHING;
+                "#,
+                result.id,
+                &user.roles
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, &email, Error::WriteError))?;
 
-        if let Some(email) = update.email {
-            builder.push("email = ");
-            builder.push_bind(email);
-            has_update = true;
+            imported.push(User {
+                id: result.id,
+                email: result.email,
+                hash: result.hash,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+                email_verified_at: result.email_verified_at,
+                deleted_at: result.deleted_at,
+            });
         }
 
-        if let Some(password_set) = update.password {
-            if has_update {
-                builder.push(", ");
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(imported)
+    }
+
+    async fn update_user(&self, id: &Uuid, update: UpdateUser) -> Result<User, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let current = sqlx::query!(
+            r#"SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE;"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        if let Some(expected) = update.expected_updated_at {
+            if expected != current.updated_at {
+                return Err(Error::Conflict(id.to_string()));
             }
-            let hash = self.hash_password(&password_set.new_password).await?;
-            builder.push("hash = ");
-            builder.push_bind(hash);
         }
 
-        builder.push(" WHERE id = ");
-        builder.push_bind(id);
+        let Some(password_set) = update.password else {
+            tx.commit().await.map_err(Error::TransactionError)?;
 
-        builder.push(" RETURNING *");
+            return Ok(User {
+                id: current.id,
+                email: current.email,
+                hash: current.hash,
+                created_at: current.created_at,
+                updated_at: current.updated_at,
+                email_verified_at: current.email_verified_at,
+                deleted_at: current.deleted_at,
+            });
+        };
 
-        let query = builder.build();
+        self.verify_password(&password_set.old_password, &current.hash)
+            .await?;
+        self.settings
+            .password_policy
+            .check(&password_set.new_password, &current.email)?;
+        let hash = self.hash_password(&password_set.new_password).await?;
 
-        let row = query.fetch_one(&mut *tx).await.map_err(Error::ReadError)?;
+        let result = sqlx::query!(
+            r#"UPDATE users SET hash = $2 WHERE id = $1 RETURNING *;"#,
+            id,
+            hash
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
 
         tx.commit().await.map_err(Error::TransactionError)?;
 
         Ok(User {
-            id: row.get("id"),
-            email: row.get("email"),
-            hash: row.get("hash"),
-            is_admin: row.get("is_admin"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
+            id: result.id,
+            email: result.email,
+            hash: result.hash,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
         })
     }
 
-    async fn delete_user(&self, id: &Uuid) -> Result<User, Error> {
+    async fn reset_user_password(&self, id: &Uuid, new_password: &str) -> Result<User, Error> {
         let mut tx = self
             .pool
             .clone()
@@ -184,17 +863,31 @@ impl UserRepository for PostgresUserRepository {
             .await
             .map_err(Error::TransactionError)?;
 
+        let email = sqlx::query_scalar!(
+            r#"SELECT email FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE;"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        self.settings.password_policy.check(new_password, &email)?;
+
+        let hash = self.hash_password(new_password).await?;
+
         let result = sqlx::query!(
             r#"
-                DELETE FROM users
+                UPDATE users
+                SET hash = $2
                 WHERE id = $1
                 RETURNING *;
             "#,
-            id
+            id,
+            hash
         )
         .fetch_one(&mut *tx)
         .await
-        .map_err(Error::ReadError)?;
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
 
         tx.commit().await.map_err(Error::TransactionError)?;
 
@@ -202,13 +895,18 @@ impl UserRepository for PostgresUserRepository {
             id: result.id,
             email: result.email,
             hash: result.hash,
-            is_admin: result.is_admin,
             created_at: result.created_at,
             updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
         })
     }
 
-    async fn verify_user_password(&self, email: &str, password: &str) -> Result<(), Error> {
+    async fn delete_user(
+        &self,
+        id: &Uuid,
+        expected_updated_at: Option<DateTime<Utc>>,
+    ) -> Result<User, Error> {
         let mut tx = self
             .pool
             .clone()
@@ -216,44 +914,870 @@ impl UserRepository for PostgresUserRepository {
             .await
             .map_err(Error::TransactionError)?;
 
-        let result = sqlx::query!(r#"SELECT * FROM users WHERE email = $1;"#, email)
+        if let Some(expected) = expected_updated_at {
+            let current = sqlx::query!(
+                r#"SELECT updated_at FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE;"#,
+                id
+            )
             .fetch_one(&mut *tx)
             .await
-            .map_err(Error::ReadError)?;
+            .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
 
-        tx.commit().await.map_err(Error::TransactionError)?;
+            if expected != current.updated_at {
+                return Err(Error::Conflict(id.to_string()));
+            }
+        }
 
-        self.verify_password(password, &result.hash).await
+        // same check as revoking the role, a deleted admin doesn't count
+        let admins = sqlx::query_scalar!(
+            r#"
+                SELECT ur.user_id
+                FROM user_roles ur
+                JOIN users u ON u.id = ur.user_id
+                WHERE ur.role = $1 AND u.deleted_at IS NULL
+                FOR UPDATE OF ur;
+            "#,
+            ADMIN_ROLE
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, ADMIN_ROLE, Error::ReadError))?;
+
+        if admins.len() == 1 && admins[0] == *id {
+            return Err(Error::InvalidArgument(format!(
+                "{id} is the last {ADMIN_ROLE}"
+            )));
+        }
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET deleted_at = NOW()
+                WHERE id = $1 AND deleted_at IS NULL
+                RETURNING *;
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        // a restored user has to log in again and ask for new tokens
+        sqlx::query!(
+            r#"
+                DELETE FROM user_tokens
+                WHERE user_id = $1 AND consumed_at IS NULL;
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        sqlx::query!(
+            r#"
+                UPDATE sessions
+                SET revoked_at = NOW()
+                WHERE user_id = $1 AND revoked_at IS NULL;
+            "#,
+            id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(User {
+            id: result.id,
+            email: result.email,
+            hash: result.hash,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
+        })
     }
 
-    async fn hash_password(&self, password: &str) -> Result<String, Error> {
-        let pass = password.to_owned();
-        let argon = self.argon.clone();
+    async fn restore_user(&self, id: &Uuid) -> Result<User, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET deleted_at = NULL
+                WHERE id = $1 AND deleted_at > NOW() - $2::interval
+                RETURNING *;
+            "#,
+            id,
+            self.settings.deletion_grace_period as _
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
 
-        task::spawn_blocking(move || {
-            let salt = SaltString::generate(&mut OsRng);
+        tx.commit().await.map_err(Error::TransactionError)?;
 
-            argon
-                .hash_password(pass.as_bytes(), &salt)
-                .map(|value| value.to_string())
-                .map_err(|_| Error::Hash)
+        Ok(User {
+            id: result.id,
+            email: result.email,
+            hash: result.hash,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
         })
+    }
+
+    async fn purge_deleted_users(&self) -> Result<u64, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        // sessions, tokens, keys and so on go with the user by ON DELETE CASCADE
+        let result = sqlx::query!(
+            r#"DELETE FROM users WHERE deleted_at <= NOW() - $1::interval;"#,
+            self.settings.deletion_grace_period as _
+        )
+        .execute(&mut *tx)
         .await
-        .map_err(|_| Error::SpawnTask)?
+        .map_err(|err| map_sqlx_error(err, "users", Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(result.rows_affected())
     }
 
-    async fn verify_password(&self, password: &str, hash: &str) -> Result<(), Error> {
-        let argon = self.argon.clone();
-        let password = password.to_owned();
-        let hash = hash.to_owned();
-
-        task::spawn_blocking(move || {
-            let parsed_hash = PasswordHash::new(&hash).map_err(|_| Error::Hash)?;
-            argon
-                .verify_password(password.as_bytes(), &parsed_hash)
-                .map_err(|_| Error::Hash)
+    async fn issue_email_verification(&self, id: &Uuid) -> Result<String, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let user = sqlx::query!(
+            r#"
+                SELECT email, email_verified_at
+                FROM users
+                WHERE id = $1 AND deleted_at IS NULL
+                FOR UPDATE;
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        if user.email_verified_at.is_some() {
+            return Err(Error::InvalidArgument(format!(
+                "email {} is already verified",
+                user.email
+            )));
+        }
+
+        sqlx::query!(
+            r#"
+                DELETE FROM user_tokens
+                WHERE user_id = $1 AND purpose = $2 AND consumed_at IS NULL;
+            "#,
+            id,
+            TokenPurpose::EmailVerification.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        let token = generate_token();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_tokens ( token_hash, user_id, purpose, email, expires_at )
+                VALUES (
+                    $1,
+                    $2,
+                    $3,
+                    $4,
+                    NOW() + $5::interval
+                );
+            "#,
+            hash_token(&token),
+            id,
+            TokenPurpose::EmailVerification.as_str(),
+            user.email,
+            self.settings.email_verification_ttl as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(token)
+    }
+
+    async fn verify_email(&self, token: &str) -> Result<User, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let stored = sqlx::query!(
+            r#"
+                UPDATE user_tokens
+                SET consumed_at = NOW()
+                WHERE token_hash = $1
+                    AND purpose = $2
+                    AND consumed_at IS NULL
+                    AND expires_at > NOW()
+                RETURNING user_id, email;
+            "#,
+            hash_token(token),
+            TokenPurpose::EmailVerification.as_str()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "email verification", Error::WriteError))?
+        .ok_or(Error::InvalidToken)?;
+
+        // the token proves ownership of the address it was sent to only
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET email_verified_at = COALESCE(email_verified_at, NOW())
+                WHERE id = $1 AND lower(email COLLATE "C") = lower($2 COLLATE "C")
+                RETURNING *;
+            "#,
+            stored.user_id,
+            stored.email
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, stored.user_id, Error::WriteError))?
+        .ok_or(Error::InvalidToken)?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(User {
+            id: result.id,
+            email: result.email,
+            hash: result.hash,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
+        })
+    }
+
+    async fn request_email_change(
+        &self,
+        id: &Uuid,
+        new_email: &str,
+        password: &str,
+    ) -> Result<EmailChange, Error> {
+        let new_email = Email::parse(new_email)?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let user = sqlx::query!(
+            r#"SELECT email, hash FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE;"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        self.verify_password(password, &user.hash).await?;
+
+        let taken_by = sqlx::query_scalar!(
+            r#"SELECT id FROM users WHERE lower(email COLLATE "C") = lower($1 COLLATE "C");"#,
+            new_email.as_str()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, &new_email, Error::ReadError))?;
+
+        match taken_by {
+            Some(owner) if owner == *id => {
+                return Err(Error::InvalidArgument(format!(
+                    "email {new_email} is already the current one"
+                )));
+            }
+            Some(_) => return Err(Error::AlreadyExists(new_email.to_string())),
+            None => {}
+        }
+
+        sqlx::query!(
+            r#"
+                DELETE FROM user_tokens
+                WHERE user_id = $1 AND purpose = $2 AND consumed_at IS NULL;
+            "#,
+            id,
+            TokenPurpose::EmailChange.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        let token = generate_token();
+
+        // the token carries the requested address, the user row keeps the current one
+        let expires_at = sqlx::query_scalar!(
+            r#"
+                INSERT INTO user_tokens ( token_hash, user_id, purpose, email, expires_at )
+                VALUES (
+                    $1,
+                    $2,
+                    $3,
+                    $4,
+                    NOW() + $5::interval
+                )
+                RETURNING expires_at;
+            "#,
+            hash_token(&token),
+            id,
+            TokenPurpose::EmailChange.as_str(),
+            new_email.as_str(),
+            self.settings.email_change_ttl as _
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(EmailChange {
+            user_id: *id,
+            current_email: user.email,
+            new_email: new_email.into(),
+            token,
+            expires_at,
+        })
+    }
+
+    async fn confirm_email_change(&self, token: &str) -> Result<EmailChanged, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let stored = sqlx::query!(
+            r#"
+                UPDATE user_tokens
+                SET consumed_at = NOW()
+                WHERE token_hash = $1
+                    AND purpose = $2
+                    AND consumed_at IS NULL
+                    AND expires_at > NOW()
+                RETURNING user_id, email;
+            "#,
+            hash_token(token),
+            TokenPurpose::EmailChange.as_str()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "email change", Error::WriteError))?
+        .ok_or(Error::InvalidToken)?;
+
+        let previous_email = sqlx::query_scalar!(
+            r#"SELECT email FROM users WHERE id = $1 FOR UPDATE;"#,
+            stored.user_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, stored.user_id, Error::ReadError))?;
+
+        // the address may have been taken since the request
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET email = $2, email_verified_at = NOW()
+                WHERE id = $1
+                RETURNING *;
+            "#,
+            stored.user_id,
+            stored.email
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, &stored.email, Error::WriteError))?;
+
+        // tokens sent to the previous address don't prove anything anymore
+        sqlx::query!(
+            r#"
+                DELETE FROM user_tokens
+                WHERE user_id = $1 AND consumed_at IS NULL;
+            "#,
+            result.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, result.id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(EmailChanged {
+            user: User {
+                id: result.id,
+                email: result.email,
+                hash: result.hash,
+                created_at: result.created_at,
+                updated_at: result.updated_at,
+                email_verified_at: result.email_verified_at,
+                deleted_at: result.deleted_at,
+            },
+            previous_email,
+        })
+    }
+
+    async fn cancel_email_change(&self, id: &Uuid) -> Result<(), Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        sqlx::query!(
+            r#"SELECT id FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE;"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM user_tokens
+                WHERE user_id = $1 AND purpose = $2 AND consumed_at IS NULL;
+            "#,
+            id,
+            TokenPurpose::EmailChange.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn issue_password_reset(&self, email: &str) -> Result<Option<String>, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let user = sqlx::query!(
+            r#"
+                SELECT id, email
+                FROM users
+                WHERE lower(email COLLATE "C") = lower($1 COLLATE "C") AND deleted_at IS NULL
+                FOR UPDATE;
+            "#,
+            email.trim()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, email, Error::ReadError))?;
+
+        let Some(user) = user else {
+            tx.commit().await.map_err(Error::TransactionError)?;
+            return Ok(None);
+        };
+
+        sqlx::query!(
+            r#"
+                DELETE FROM user_tokens
+                WHERE user_id = $1 AND purpose = $2 AND consumed_at IS NULL;
+            "#,
+            user.id,
+            TokenPurpose::PasswordReset.as_str()
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user.id, Error::WriteError))?;
+
+        let token = generate_token();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_tokens ( token_hash, user_id, purpose, email, expires_at )
+                VALUES (
+                    $1,
+                    $2,
+                    $3,
+                    $4,
+                    NOW() + $5::interval
+                );
+            "#,
+            hash_token(&token),
+            user.id,
+            TokenPurpose::PasswordReset.as_str(),
+            user.email,
+            self.settings.password_reset_ttl as _
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, user.id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(Some(token))
+    }
+
+    async fn reset_password(&self, token: &str, new_password: &str) -> Result<User, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let stored = sqlx::query!(
+            r#"
+                UPDATE user_tokens
+                SET consumed_at = NOW()
+                WHERE token_hash = $1
+                    AND purpose = $2
+                    AND consumed_at IS NULL
+                    AND expires_at > NOW()
+                RETURNING user_id, email;
+            "#,
+            hash_token(token),
+            TokenPurpose::PasswordReset.as_str()
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "password reset", Error::WriteError))?
+        .ok_or(Error::InvalidToken)?;
+
+        self.settings
+            .password_policy
+            .check(new_password, &stored.email)?;
+
+        let hash = self.hash_password(new_password).await?;
+
+        // the address may have changed since the token was sent
+        let result = sqlx::query!(
+            r#"
+                UPDATE users
+                SET hash = $3
+                WHERE id = $1 AND lower(email COLLATE "C") = lower($2 COLLATE "C")
+                RETURNING *;
+            "#,
+            stored.user_id,
+            stored.email,
+            hash
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, stored.user_id, Error::WriteError))?
+        .ok_or(Error::InvalidToken)?;
+
+        sqlx::query!(
+            r#"
+                DELETE FROM user_tokens
+                WHERE user_id = $1 AND consumed_at IS NULL;
+            "#,
+            result.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, result.id, Error::WriteError))?;
+
+        sqlx::query!(
+            r#"
+                UPDATE sessions
+                SET revoked_at = NOW()
+                WHERE user_id = $1 AND revoked_at IS NULL;
+            "#,
+            result.id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, result.id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(User {
+            id: result.id,
+            email: result.email,
+            hash: result.hash,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
+        })
+    }
+
+    async fn verify_user_password(&self, email: &str, password: &str) -> Result<User, Error> {
+        self.verify_login(email, password, None).await
+    }
+
+    async fn verify_user_password_from(
+        &self,
+        email: &str,
+        password: &str,
+        source: &str,
+    ) -> Result<User, Error> {
+        self.verify_login(email, password, Some(source)).await
+    }
+
+    async fn outdated_hash_report(&self) -> Result<HashReport, Error> {
+        let reference = hashing::reference_hash(self.keyring.clone(), &self.reference_hash).await?;
+        let settings = hashing::hash_settings(reference).ok_or(Error::Hash)?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"
+                SELECT
+                    COUNT(*) AS "total!",
+                    COUNT(*) FILTER (WHERE NOT starts_with(hash, $1)) AS "outdated!"
+                FROM users
+                WHERE deleted_at IS NULL;
+            "#,
+            settings
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, "users", Error::ReadError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(HashReport {
+            total: result.total,
+            outdated: result.outdated,
+        })
+    }
+
+    async fn unlock_user(&self, id: &Uuid) -> Result<User, Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let result = sqlx::query!(
+            r#"SELECT * FROM users WHERE id = $1 AND deleted_at IS NULL;"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        self.clear_failed_logins(&mut tx, &Email::lookup_key(&result.email))
+            .await?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(User {
+            id: result.id,
+            email: result.email,
+            hash: result.hash,
+            created_at: result.created_at,
+            updated_at: result.updated_at,
+            email_verified_at: result.email_verified_at,
+            deleted_at: result.deleted_at,
         })
+    }
+
+    async fn enroll_totp(&self, id: &Uuid) -> Result<TotpEnrollment, Error> {
+        let settings = self.settings.totp()?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let email = sqlx::query_scalar!(
+            r#"SELECT email FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE;"#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        let confirmed_at = sqlx::query_scalar!(
+            r#"SELECT confirmed_at FROM user_totp WHERE user_id = $1;"#,
+            id
+        )
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?
+        .flatten();
+
+        if confirmed_at.is_some() {
+            return Err(Error::InvalidArgument(format!(
+                "user {id} has TOTP enabled already"
+            )));
+        }
+
+        let secret = TotpSettings::generate_secret();
+        let enrollment = settings.enrollment(secret.clone(), &email)?;
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_totp ( user_id, secret )
+                VALUES ( $1, $2 )
+                ON CONFLICT ( user_id ) DO UPDATE
+                SET secret = EXCLUDED.secret, last_used_step = NULL, created_at = NOW();
+            "#,
+            id,
+            settings.encrypt(id, &secret)?
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(enrollment)
+    }
+
+    async fn confirm_totp(&self, id: &Uuid, code: &str) -> Result<Vec<String>, Error> {
+        let settings = self.settings.totp()?;
+
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let totp = sqlx::query!(
+            r#"
+                SELECT t.secret, t.confirmed_at
+                FROM user_totp t
+                JOIN users u ON u.id = t.user_id
+                WHERE t.user_id = $1 AND u.deleted_at IS NULL
+                FOR UPDATE OF t;
+            "#,
+            id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        if totp.confirmed_at.is_some() {
+            return Err(Error::InvalidArgument(format!(
+                "user {id} has TOTP enabled already"
+            )));
+        }
+
+        let step = settings
+            .matching_step(settings.decrypt(id, &totp.secret)?, code)?
+            .ok_or(Error::InvalidCode)?;
+
+        sqlx::query!(
+            r#"
+                UPDATE user_totp
+                SET confirmed_at = NOW(), last_used_step = $2
+                WHERE user_id = $1;
+            "#,
+            id,
+            step
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        let codes = totp::generate_recovery_codes();
+        let hashes: Vec<String> = codes
+            .iter()
+            .map(|code| hash_token(&totp::normalize_recovery_code(code)))
+            .collect();
+
+        sqlx::query!(
+            r#"
+                INSERT INTO user_recovery_codes ( user_id, code_hash )
+                SELECT $1, UNNEST($2::VARCHAR[]);
+            "#,
+            id,
+            &hashes
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(codes)
+    }
+
+    async fn verify_totp(&self, challenge: &str, code: &str) -> Result<User, Error> {
+        self.verify_second_factor(challenge, SecondFactor::Totp(code))
+            .await
+    }
+
+    async fn verify_recovery_code(&self, challenge: &str, code: &str) -> Result<User, Error> {
+        self.verify_second_factor(challenge, SecondFactor::RecoveryCode(code))
+            .await
+    }
+
+    async fn disable_totp(&self, id: &Uuid, password: &str) -> Result<(), Error> {
+        let mut tx = self
+            .pool
+            .clone()
+            .begin()
+            .await
+            .map_err(Error::TransactionError)?;
+
+        let hash = sqlx::query_scalar!(
+            r#"SELECT hash FROM users WHERE id = $1 AND deleted_at IS NULL FOR UPDATE;"#,
+            id
+        )
+        .fetch_one(&mut *tx)
         .await
-        .map_err(|_| Error::SpawnTask)?
+        .map_err(|err| map_sqlx_error(err, id, Error::ReadError))?;
+
+        self.verify_password(password, &hash).await?;
+
+        // recovery codes go with it
+        sqlx::query!(r#"DELETE FROM user_totp WHERE user_id = $1;"#, id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| map_sqlx_error(err, id, Error::WriteError))?;
+
+        tx.commit().await.map_err(Error::TransactionError)?;
+
+        Ok(())
+    }
+
+    async fn hash_password(&self, password: &str) -> Result<String, Error> {
+        hashing::hash_password(self.keyring.clone(), password).await
+    }
+
+    async fn verify_password(&self, password: &str, hash: &str) -> Result<(), Error> {
+        hashing::verify_password(self.keyring.clone(), password, hash).await
     }
 }
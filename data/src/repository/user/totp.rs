@@ -0,0 +1,195 @@
+use std::fmt;
+
+use aes_gcm::{
+    aead::{Aead, KeyInit, Payload},
+    Aes256Gcm, Nonce,
+};
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::Utc;
+use subtle::ConstantTimeEq;
+use totp_rs::{Algorithm, TOTP};
+use uuid::Uuid;
+
+use crate::{model::user::TotpEnrollment, Error};
+
+const SECRET_BYTES: usize = 20;
+const NONCE_BYTES: usize = 12;
+const DIGITS: usize = 6;
+const STEP_SECONDS: u64 = 30;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_CHARS: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+/// 5 bits each, 140 in all: codes are hashed unsalted, so they must not be guessable.
+const RECOVERY_CODE_LENGTH: usize = 28;
+const RECOVERY_CODE_GROUP: usize = 7;
+
+/// TOTP (RFC 6238) second factor: 6 digits, 30 second steps, SHA-1, which is what
+/// authenticator apps support.
+///
+/// Secrets are encrypted at rest with AES-256-GCM under `key`, bound to the user id.
+#[derive(Clone)]
+pub struct TotpSettings {
+    issuer: String,
+    cipher: Aes256Gcm,
+    skew: u8,
+}
+
+impl TotpSettings {
+    /// `issuer` names the service in authenticator apps, `key` has to be 32 bytes.
+    pub fn new(issuer: &str, key: &[u8]) -> Result<Self, Error> {
+        if issuer.contains(':') {
+            return Err(Error::InvalidArgument(format!(
+                "TOTP issuer {issuer} contains ':'"
+            )));
+        }
+
+        let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| {
+            Error::InvalidArgument(format!("TOTP key has to be 32 bytes, got {}", key.len()))
+        })?;
+
+        Ok(Self {
+            issuer: issuer.to_string(),
+            cipher,
+            skew: 1,
+        })
+    }
+
+    /// Steps before and after the current one whose codes are accepted too, 1 by default.
+    pub fn with_skew(mut self, skew: u8) -> Self {
+        self.skew = skew;
+        self
+    }
+
+    pub(crate) fn generate_secret() -> Vec<u8> {
+        let mut secret = vec![0u8; SECRET_BYTES];
+        OsRng.fill_bytes(&mut secret);
+        secret
+    }
+
+    pub(crate) fn enrollment(&self, secret: Vec<u8>, email: &str) -> Result<TotpEnrollment, Error> {
+        let totp = self.totp(secret, email);
+
+        Ok(TotpEnrollment {
+            secret: totp.get_secret_base32(),
+            otpauth_uri: totp.get_url(),
+        })
+    }
+
+    /// Nonce followed by the ciphertext.
+    pub(crate) fn encrypt(&self, user_id: &Uuid, secret: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut nonce = [0u8; NONCE_BYTES];
+        OsRng.fill_bytes(&mut nonce);
+
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: secret,
+                    aad: user_id.as_bytes(),
+                },
+            )
+            .map_err(|_| Error::DataIntegrity(format!("can't encrypt TOTP secret of {user_id}")))?;
+
+        Ok([nonce.as_slice(), &ciphertext].concat())
+    }
+
+    pub(crate) fn decrypt(&self, user_id: &Uuid, stored: &[u8]) -> Result<Vec<u8>, Error> {
+        let invalid = || Error::DataIntegrity(format!("can't decrypt TOTP secret of {user_id}"));
+
+        if stored.len() < NONCE_BYTES {
+            return Err(invalid());
+        }
+        let (nonce, ciphertext) = stored.split_at(NONCE_BYTES);
+
+        self.cipher
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: user_id.as_bytes(),
+                },
+            )
+            .map_err(|_| invalid())
+    }
+
+    /// Time step `code` belongs to, if it is valid within the skew around now.
+    pub(crate) fn matching_step(&self, secret: Vec<u8>, code: &str) -> Result<Option<i64>, Error> {
+        let code = code.trim();
+        if code.len() != DIGITS || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Ok(None);
+        }
+
+        let totp = self.totp(secret, "");
+        let current = Utc::now().timestamp().max(0) as u64 / STEP_SECONDS;
+
+        let skew = u64::from(self.skew);
+        let steps = current.saturating_sub(skew)..=current + skew;
+
+        // every step is checked so the timing doesn't tell which one matched
+        let mut matched = None;
+        for step in steps.rev() {
+            let expected = totp.generate(step * STEP_SECONDS);
+            if bool::from(expected.as_bytes().ct_eq(code.as_bytes())) && matched.is_none() {
+                matched = Some(step as i64);
+            }
+        }
+
+        Ok(matched)
+    }
+
+    fn totp(&self, secret: Vec<u8>, email: &str) -> TOTP {
+        // `TOTP::new` refuses ':' in the account name, which emails may have in the local
+        // part. The issuer is checked in `new` and the url percent-encodes the account
+        // name, so the label stays unambiguous.
+        TOTP::new_unchecked(
+            Algorithm::SHA1,
+            DIGITS,
+            self.skew,
+            STEP_SECONDS,
+            secret,
+            Some(self.issuer.clone()),
+            email.to_string(),
+        )
+    }
+}
+
+impl fmt::Debug for TotpSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TotpSettings")
+            .field("issuer", &self.issuer)
+            .field("skew", &self.skew)
+            .finish_non_exhaustive()
+    }
+}
+
+/// One-time codes for when the authenticator is lost, formatted
+/// `xxxxxxx-xxxxxxx-xxxxxxx-xxxxxxx`.
+pub(crate) fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0u8; RECOVERY_CODE_LENGTH];
+            OsRng.fill_bytes(&mut bytes);
+
+            // 256 is a multiple of 32, so every char is as likely
+            let chars: Vec<char> = bytes
+                .iter()
+                .map(|b| RECOVERY_CODE_CHARS[usize::from(*b) % RECOVERY_CODE_CHARS.len()] as char)
+                .collect();
+
+            chars
+                .chunks(RECOVERY_CODE_GROUP)
+                .map(|group| group.iter().collect::<String>())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect()
+}
+
+/// Form recovery codes are hashed in, so case and separators don't matter.
+pub(crate) fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
@@ -0,0 +1,34 @@
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+const TOKEN_BYTES: usize = 32;
+const PREFIX_BYTES: usize = 6;
+
+/// Random URL-safe token handed out to clients, only its [`hash_token`] is stored.
+pub(crate) fn generate_token() -> String {
+    random_string(TOKEN_BYTES)
+}
+
+/// Short random public identifier, 8 URL-safe characters.
+pub(crate) fn generate_prefix() -> String {
+    random_string(PREFIX_BYTES)
+}
+
+/// Tokens carry enough entropy that a fast hash is sufficient, unlike passwords.
+pub(crate) fn hash_token(token: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Compares a presented token with a stored [`hash_token`] in constant time.
+pub(crate) fn verify_token(token: &str, hash: &str) -> bool {
+    hash_token(token).as_bytes().ct_eq(hash.as_bytes()).into()
+}
+
+fn random_string(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+
+    URL_SAFE_NO_PAD.encode(bytes)
+}
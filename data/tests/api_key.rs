@@ -0,0 +1,143 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use data::repository::{
+    api_key::{
+        postgres::PostgresApiKeyRepository, AnyApiKeyRepository, ApiKeyRepository,
+        ApiKeyRepositorySettings,
+    },
+    user::{postgres::PostgresUserRepository, AnyUserRepository, UserRepositorySettings},
+};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+mod api_key_suite;
+mod utils;
+
+use api_key_suite::test_user;
+use utils::{connect, postgres_settings};
+
+fn argon() -> Argon2<'static> {
+    Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap()
+}
+
+async fn build_repos(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<(PostgresUserRepository, PostgresApiKeyRepository)> {
+    let conn = connect(pool_options, connect_options).await?;
+
+    Ok((
+        PostgresUserRepository::new(conn.clone(), argon()),
+        PostgresApiKeyRepository::new(conn),
+    ))
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_and_resolve_api_key(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, api_key_repo) = build_repos(pool_options, connect_options).await?;
+
+    api_key_suite::create_and_resolve_api_key(api_key_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn resolve_invalid_api_key(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, api_key_repo) = build_repos(pool_options, connect_options).await?;
+
+    api_key_suite::resolve_invalid_api_key(api_key_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn revoked_and_expired_api_keys(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, api_key_repo) = build_repos(pool_options, connect_options).await?;
+
+    api_key_suite::revoked_and_expired_api_keys(api_key_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_api_key_validation(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, api_key_repo) = build_repos(pool_options, connect_options).await?;
+
+    api_key_suite::create_api_key_validation(api_key_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_api_key_unknown_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, api_key_repo) = build_repos(pool_options, connect_options).await?;
+
+    api_key_suite::create_api_key_unknown_user(api_key_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_api_key_deleted_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (user_repo, api_key_repo) = build_repos(pool_options, connect_options).await?;
+
+    api_key_suite::create_api_key_deleted_user(user_repo, api_key_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn from_settings(
+    _pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let settings = postgres_settings(connect_options);
+
+    let user_repo = AnyUserRepository::from_settings(
+        &UserRepositorySettings::Postgres(settings.clone()),
+        argon(),
+    )
+    .await
+    .unwrap();
+
+    let api_key_repo = AnyApiKeyRepository::from_settings(
+        &ApiKeyRepositorySettings::Postgres(settings),
+        &user_repo,
+    )
+    .await
+    .unwrap();
+
+    assert!(matches!(api_key_repo, AnyApiKeyRepository::Postgres(_)));
+
+    api_key_repo.list_api_keys(&test_user()).await.unwrap();
+
+    match AnyApiKeyRepository::from_settings(&ApiKeyRepositorySettings::Mock, &user_repo).await {
+        Ok(_) => panic!("Built mock keys for Postgres users"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for mixed backends: {err}"),
+    }
+
+    Ok(())
+}
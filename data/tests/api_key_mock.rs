@@ -0,0 +1,122 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chrono::Utc;
+use data::{
+    model::{api_key::NewApiKey, user::User},
+    repository::{
+        api_key::{
+            mock::MockApiKeyRepository, AnyApiKeyRepository, ApiKeyRepository,
+            ApiKeyRepositorySettings,
+        },
+        user::{
+            mock::MockUserRepository, AnyUserRepository, UserRepository, UserRepositorySettings,
+        },
+    },
+};
+
+mod api_key_suite;
+
+use api_key_suite::test_user;
+
+fn argon() -> Argon2<'static> {
+    Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap()
+}
+
+async fn build_repos() -> (MockUserRepository, MockApiKeyRepository) {
+    let user_repo = MockUserRepository::new(argon());
+
+    // same user as in fixtures/user.sql
+    user_repo
+        .insert_user(User {
+            id: test_user(),
+            email: "test@myemail.com".to_string(),
+            // dev_only_pass
+            hash: "$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            email_verified_at: None,
+            deleted_at: None,
+        })
+        .await
+        .unwrap();
+
+    let api_key_repo = MockApiKeyRepository::new(user_repo.clone());
+
+    (user_repo, api_key_repo)
+}
+
+#[tokio::test]
+async fn create_and_resolve_api_key() {
+    let (_, api_key_repo) = build_repos().await;
+    api_key_suite::create_and_resolve_api_key(api_key_repo).await;
+}
+
+#[tokio::test]
+async fn resolve_invalid_api_key() {
+    let (_, api_key_repo) = build_repos().await;
+    api_key_suite::resolve_invalid_api_key(api_key_repo).await;
+}
+
+#[tokio::test]
+async fn revoked_and_expired_api_keys() {
+    let (_, api_key_repo) = build_repos().await;
+    api_key_suite::revoked_and_expired_api_keys(api_key_repo).await;
+}
+
+#[tokio::test]
+async fn create_api_key_validation() {
+    let (_, api_key_repo) = build_repos().await;
+    api_key_suite::create_api_key_validation(api_key_repo).await;
+}
+
+#[tokio::test]
+async fn create_api_key_unknown_user() {
+    let (_, api_key_repo) = build_repos().await;
+    api_key_suite::create_api_key_unknown_user(api_key_repo).await;
+}
+
+#[tokio::test]
+async fn create_api_key_deleted_user() {
+    let (user_repo, api_key_repo) = build_repos().await;
+    api_key_suite::create_api_key_deleted_user(user_repo, api_key_repo).await;
+}
+
+#[tokio::test]
+async fn from_settings() {
+    let user_repo = AnyUserRepository::from_settings(&UserRepositorySettings::Mock, argon())
+        .await
+        .unwrap();
+
+    let api_key_repo =
+        AnyApiKeyRepository::from_settings(&ApiKeyRepositorySettings::Mock, &user_repo)
+            .await
+            .unwrap();
+
+    assert!(matches!(api_key_repo, AnyApiKeyRepository::Mock(_)));
+
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    let issued = api_key_repo
+        .create_api_key(
+            &user.id,
+            NewApiKey {
+                name: "script".to_string(),
+                scopes: vec![],
+                expires_at: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    let owner = api_key_repo.resolve_api_key(&issued.key).await.unwrap();
+
+    assert_eq!(owner.user.id, user.id);
+}
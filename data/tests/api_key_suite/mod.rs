@@ -0,0 +1,196 @@
+//! Behaviour every [`ApiKeyRepository`] backend shares, run by the thin tests in
+//! `api_key.rs` and `api_key_mock.rs` for the user of `fixtures/user.sql` or its
+//! in-memory copy.
+
+use chrono::{Duration, Utc};
+use data::{
+    model::api_key::NewApiKey,
+    repository::{api_key::ApiKeyRepository, user::UserRepository},
+};
+use uuid::Uuid;
+
+pub fn test_user() -> Uuid {
+    Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap()
+}
+
+fn new_key(name: &str) -> NewApiKey {
+    NewApiKey {
+        name: name.to_string(),
+        scopes: vec![],
+        expires_at: None,
+    }
+}
+
+pub async fn create_and_resolve_api_key(api_key_repo: impl ApiKeyRepository) {
+    let issued = api_key_repo
+        .create_api_key(
+            &test_user(),
+            NewApiKey {
+                name: "browser extension".to_string(),
+                scopes: vec!["items:read".to_string(), "items:write".to_string()],
+                expires_at: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert!(issued.key.starts_with("spk_"));
+    assert!(issued.key.contains(&issued.api_key.prefix));
+    assert!(issued.api_key.last_used_at.is_none());
+
+    let owner = api_key_repo.resolve_api_key(&issued.key).await.unwrap();
+
+    assert_eq!(owner.user.id, test_user());
+    assert_eq!(&owner.user.email, "test@myemail.com");
+    assert_eq!(owner.api_key.id, issued.api_key.id);
+    assert_eq!(owner.api_key.scopes, vec!["items:read", "items:write"]);
+    assert!(owner.api_key.last_used_at.is_some());
+
+    let keys = api_key_repo.list_api_keys(&test_user()).await.unwrap();
+
+    assert_eq!(keys.len(), 1);
+    assert_eq!(&keys.first().unwrap().name, "browser extension");
+    assert_eq!(
+        keys.first().unwrap().last_used_at,
+        owner.api_key.last_used_at
+    );
+}
+
+pub async fn resolve_invalid_api_key(api_key_repo: impl ApiKeyRepository) {
+    let issued = api_key_repo
+        .create_api_key(&test_user(), new_key("script"))
+        .await
+        .unwrap();
+
+    let wrong_secret = format!("spk_{}.not_the_secret", issued.api_key.prefix);
+    let unknown_prefix = "spk_AAAAAAAA.not_the_secret".to_string();
+
+    for key in [&wrong_secret, &unknown_prefix, &"garbage".to_string()] {
+        match api_key_repo.resolve_api_key(key).await {
+            Ok(_) => panic!("Resolved invalid key {key}"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for invalid key: {err}"),
+        }
+    }
+}
+
+pub async fn revoked_and_expired_api_keys(api_key_repo: impl ApiKeyRepository) {
+    let revoked = api_key_repo
+        .create_api_key(&test_user(), new_key("revoked"))
+        .await
+        .unwrap();
+    let expired = api_key_repo
+        .create_api_key(
+            &test_user(),
+            NewApiKey {
+                expires_at: Some(Utc::now() + Duration::milliseconds(500)),
+                ..new_key("expired")
+            },
+        )
+        .await
+        .unwrap();
+
+    // keys can't be created already expired, wait for this one to run out
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+
+    let key = api_key_repo
+        .revoke_api_key(&revoked.api_key.id)
+        .await
+        .unwrap();
+
+    assert!(key.revoked_at.is_some());
+
+    for key in [&revoked.key, &expired.key] {
+        match api_key_repo.resolve_api_key(key).await {
+            Ok(_) => panic!("Resolved unusable key"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for unusable key: {err}"),
+        }
+    }
+
+    match api_key_repo.revoke_api_key(&Uuid::new_v4()).await {
+        Ok(_) => panic!("Revoked unknown key"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown key: {err}"),
+    }
+}
+
+pub async fn create_api_key_validation(api_key_repo: impl ApiKeyRepository) {
+    let scopes = |scopes: &[&str]| scopes.iter().map(|scope| scope.to_string()).collect();
+    let invalid_keys = [
+        new_key(" "),
+        NewApiKey {
+            scopes: scopes(&["items:read", "admin"]),
+            ..new_key("script")
+        },
+        NewApiKey {
+            scopes: scopes(&["items:read", "items:read"]),
+            ..new_key("script")
+        },
+        NewApiKey {
+            expires_at: Some(Utc::now() - Duration::minutes(1)),
+            ..new_key("script")
+        },
+    ];
+
+    for new_key in invalid_keys {
+        match api_key_repo
+            .create_api_key(&test_user(), new_key.clone())
+            .await
+        {
+            Ok(_) => panic!("Created invalid key {new_key:?}"),
+            Err(data::Error::Validation(_)) => (),
+            Err(err) => panic!("Get wrong error for invalid key: {err}"),
+        }
+    }
+
+    assert!(api_key_repo
+        .list_api_keys(&test_user())
+        .await
+        .unwrap()
+        .is_empty());
+}
+
+pub async fn create_api_key_unknown_user(api_key_repo: impl ApiKeyRepository) {
+    match api_key_repo
+        .create_api_key(&Uuid::new_v4(), new_key("script"))
+        .await
+    {
+        Ok(_) => panic!("Created key for unknown user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown user: {err}"),
+    }
+}
+
+pub async fn create_api_key_deleted_user(
+    user_repo: impl UserRepository,
+    api_key_repo: impl ApiKeyRepository,
+) {
+    // the fixture user is the last admin, which can't be deleted
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    let issued = api_key_repo
+        .create_api_key(&user.id, new_key("script"))
+        .await
+        .unwrap();
+
+    user_repo.delete_user(&user.id, None).await.unwrap();
+
+    match api_key_repo.resolve_api_key(&issued.key).await {
+        Ok(_) => panic!("Resolved key of deleted user"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for key of deleted user: {err}"),
+    }
+
+    match api_key_repo
+        .create_api_key(&user.id, new_key("script"))
+        .await
+    {
+        Ok(_) => panic!("Created key for deleted user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for deleted user: {err}"),
+    }
+}
@@ -0,0 +1,54 @@
+INSERT INTO
+  items (
+    id,
+    user_id,
+    url,
+    canonical_url,
+    title,
+    excerpt,
+    status,
+    favorite,
+    added_at,
+    read_at,
+    updated_at
+  )
+VALUES
+  (
+    '0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001',
+    'a74f9b43-8a49-4d97-8270-9879d37c600d',
+    'https://example.com/first',
+    'https://example.com/first',
+    'First',
+    NULL,
+    'unread',
+    false,
+    '2024-02-01T10:00:00Z',
+    NULL,
+    '2024-02-01T10:00:00Z'
+  ),
+  (
+    '0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002',
+    'a74f9b43-8a49-4d97-8270-9879d37c600d',
+    'https://example.com/second',
+    'https://example.com/second',
+    'Second',
+    'Read and kept',
+    'archived',
+    true,
+    '2024-02-02T10:00:00Z',
+    '2024-02-03T10:00:00Z',
+    '2024-02-03T10:00:00Z'
+  ),
+  (
+    '0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0003',
+    'a74f9b43-8a49-4d97-8270-9879d37c600d',
+    'https://example.org/third',
+    'https://example.org/third',
+    NULL,
+    NULL,
+    'unread',
+    true,
+    '2024-02-03T10:00:00Z',
+    NULL,
+    '2024-02-03T10:00:00Z'
+  );
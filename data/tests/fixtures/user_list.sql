@@ -0,0 +1,44 @@
+INSERT INTO
+  users (id, email, hash, created_at, updated_at)
+VALUES
+  (
+    '0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001',
+    'alice@example.com',
+    -- dev_only_pass
+    '$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw',
+    '2024-01-01T10:00:00Z',
+    '2024-01-01T10:00:00Z'
+  ),
+  (
+    '0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002',
+    'bob@example.com',
+    '$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw',
+    '2024-01-02T10:00:00Z',
+    '2024-01-02T10:00:00Z'
+  ),
+  (
+    '0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0003',
+    'carol@example.com',
+    '$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw',
+    '2024-01-03T10:00:00Z',
+    '2024-01-03T10:00:00Z'
+  ),
+  (
+    '0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0004',
+    'dave@example.com',
+    '$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw',
+    '2024-01-04T10:00:00Z',
+    '2024-01-04T10:00:00Z'
+  ),
+  (
+    '0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0005',
+    'erin@example.com',
+    '$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw',
+    '2024-01-05T10:00:00Z',
+    '2024-01-05T10:00:00Z'
+  );
+
+INSERT INTO
+  user_roles (user_id, role)
+VALUES
+  ('0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002', 'admin');
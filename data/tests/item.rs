@@ -0,0 +1,385 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use data::{
+    model::item::{ImportItem, ItemStatus, ListItems},
+    repository::{
+        item::{
+            postgres::PostgresItemRepository, AnyItemRepository, ItemRepository,
+            ItemRepositorySettings,
+        },
+        user::{postgres::PostgresUserRepository, AnyUserRepository, UserRepositorySettings},
+    },
+};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+mod item_suite;
+mod utils;
+
+use item_suite::test_user;
+use utils::{connect, postgres_settings};
+
+fn argon() -> Argon2<'static> {
+    Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap()
+}
+
+async fn build_repo(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<PostgresItemRepository> {
+    let conn = connect(pool_options, connect_options).await?;
+
+    Ok(PostgresItemRepository::new(conn))
+}
+
+async fn build_repos(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<(PostgresUserRepository, PostgresItemRepository)> {
+    let conn = connect(pool_options, connect_options).await?;
+
+    Ok((
+        PostgresUserRepository::new(conn.clone(), argon()),
+        PostgresItemRepository::new(conn),
+    ))
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_item(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::create_item(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn create_item_again(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::create_item_again(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_item_invalid_url(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::create_item_invalid_url(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_item_unknown_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::create_item_unknown_user(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_item_deleted_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (user_repo, item_repo) = build_repos(pool_options, connect_options).await?;
+
+    item_suite::create_item_deleted_user(user_repo, item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn get_item_of_other_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::get_item_of_other_user(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn update_item(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::update_item(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn delete_item(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::delete_item(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn list_items(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::list_items(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn tag_items(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::tag_items(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn rename_tag(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::rename_tag(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn merge_tags(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::merge_tags(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn list_items_by_tag_and_domain(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::list_items_by_tag_and_domain(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn search_items(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::search_items(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn import_items(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::import_items(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn import_items_in_batches(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::import_items_in_batches(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn import_items_unknown_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    item_suite::import_items_unknown_user(item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn import_items_deleted_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (user_repo, item_repo) = build_repos(pool_options, connect_options).await?;
+
+    item_suite::import_items_deleted_user(user_repo, item_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn import_items_failed_batch(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    sqlx::raw_sql(
+        r#"
+            CREATE FUNCTION fail_import() RETURNS trigger AS $$
+            BEGIN
+                RAISE EXCEPTION 'import broke';
+            END;
+            $$ LANGUAGE plpgsql;
+
+            CREATE TRIGGER fail_import BEFORE INSERT ON items
+            FOR EACH ROW WHEN ( NEW.url = 'https://example.net/150' )
+            EXECUTE FUNCTION fail_import();
+        "#,
+    )
+    .execute(&item_repo.pool)
+    .await?;
+
+    let entries: Vec<ImportItem> = (0..250)
+        .map(|index| ImportItem {
+            url: format!("https://example.net/{index}"),
+            title: None,
+            tags: if index == 120 {
+                vec![" ".to_string()]
+            } else {
+                Vec::new()
+            },
+            status: ItemStatus::Unread,
+            added_at: None,
+        })
+        .collect();
+
+    let report = item_repo.import_items(&test_user(), entries).await.unwrap();
+
+    // the second batch is rolled back, the third one still goes through
+    assert_eq!(report.created.len(), 150);
+    assert!(report.skipped.is_empty());
+    assert_eq!(report.failed.len(), 100);
+    assert_eq!(&report.failed[0].url, "https://example.net/100");
+    assert!(report.failed[0].reason.contains("import broke"));
+    assert!(!report.failed[20].reason.contains("import broke"));
+
+    let page = item_repo
+        .list_items(
+            &test_user(),
+            ListItems {
+                with_total: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(page.total, Some(150));
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "item"))]
+async fn items_removed_with_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let item_repo = build_repo(pool_options, connect_options).await?;
+
+    sqlx::query("DELETE FROM users WHERE id = $1;")
+        .bind(test_user())
+        .execute(&item_repo.pool)
+        .await?;
+
+    let page = item_repo
+        .list_items(&test_user(), ListItems::default())
+        .await
+        .unwrap();
+
+    assert!(page.items.is_empty());
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn from_settings(
+    _pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let settings = postgres_settings(connect_options);
+
+    let user_repo = AnyUserRepository::from_settings(
+        &UserRepositorySettings::Postgres(settings.clone()),
+        argon(),
+    )
+    .await
+    .unwrap();
+
+    let item_repo =
+        AnyItemRepository::from_settings(&ItemRepositorySettings::Postgres(settings), &user_repo)
+            .await
+            .unwrap();
+
+    assert!(matches!(item_repo, AnyItemRepository::Postgres(_)));
+
+    item_repo
+        .list_items(&test_user(), ListItems::default())
+        .await
+        .unwrap();
+
+    match AnyItemRepository::from_settings(&ItemRepositorySettings::Mock, &user_repo).await {
+        Ok(_) => panic!("Built mock items for Postgres users"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for mixed backends: {err}"),
+    }
+
+    Ok(())
+}
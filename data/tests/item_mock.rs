@@ -0,0 +1,344 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chrono::{DateTime, Utc};
+use data::{
+    model::{
+        item::{Item, ItemStatus, NewItem},
+        user::User,
+    },
+    repository::{
+        item::{
+            canonical, mock::MockItemRepository, pocket, search, AnyItemRepository, ItemRepository,
+            ItemRepositorySettings,
+        },
+        user::{
+            mock::MockUserRepository, AnyUserRepository, UserRepository, UserRepositorySettings,
+        },
+    },
+};
+use uuid::Uuid;
+
+mod item_suite;
+
+use item_suite::test_user;
+
+fn argon() -> Argon2<'static> {
+    Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap()
+}
+
+async fn build_repos() -> (MockUserRepository, MockItemRepository) {
+    let user_repo = MockUserRepository::new(argon());
+
+    // same user as in fixtures/user.sql
+    user_repo
+        .insert_user(User {
+            id: test_user(),
+            email: "test@myemail.com".to_string(),
+            // dev_only_pass
+            hash: "$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            email_verified_at: None,
+            deleted_at: None,
+        })
+        .await
+        .unwrap();
+
+    let repo = MockItemRepository::new(user_repo.clone());
+
+    // same items as in fixtures/item.sql
+    let items = [
+        (
+            "0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001",
+            "https://example.com/first",
+            Some("First"),
+            None,
+            ItemStatus::Unread,
+            false,
+            "2024-02-01T10:00:00Z",
+            None,
+        ),
+        (
+            "0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002",
+            "https://example.com/second",
+            Some("Second"),
+            Some("Read and kept"),
+            ItemStatus::Archived,
+            true,
+            "2024-02-02T10:00:00Z",
+            Some("2024-02-03T10:00:00Z"),
+        ),
+        (
+            "0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0003",
+            "https://example.org/third",
+            None,
+            None,
+            ItemStatus::Unread,
+            true,
+            "2024-02-03T10:00:00Z",
+            None,
+        ),
+    ];
+
+    for (id, url, title, excerpt, status, favorite, added_at, read_at) in items {
+        let added_at: DateTime<Utc> = added_at.parse().unwrap();
+        let read_at: Option<DateTime<Utc>> = read_at.map(|read_at| read_at.parse().unwrap());
+
+        repo.insert_item(Item {
+            id: Uuid::parse_str(id).unwrap(),
+            user_id: test_user(),
+            url: url.to_string(),
+            canonical_url: url.to_string(),
+            title: title.map(str::to_string),
+            excerpt: excerpt.map(str::to_string),
+            content: None,
+            status,
+            favorite,
+            added_at,
+            read_at,
+            updated_at: read_at.unwrap_or(added_at),
+        })
+        .await
+        .unwrap();
+    }
+
+    (user_repo, repo)
+}
+
+async fn build_repo() -> MockItemRepository {
+    let (_, item_repo) = build_repos().await;
+    item_repo
+}
+
+#[tokio::test]
+async fn create_item() {
+    item_suite::create_item(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_item_again() {
+    item_suite::create_item_again(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_item_invalid_url() {
+    item_suite::create_item_invalid_url(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_item_unknown_user() {
+    item_suite::create_item_unknown_user(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_item_deleted_user() {
+    let (user_repo, item_repo) = build_repos().await;
+    item_suite::create_item_deleted_user(user_repo, item_repo).await;
+}
+
+#[tokio::test]
+async fn get_item_of_other_user() {
+    item_suite::get_item_of_other_user(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn update_item() {
+    item_suite::update_item(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn delete_item() {
+    item_suite::delete_item(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn list_items() {
+    item_suite::list_items(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn tag_items() {
+    item_suite::tag_items(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn rename_tag() {
+    item_suite::rename_tag(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn merge_tags() {
+    item_suite::merge_tags(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn list_items_by_tag_and_domain() {
+    item_suite::list_items_by_tag_and_domain(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn search_items() {
+    item_suite::search_items(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn import_items() {
+    item_suite::import_items(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn import_items_unknown_user() {
+    item_suite::import_items_unknown_user(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn import_items_deleted_user() {
+    let (user_repo, item_repo) = build_repos().await;
+    item_suite::import_items_deleted_user(user_repo, item_repo).await;
+}
+
+#[tokio::test]
+async fn import_items_in_batches() {
+    item_suite::import_items_in_batches(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn from_settings() {
+    let user_repo = AnyUserRepository::from_settings(&UserRepositorySettings::Mock, argon())
+        .await
+        .unwrap();
+
+    let item_repo = AnyItemRepository::from_settings(&ItemRepositorySettings::Mock, &user_repo)
+        .await
+        .unwrap();
+
+    assert!(matches!(item_repo, AnyItemRepository::Mock(_)));
+
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    let item = item_repo
+        .create_item(
+            &user.id,
+            NewItem {
+                url: "https://example.com/article".to_string(),
+                title: None,
+                excerpt: None,
+                content: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(item.user_id, user.id);
+}
+
+#[test]
+fn canonicalize_url() {
+    let canonical = |url: &str| canonical::canonicalize(&canonical::parse_url(url).unwrap());
+
+    assert_eq!(
+        canonical("HTTP://WWW.Example.COM:80/Path/?utm_campaign=x&z=1&a=2&gclid=y#top"),
+        "https://www.example.com/Path?a=2&z=1"
+    );
+    assert_eq!(
+        canonical("https://example.com:443/"),
+        canonical("http://example.com")
+    );
+    assert_eq!(
+        canonical("https://example.com./a//"),
+        "https://example.com/a"
+    );
+    assert_eq!(
+        canonical("https://example.com:8443/a?UTM_Source=x"),
+        "https://example.com:8443/a"
+    );
+    assert_ne!(
+        canonical("https://example.com/a?page=1"),
+        canonical("https://example.com/a?page=2")
+    );
+
+    for invalid in [
+        "",
+        "/relative/path",
+        "file:///etc/passwd",
+        "javascript:alert(1)",
+    ] {
+        assert!(matches!(
+            canonical::parse_url(invalid),
+            Err(data::Error::InvalidArgument(_))
+        ));
+    }
+}
+
+#[test]
+fn parse_search_query() {
+    let tsquery = |query: &str| search::to_tsquery(&search::parse_query(query).unwrap());
+
+    assert_eq!(tsquery("Rust async"), "rust & async");
+    assert_eq!(tsquery("\"Async  Rust\" tok*"), "(async <-> rust) & tok:*");
+    assert_eq!(tsquery("e-mail*"), "e & mail:*");
+    assert_eq!(tsquery("\"rust"), "rust");
+    assert_eq!(tsquery("rust' | !(x) <-> y:*"), "rust & x & y:*");
+
+    for invalid in ["", "   ", "\"\"", "* & | !"] {
+        match search::parse_query(invalid) {
+            Ok(terms) => panic!("Parsed {invalid:?} into {terms:?}"),
+            Err(data::Error::InvalidArgument(_)) => (),
+            Err(err) => panic!("Get wrong error for {invalid:?}: {err}"),
+        }
+    }
+}
+
+#[test]
+fn parse_pocket_export() {
+    let entries = pocket::parse_export(include_str!("fixtures/ril_export.html"));
+
+    assert_eq!(
+        entries
+            .iter()
+            .map(|entry| (entry.url.as_str(), entry.title.as_deref(), entry.status))
+            .collect::<Vec<_>>(),
+        vec![
+            (
+                "https://example.com/first?utm_source=pocket",
+                None,
+                ItemStatus::Unread
+            ),
+            (
+                "https://example.net/new",
+                Some("New article"),
+                ItemStatus::Unread
+            ),
+            ("javascript:alert(1)", Some("Broken"), ItemStatus::Unread),
+            (
+                "http://example.net/new/",
+                Some("New article again"),
+                ItemStatus::Unread
+            ),
+            (
+                "https://example.net/done",
+                Some("Done & dusted"),
+                ItemStatus::Archived
+            ),
+        ]
+    );
+
+    assert_eq!(entries[1].tags, vec!["Rust", "To  Read"]);
+    assert!(entries[0].tags.is_empty());
+    assert_eq!(
+        entries[1].added_at.map(|added_at| added_at.timestamp()),
+        Some(1_700_000_100)
+    );
+    assert!(entries[3].added_at.is_none());
+
+    assert!(pocket::parse_export("").is_empty());
+    assert!(pocket::parse_export("<h1>Unread</h1><ul></ul>").is_empty());
+}
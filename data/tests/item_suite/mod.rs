@@ -0,0 +1,1015 @@
+//! Behaviour every [`ItemRepository`] backend shares, run by the thin tests in
+//! `item.rs` and `item_mock.rs` against the items of `fixtures/item.sql` or their
+//! in-memory copies.
+
+use chrono::Utc;
+use data::{
+    model::{
+        item::{
+            ImportItem, ItemFilter, ItemSort, ItemStatus, ListItems, NewItem, SearchItems,
+            UpdateItem,
+        },
+        tag::Tag,
+    },
+    repository::{
+        item::{pocket, ItemRepository},
+        user::UserRepository,
+    },
+};
+use uuid::Uuid;
+
+pub fn test_user() -> Uuid {
+    Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap()
+}
+
+pub async fn list_urls(item_repo: &impl ItemRepository, query: ListItems) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut query = query;
+
+    loop {
+        let page = item_repo
+            .list_items(&test_user(), query.clone())
+            .await
+            .unwrap();
+        urls.extend(page.items.into_iter().map(|item| item.url));
+
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => return urls,
+        }
+    }
+}
+
+pub fn tag_names(tags: &[Tag]) -> Vec<&str> {
+    tags.iter().map(|tag| tag.name.as_str()).collect()
+}
+
+pub async fn search_urls(item_repo: &impl ItemRepository, query: SearchItems) -> Vec<String> {
+    let mut urls = Vec::new();
+    let mut query = query;
+
+    loop {
+        let page = item_repo
+            .search_items(&test_user(), query.clone())
+            .await
+            .unwrap();
+        urls.extend(page.items.into_iter().map(|hit| hit.item.url));
+
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => return urls,
+        }
+    }
+}
+
+pub async fn create_item(item_repo: impl ItemRepository) {
+    let item = item_repo
+        .create_item(
+            &test_user(),
+            NewItem {
+                url: " https://Example.com/article ".to_string(),
+                title: Some("An article".to_string()),
+                excerpt: None,
+                content: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(&item.url, "https://example.com/article");
+    assert_eq!(item.title.as_deref(), Some("An article"));
+    assert_eq!(item.status, ItemStatus::Unread);
+    assert!(!item.favorite);
+    assert!(item.read_at.is_none());
+
+    let stored = item_repo.get_item(&test_user(), &item.id).await.unwrap();
+
+    assert_eq!(stored.id, item.id);
+    assert_eq!(stored.added_at, item.added_at);
+}
+
+pub async fn create_item_again(item_repo: impl ItemRepository) {
+    let before = Utc::now();
+
+    let first = item_repo
+        .create_item(
+            &test_user(),
+            NewItem {
+                url: "http://Example.com/first/?utm_source=feed&utm_medium=rss#comments"
+                    .to_string(),
+                title: Some("Not the first title".to_string()),
+                excerpt: Some("Filled in".to_string()),
+                content: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    // one of the fixture items already
+    assert_eq!(
+        first.id,
+        Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001").unwrap()
+    );
+    assert_eq!(&first.url, "https://example.com/first");
+    assert_eq!(first.title.as_deref(), Some("First"));
+    assert_eq!(first.excerpt.as_deref(), Some("Filled in"));
+    assert!(first.added_at >= before);
+
+    let urls = list_urls(&item_repo, ListItems::default()).await;
+
+    assert_eq!(
+        urls,
+        vec![
+            "https://example.com/first",
+            "https://example.org/third",
+            "https://example.com/second",
+        ]
+    );
+
+    let item = item_repo
+        .create_item(
+            &test_user(),
+            NewItem {
+                url: "https://example.com/article?b=2&a=1".to_string(),
+                title: None,
+                excerpt: None,
+                content: None,
+            },
+        )
+        .await
+        .unwrap();
+    let again = item_repo
+        .create_item(
+            &test_user(),
+            NewItem {
+                url: "https://example.com/article/?a=1&fbclid=abc&b=2".to_string(),
+                title: None,
+                excerpt: None,
+                content: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(again.id, item.id);
+    assert_eq!(&again.canonical_url, "https://example.com/article?a=1&b=2");
+
+    // other query values are another page
+    let other = item_repo
+        .create_item(
+            &test_user(),
+            NewItem {
+                url: "https://example.com/article?a=1&b=3".to_string(),
+                title: None,
+                excerpt: None,
+                content: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_ne!(other.id, item.id);
+}
+
+pub async fn create_item_invalid_url(item_repo: impl ItemRepository) {
+    for url in [
+        "",
+        "example.com/article",
+        "ftp://example.com/file",
+        "mailto:a@b.c",
+    ] {
+        let new_item = NewItem {
+            url: url.to_string(),
+            title: None,
+            excerpt: None,
+            content: None,
+        };
+
+        match item_repo.create_item(&test_user(), new_item).await {
+            Ok(_) => panic!("Created item with invalid url {url}"),
+            Err(data::Error::InvalidArgument(_)) => (),
+            Err(err) => panic!("Get wrong error for invalid url {url}: {err}"),
+        }
+    }
+}
+
+fn article() -> NewItem {
+    NewItem {
+        url: "https://example.com/article".to_string(),
+        title: None,
+        excerpt: None,
+        content: None,
+    }
+}
+
+/// A user of its own, the fixture user is the last admin and can't be deleted.
+async fn deleted_user(user_repo: &impl UserRepository) -> Uuid {
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    user_repo.delete_user(&user.id, None).await.unwrap();
+
+    user.id
+}
+
+pub async fn create_item_unknown_user(item_repo: impl ItemRepository) {
+    match item_repo.create_item(&Uuid::new_v4(), article()).await {
+        Ok(_) => panic!("Created item for unknown user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown user: {err}"),
+    }
+}
+
+pub async fn create_item_deleted_user(
+    user_repo: impl UserRepository,
+    item_repo: impl ItemRepository,
+) {
+    let user_id = deleted_user(&user_repo).await;
+
+    match item_repo.create_item(&user_id, article()).await {
+        Ok(_) => panic!("Created item for deleted user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for deleted user: {err}"),
+    }
+}
+
+pub async fn get_item_of_other_user(item_repo: impl ItemRepository) {
+    let item_id = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001").unwrap();
+    let other_user = Uuid::new_v4();
+
+    match item_repo.get_item(&other_user, &item_id).await {
+        Ok(_) => panic!("Got item of other user"),
+        Err(data::Error::NotFound(entity)) => assert_eq!(entity, item_id.to_string()),
+        Err(err) => panic!("Get wrong error for item of other user: {err}"),
+    }
+
+    match item_repo
+        .update_item(&other_user, &item_id, UpdateItem::default())
+        .await
+    {
+        Ok(_) => panic!("Updated item of other user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for item of other user: {err}"),
+    }
+
+    match item_repo.delete_item(&other_user, &item_id).await {
+        Ok(_) => panic!("Deleted item of other user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for item of other user: {err}"),
+    }
+
+    assert!(item_repo.get_item(&test_user(), &item_id).await.is_ok());
+}
+
+pub async fn update_item(item_repo: impl ItemRepository) {
+    let item_id = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001").unwrap();
+    let before = Utc::now();
+
+    let archived = item_repo
+        .update_item(
+            &test_user(),
+            &item_id,
+            UpdateItem {
+                title: Some("First, renamed".to_string()),
+                status: Some(ItemStatus::Archived),
+                favorite: Some(true),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(archived.title.as_deref(), Some("First, renamed"));
+    assert_eq!(archived.status, ItemStatus::Archived);
+    assert!(archived.favorite);
+    assert!(archived.read_at.is_some_and(|read_at| read_at >= before));
+
+    // archiving again keeps when it was read
+    let again = item_repo
+        .update_item(
+            &test_user(),
+            &item_id,
+            UpdateItem {
+                status: Some(ItemStatus::Archived),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(again.read_at, archived.read_at);
+    assert_eq!(again.title, archived.title);
+
+    match item_repo
+        .update_item(
+            &test_user(),
+            &item_id,
+            UpdateItem {
+                status: Some(ItemStatus::Unread),
+                expected_updated_at: Some(archived.updated_at),
+                ..Default::default()
+            },
+        )
+        .await
+    {
+        Ok(_) => panic!("Updated item with stale updated_at"),
+        Err(data::Error::Conflict(entity)) => assert_eq!(entity, item_id.to_string()),
+        Err(err) => panic!("Get wrong error for stale update: {err}"),
+    }
+
+    let unread = item_repo
+        .update_item(
+            &test_user(),
+            &item_id,
+            UpdateItem {
+                status: Some(ItemStatus::Unread),
+                expected_updated_at: Some(again.updated_at),
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(unread.status, ItemStatus::Unread);
+    assert!(unread.read_at.is_none());
+    assert!(unread.favorite);
+}
+
+pub async fn delete_item(item_repo: impl ItemRepository) {
+    let item_id = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002").unwrap();
+
+    let deleted = item_repo.delete_item(&test_user(), &item_id).await.unwrap();
+
+    assert_eq!(&deleted.url, "https://example.com/second");
+
+    match item_repo.get_item(&test_user(), &item_id).await {
+        Ok(_) => panic!("Can get item after deletion"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error after getting deleted item: {err}"),
+    }
+
+    match item_repo.delete_item(&test_user(), &item_id).await {
+        Ok(_) => panic!("Deleted item twice"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for deleting deleted item: {err}"),
+    }
+}
+
+pub async fn list_items(item_repo: impl ItemRepository) {
+    let urls = list_urls(
+        &item_repo,
+        ListItems {
+            limit: Some(1),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        urls,
+        vec![
+            "https://example.org/third",
+            "https://example.com/second",
+            "https://example.com/first",
+        ]
+    );
+
+    let urls = list_urls(
+        &item_repo,
+        ListItems {
+            sort: ItemSort::AddedAtAsc,
+            limit: Some(2),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        urls,
+        vec![
+            "https://example.com/first",
+            "https://example.com/second",
+            "https://example.org/third",
+        ]
+    );
+
+    let page = item_repo
+        .list_items(
+            &test_user(),
+            ListItems {
+                filter: ItemFilter {
+                    status: Some(ItemStatus::Unread),
+                    favorite: Some(true),
+                    ..Default::default()
+                },
+                with_total: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(page.total, Some(1));
+    assert_eq!(
+        &page.items.first().unwrap().url,
+        "https://example.org/third"
+    );
+
+    let page = item_repo
+        .list_items(&Uuid::new_v4(), ListItems::default())
+        .await
+        .unwrap();
+
+    assert!(page.items.is_empty());
+}
+
+pub async fn tag_items(item_repo: impl ItemRepository) {
+    let first = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001").unwrap();
+    let second = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002").unwrap();
+
+    let tags = item_repo
+        .add_tags(
+            &test_user(),
+            &first,
+            vec![
+                " Rust ".to_string(),
+                "Async   Programming".to_string(),
+                "rust".to_string(),
+            ],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(tag_names(&tags), vec!["async programming", "rust"]);
+
+    let tags = item_repo
+        .add_tags(&test_user(), &second, vec!["RUST".to_string()])
+        .await
+        .unwrap();
+
+    assert_eq!(tag_names(&tags), vec!["rust"]);
+
+    let usage = item_repo.list_tags(&test_user()).await.unwrap();
+
+    assert_eq!(
+        usage
+            .iter()
+            .map(|usage| (usage.tag.name.as_str(), usage.item_count))
+            .collect::<Vec<_>>(),
+        vec![("async programming", 1), ("rust", 2)]
+    );
+
+    let tags = item_repo
+        .remove_tags(
+            &test_user(),
+            &first,
+            vec!["Rust".to_string(), "unknown".to_string()],
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(tag_names(&tags), vec!["async programming"]);
+
+    let tags = item_repo.item_tags(&test_user(), &second).await.unwrap();
+
+    assert_eq!(tag_names(&tags), vec!["rust"]);
+
+    match item_repo
+        .add_tags(
+            &test_user(),
+            &first,
+            vec!["ok".to_string(), "  ".to_string()],
+        )
+        .await
+    {
+        Ok(_) => panic!("Added blank tag"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for blank tag: {err}"),
+    }
+
+    match item_repo
+        .add_tags(&test_user(), &first, vec!["x".repeat(65)])
+        .await
+    {
+        Ok(_) => panic!("Added too long tag"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for too long tag: {err}"),
+    }
+
+    let tags = item_repo.item_tags(&test_user(), &first).await.unwrap();
+
+    assert_eq!(tag_names(&tags), vec!["async programming"]);
+
+    match item_repo
+        .add_tags(&Uuid::new_v4(), &first, vec!["rust".to_string()])
+        .await
+    {
+        Ok(_) => panic!("Tagged item of other user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for tagging item of other user: {err}"),
+    }
+
+    item_repo.delete_item(&test_user(), &second).await.unwrap();
+
+    let usage = item_repo.list_tags(&test_user()).await.unwrap();
+
+    assert_eq!(
+        usage
+            .iter()
+            .map(|usage| (usage.tag.name.as_str(), usage.item_count))
+            .collect::<Vec<_>>(),
+        vec![("async programming", 1), ("rust", 0)]
+    );
+}
+
+pub async fn rename_tag(item_repo: impl ItemRepository) {
+    let first = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001").unwrap();
+
+    item_repo
+        .add_tags(
+            &test_user(),
+            &first,
+            vec!["rust".to_string(), "tokio".to_string()],
+        )
+        .await
+        .unwrap();
+
+    let renamed = item_repo
+        .rename_tag(&test_user(), "Tokio", " Async  Rust ")
+        .await
+        .unwrap();
+
+    assert_eq!(&renamed.name, "async rust");
+
+    let tags = item_repo.item_tags(&test_user(), &first).await.unwrap();
+
+    assert_eq!(tag_names(&tags), vec!["async rust", "rust"]);
+
+    match item_repo
+        .rename_tag(&test_user(), "async rust", "RUST")
+        .await
+    {
+        Ok(_) => panic!("Renamed tag to existing name"),
+        Err(data::Error::AlreadyExists(_)) => (),
+        Err(err) => panic!("Get wrong error for renaming to existing tag: {err}"),
+    }
+
+    match item_repo.rename_tag(&test_user(), "tokio", "runtime").await {
+        Ok(_) => panic!("Renamed unknown tag"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for renaming unknown tag: {err}"),
+    }
+
+    match item_repo.rename_tag(&Uuid::new_v4(), "rust", "go").await {
+        Ok(_) => panic!("Renamed tag of other user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for renaming tag of other user: {err}"),
+    }
+}
+
+pub async fn merge_tags(item_repo: impl ItemRepository) {
+    let first = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001").unwrap();
+    let second = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002").unwrap();
+    let third = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0003").unwrap();
+
+    item_repo
+        .add_tags(
+            &test_user(),
+            &first,
+            vec!["rustlang".to_string(), "rust".to_string()],
+        )
+        .await
+        .unwrap();
+    item_repo
+        .add_tags(&test_user(), &second, vec!["rustlang".to_string()])
+        .await
+        .unwrap();
+    item_repo
+        .add_tags(&test_user(), &third, vec!["rust".to_string()])
+        .await
+        .unwrap();
+
+    let merged = item_repo
+        .merge_tags(&test_user(), "RustLang", "rust")
+        .await
+        .unwrap();
+
+    assert_eq!(&merged.tag.name, "rust");
+    assert_eq!(merged.item_count, 3);
+
+    for item_id in [first, second, third] {
+        let tags = item_repo.item_tags(&test_user(), &item_id).await.unwrap();
+
+        assert_eq!(tag_names(&tags), vec!["rust"]);
+    }
+
+    let usage = item_repo.list_tags(&test_user()).await.unwrap();
+
+    assert_eq!(usage.len(), 1);
+
+    match item_repo.merge_tags(&test_user(), "rustlang", "rust").await {
+        Ok(_) => panic!("Merged deleted tag"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for merging deleted tag: {err}"),
+    }
+
+    match item_repo.merge_tags(&test_user(), "rust", "unknown").await {
+        Ok(_) => panic!("Merged into unknown tag"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for merging into unknown tag: {err}"),
+    }
+
+    match item_repo.merge_tags(&test_user(), "Rust", "rust").await {
+        Ok(_) => panic!("Merged tag into itself"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for merging tag into itself: {err}"),
+    }
+
+    let usage = item_repo.list_tags(&test_user()).await.unwrap();
+
+    assert_eq!(usage[0].item_count, 3);
+}
+
+pub async fn list_items_by_tag_and_domain(item_repo: impl ItemRepository) {
+    let second = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002").unwrap();
+    let third = Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0003").unwrap();
+
+    item_repo
+        .add_tags(&test_user(), &second, vec!["later".to_string()])
+        .await
+        .unwrap();
+    item_repo
+        .add_tags(&test_user(), &third, vec!["later".to_string()])
+        .await
+        .unwrap();
+
+    let urls = list_urls(
+        &item_repo,
+        ListItems {
+            filter: ItemFilter {
+                tag: Some(" Later ".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        urls,
+        vec!["https://example.org/third", "https://example.com/second"]
+    );
+
+    let urls = list_urls(
+        &item_repo,
+        ListItems {
+            filter: ItemFilter {
+                tag: Some("later".to_string()),
+                domain: Some("Example.COM".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(urls, vec!["https://example.com/second"]);
+
+    item_repo
+        .create_item(
+            &test_user(),
+            NewItem {
+                url: "https://blog.example.org/post".to_string(),
+                title: None,
+                excerpt: None,
+                content: None,
+            },
+        )
+        .await
+        .unwrap();
+
+    let urls = list_urls(
+        &item_repo,
+        ListItems {
+            filter: ItemFilter {
+                domain: Some("example.org".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        urls,
+        vec!["https://blog.example.org/post", "https://example.org/third"]
+    );
+
+    match item_repo
+        .list_items(
+            &test_user(),
+            ListItems {
+                filter: ItemFilter {
+                    domain: Some("example.org/third".to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .await
+    {
+        Ok(_) => panic!("Listed items of invalid domain"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for invalid domain: {err}"),
+    }
+}
+
+pub async fn search_items(item_repo: impl ItemRepository) {
+    let async_rust = item_repo
+        .create_item(
+            &test_user(),
+            NewItem {
+                url: "https://blog.example.com/async-rust".to_string(),
+                title: Some("Async Rust".to_string()),
+                excerpt: Some("Futures explained".to_string()),
+                content: Some("Executors poll futures until they are ready.".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+    let pasta = item_repo
+        .create_item(
+            &test_user(),
+            NewItem {
+                url: "https://news.example.org/pasta".to_string(),
+                title: Some("Cooking pasta".to_string()),
+                excerpt: None,
+                content: Some("Boil the water, this page mentions rust only once.".to_string()),
+            },
+        )
+        .await
+        .unwrap();
+
+    let search = |query: &str| SearchItems {
+        query: query.to_string(),
+        ..Default::default()
+    };
+
+    // title matches rank above content ones
+    let urls = search_urls(&item_repo, search("Rust")).await;
+
+    assert_eq!(urls, vec![async_rust.url.clone(), pasta.url.clone()]);
+
+    let urls = search_urls(
+        &item_repo,
+        SearchItems {
+            limit: Some(1),
+            ..search("rust")
+        },
+    )
+    .await;
+
+    assert_eq!(urls, vec![async_rust.url.clone(), pasta.url.clone()]);
+
+    let page = item_repo
+        .search_items(&test_user(), search("\"poll futures\""))
+        .await
+        .unwrap();
+
+    assert_eq!(page.items.len(), 1);
+    assert_eq!(page.items[0].item.id, async_rust.id);
+    assert!(page.items[0]
+        .snippet
+        .as_deref()
+        .unwrap()
+        .contains("<mark>poll</mark> <mark>futures</mark>"));
+
+    let urls = search_urls(&item_repo, search("\"futures poll\"")).await;
+
+    assert!(urls.is_empty());
+
+    let urls = search_urls(&item_repo, search("execut*")).await;
+
+    assert_eq!(urls, vec![async_rust.url.clone()]);
+
+    let urls = search_urls(
+        &item_repo,
+        SearchItems {
+            filter: ItemFilter {
+                domain: Some("example.org".to_string()),
+                ..Default::default()
+            },
+            ..search("rust")
+        },
+    )
+    .await;
+
+    assert_eq!(urls, vec![pasta.url.clone()]);
+
+    item_repo
+        .add_tags(&test_user(), &pasta.id, vec!["Food".to_string()])
+        .await
+        .unwrap();
+
+    let urls = search_urls(
+        &item_repo,
+        SearchItems {
+            filter: ItemFilter {
+                tag: Some("food".to_string()),
+                ..Default::default()
+            },
+            ..search("rust")
+        },
+    )
+    .await;
+
+    assert_eq!(urls, vec![pasta.url.clone()]);
+
+    let urls = search_urls(
+        &item_repo,
+        SearchItems {
+            filter: ItemFilter {
+                status: Some(ItemStatus::Archived),
+                ..Default::default()
+            },
+            ..search("rust")
+        },
+    )
+    .await;
+
+    assert!(urls.is_empty());
+
+    let page = item_repo
+        .search_items(&Uuid::new_v4(), search("rust"))
+        .await
+        .unwrap();
+
+    assert!(page.items.is_empty());
+
+    match item_repo
+        .search_items(&test_user(), search(" \"\" * "))
+        .await
+    {
+        Ok(_) => panic!("Searched without words"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for empty search: {err}"),
+    }
+
+    // stop words are left out of the query, as Postgres does
+    let urls = search_urls(&item_repo, search("the rust")).await;
+
+    assert_eq!(urls, vec![async_rust.url.clone(), pasta.url.clone()]);
+
+    match item_repo
+        .search_items(&test_user(), search("the and"))
+        .await
+    {
+        Ok(_) => panic!("Searched only stop words"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for stop word search: {err}"),
+    }
+}
+
+pub async fn import_items(item_repo: impl ItemRepository) {
+    let entries = pocket::parse_export(include_str!("../fixtures/ril_export.html"));
+
+    let report = item_repo
+        .import_items(&test_user(), entries.clone())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        report
+            .created
+            .iter()
+            .map(|item| (item.url.as_str(), item.status))
+            .collect::<Vec<_>>(),
+        vec![
+            ("https://example.net/new", ItemStatus::Unread),
+            ("https://example.net/done", ItemStatus::Archived),
+        ]
+    );
+    assert_eq!(
+        report.skipped,
+        vec![
+            "https://example.com/first?utm_source=pocket",
+            "http://example.net/new/",
+        ]
+    );
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(&report.failed[0].url, "javascript:alert(1)");
+
+    let new = &report.created[0];
+
+    assert_eq!(new.title.as_deref(), Some("New article"));
+    assert_eq!(new.added_at.timestamp(), 1_700_000_100);
+    assert!(new.read_at.is_none());
+
+    let done = &report.created[1];
+
+    assert_eq!(done.added_at.timestamp(), 1_600_000_000);
+    assert_eq!(done.read_at, Some(done.added_at));
+
+    let tags = item_repo.item_tags(&test_user(), &new.id).await.unwrap();
+
+    assert_eq!(tag_names(&tags), vec!["rust", "to read"]);
+
+    let usage = item_repo.list_tags(&test_user()).await.unwrap();
+
+    assert_eq!(
+        usage
+            .iter()
+            .map(|usage| (usage.tag.name.as_str(), usage.item_count))
+            .collect::<Vec<_>>(),
+        vec![("rust", 2), ("to read", 1)]
+    );
+
+    // the first item of the fixture is left as it was
+    let first = item_repo
+        .get_item(
+            &test_user(),
+            &Uuid::parse_str("0c1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001").unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(first.title.as_deref(), Some("First"));
+    assert_eq!(first.added_at.to_rfc3339(), "2024-02-01T10:00:00+00:00");
+
+    let report = item_repo.import_items(&test_user(), entries).await.unwrap();
+
+    assert!(report.created.is_empty());
+    assert_eq!(report.skipped.len(), 4);
+    assert_eq!(report.failed.len(), 1);
+}
+
+pub async fn import_items_unknown_user(item_repo: impl ItemRepository) {
+    let entries = pocket::parse_export(include_str!("../fixtures/ril_export.html"));
+
+    match item_repo.import_items(&Uuid::new_v4(), entries).await {
+        Ok(_) => panic!("Imported items for unknown user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for importing for unknown user: {err}"),
+    }
+}
+
+pub async fn import_items_deleted_user(
+    user_repo: impl UserRepository,
+    item_repo: impl ItemRepository,
+) {
+    let user_id = deleted_user(&user_repo).await;
+    let entries = pocket::parse_export(include_str!("../fixtures/ril_export.html"));
+
+    match item_repo.import_items(&user_id, entries).await {
+        Ok(_) => panic!("Imported items for deleted user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for importing for deleted user: {err}"),
+    }
+
+    let page = item_repo
+        .list_items(&user_id, ListItems::default())
+        .await
+        .unwrap();
+
+    assert!(page.items.is_empty());
+}
+
+pub async fn import_items_in_batches(item_repo: impl ItemRepository) {
+    let entries: Vec<ImportItem> = (0..250)
+        .map(|index| ImportItem {
+            // every tenth entry repeats the one before
+            url: format!("https://example.net/{}", index - (index % 10 == 9) as i32),
+            title: None,
+            tags: if index == 120 {
+                vec![" ".to_string()]
+            } else {
+                Vec::new()
+            },
+            status: ItemStatus::Unread,
+            added_at: None,
+        })
+        .collect();
+
+    let report = item_repo.import_items(&test_user(), entries).await.unwrap();
+
+    assert_eq!(report.created.len(), 224);
+    assert_eq!(report.skipped.len(), 25);
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(&report.failed[0].url, "https://example.net/120");
+
+    let page = item_repo
+        .list_items(
+            &test_user(),
+            ListItems {
+                with_total: true,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+
+    // on top of the three fixture items
+    assert_eq!(page.total, Some(227));
+}
@@ -0,0 +1,163 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use data::repository::{
+    role::{
+        postgres::PostgresRoleRepository, AnyRoleRepository, RoleRepository, RoleRepositorySettings,
+    },
+    user::{postgres::PostgresUserRepository, AnyUserRepository, UserRepositorySettings},
+};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+mod role_suite;
+mod utils;
+
+use utils::{connect, postgres_settings};
+
+fn argon() -> Argon2<'static> {
+    Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap()
+}
+
+async fn build_repos(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<(PostgresUserRepository, PostgresRoleRepository)> {
+    let conn = connect(pool_options, connect_options).await?;
+
+    Ok((
+        PostgresUserRepository::new(conn.clone(), argon()),
+        PostgresRoleRepository::new(conn),
+    ))
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn list_roles(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, role_repo) = build_repos(pool_options, connect_options).await?;
+
+    role_suite::list_roles(role_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn assign_and_revoke_role(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, role_repo) = build_repos(pool_options, connect_options).await?;
+
+    role_suite::assign_and_revoke_role(role_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "user_list"))]
+async fn has_permission(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, role_repo) = build_repos(pool_options, connect_options).await?;
+
+    role_suite::has_permission(role_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "user_list"))]
+async fn deleted_user_permissions(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (user_repo, role_repo) = build_repos(pool_options, connect_options).await?;
+
+    role_suite::deleted_user_permissions(user_repo, role_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "user_list"))]
+async fn assign_role_deleted_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (user_repo, role_repo) = build_repos(pool_options, connect_options).await?;
+
+    role_suite::assign_role_deleted_user(user_repo, role_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn assign_role_unknown(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, role_repo) = build_repos(pool_options, connect_options).await?;
+
+    role_suite::assign_role_unknown(role_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "user_list"))]
+async fn revoke_last_admin(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, role_repo) = build_repos(pool_options, connect_options).await?;
+
+    role_suite::revoke_last_admin(role_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "user_list"))]
+async fn delete_last_admin(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (user_repo, role_repo) = build_repos(pool_options, connect_options).await?;
+
+    role_suite::delete_last_admin(user_repo, role_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn from_settings(
+    _pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let settings = postgres_settings(connect_options);
+
+    let user_repo = AnyUserRepository::from_settings(
+        &UserRepositorySettings::Postgres(settings.clone()),
+        argon(),
+    )
+    .await
+    .unwrap();
+
+    let role_repo =
+        AnyRoleRepository::from_settings(&RoleRepositorySettings::Postgres(settings), &user_repo)
+            .await
+            .unwrap();
+
+    assert!(matches!(role_repo, AnyRoleRepository::Postgres(_)));
+
+    role_repo.list_roles().await.unwrap();
+
+    match AnyRoleRepository::from_settings(&RoleRepositorySettings::Mock, &user_repo).await {
+        Ok(_) => panic!("Built mock roles for Postgres users"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for mixed backends: {err}"),
+    }
+
+    Ok(())
+}
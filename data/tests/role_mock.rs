@@ -0,0 +1,189 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chrono::Utc;
+use data::{
+    model::{role::ADMIN_ROLE, user::User},
+    repository::{
+        role::{
+            mock::MockRoleRepository, AnyRoleRepository, RoleRepository, RoleRepositorySettings,
+        },
+        user::{
+            mock::MockUserRepository, AnyUserRepository, UserRepository, UserRepositorySettings,
+        },
+    },
+};
+use uuid::Uuid;
+
+mod role_suite;
+
+use role_suite::{bob, test_user};
+
+fn argon() -> Argon2<'static> {
+    Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap()
+}
+
+fn fixture_user(id: Uuid, email: &str, created_at: &str) -> User {
+    User {
+        id,
+        email: email.to_string(),
+        // dev_only_pass
+        hash: "$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw".to_string(),
+        created_at: created_at.parse().unwrap(),
+        updated_at: created_at.parse().unwrap(),
+        email_verified_at: None,
+        deleted_at: None,
+    }
+}
+
+async fn build_repos() -> (MockUserRepository, MockRoleRepository) {
+    let user_repo = MockUserRepository::new(argon());
+    let role_repo = MockRoleRepository::new(user_repo.clone());
+
+    // same user as in fixtures/user.sql
+    user_repo
+        .insert_user(User {
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            ..fixture_user(test_user(), "test@myemail.com", "2024-01-01T00:00:00Z")
+        })
+        .await
+        .unwrap();
+    role_repo
+        .assign_role(&test_user(), ADMIN_ROLE)
+        .await
+        .unwrap();
+
+    (user_repo, role_repo)
+}
+
+// same users as in fixtures/user_list.sql
+async fn seed_user_list(user_repo: &MockUserRepository, role_repo: &MockRoleRepository) {
+    let users = [
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001",
+            "alice@example.com",
+            "2024-01-01T10:00:00Z",
+        ),
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002",
+            "bob@example.com",
+            "2024-01-02T10:00:00Z",
+        ),
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0003",
+            "carol@example.com",
+            "2024-01-03T10:00:00Z",
+        ),
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0004",
+            "dave@example.com",
+            "2024-01-04T10:00:00Z",
+        ),
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0005",
+            "erin@example.com",
+            "2024-01-05T10:00:00Z",
+        ),
+    ];
+
+    for (id, email, created_at) in users {
+        user_repo
+            .insert_user(fixture_user(
+                Uuid::parse_str(id).unwrap(),
+                email,
+                created_at,
+            ))
+            .await
+            .unwrap();
+    }
+
+    role_repo.assign_role(&bob(), ADMIN_ROLE).await.unwrap();
+}
+
+#[tokio::test]
+async fn list_roles() {
+    let (_, role_repo) = build_repos().await;
+    role_suite::list_roles(role_repo).await;
+}
+
+#[tokio::test]
+async fn assign_and_revoke_role() {
+    let (_, role_repo) = build_repos().await;
+    role_suite::assign_and_revoke_role(role_repo).await;
+}
+
+#[tokio::test]
+async fn has_permission() {
+    let (user_repo, role_repo) = build_repos().await;
+    seed_user_list(&user_repo, &role_repo).await;
+
+    role_suite::has_permission(role_repo).await;
+}
+
+#[tokio::test]
+async fn deleted_user_permissions() {
+    let (user_repo, role_repo) = build_repos().await;
+    seed_user_list(&user_repo, &role_repo).await;
+
+    role_suite::deleted_user_permissions(user_repo, role_repo).await;
+}
+
+#[tokio::test]
+async fn assign_role_deleted_user() {
+    let (user_repo, role_repo) = build_repos().await;
+    seed_user_list(&user_repo, &role_repo).await;
+
+    role_suite::assign_role_deleted_user(user_repo, role_repo).await;
+}
+
+#[tokio::test]
+async fn assign_role_unknown() {
+    let (_, role_repo) = build_repos().await;
+    role_suite::assign_role_unknown(role_repo).await;
+}
+
+#[tokio::test]
+async fn revoke_last_admin() {
+    let (user_repo, role_repo) = build_repos().await;
+    seed_user_list(&user_repo, &role_repo).await;
+
+    role_suite::revoke_last_admin(role_repo).await;
+}
+
+#[tokio::test]
+async fn delete_last_admin() {
+    let (user_repo, role_repo) = build_repos().await;
+    seed_user_list(&user_repo, &role_repo).await;
+
+    role_suite::delete_last_admin(user_repo, role_repo).await;
+}
+
+#[tokio::test]
+async fn from_settings() {
+    let user_repo = AnyUserRepository::from_settings(&UserRepositorySettings::Mock, argon())
+        .await
+        .unwrap();
+
+    let role_repo = AnyRoleRepository::from_settings(&RoleRepositorySettings::Mock, &user_repo)
+        .await
+        .unwrap();
+
+    assert!(matches!(role_repo, AnyRoleRepository::Mock(_)));
+
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    role_repo.assign_role(&user.id, "auditor").await.unwrap();
+
+    let users = role_repo.users_with_role("auditor").await.unwrap();
+
+    assert_eq!(users.len(), 1);
+    assert_eq!(users.first().unwrap().id, user.id);
+}
@@ -0,0 +1,195 @@
+//! Behaviour every [`RoleRepository`] backend shares, run by the thin tests in `role.rs`
+//! and `role_mock.rs` for the users of `fixtures/user.sql` and `fixtures/user_list.sql`
+//! or their in-memory copies.
+
+use data::{
+    model::role::{permission, ADMIN_ROLE},
+    repository::{role::RoleRepository, user::UserRepository},
+};
+use uuid::Uuid;
+
+/// Admin in `fixtures/user.sql`.
+pub fn test_user() -> Uuid {
+    Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap()
+}
+
+/// Admin in `fixtures/user_list.sql`.
+pub fn bob() -> Uuid {
+    Uuid::parse_str("0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002").unwrap()
+}
+
+/// No role in `fixtures/user_list.sql`.
+pub fn carol() -> Uuid {
+    Uuid::parse_str("0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0003").unwrap()
+}
+
+pub async fn list_roles(role_repo: impl RoleRepository) {
+    let roles = role_repo.list_roles().await.unwrap();
+    let names: Vec<&str> = roles.iter().map(|role| role.name.as_str()).collect();
+
+    assert_eq!(names, vec!["admin", "auditor", "moderator", "support"]);
+
+    let moderator = roles.iter().find(|role| role.name == "moderator").unwrap();
+
+    assert_eq!(
+        moderator.permissions,
+        vec![permission::ITEMS_MODERATE, permission::USERS_READ]
+    );
+}
+
+pub async fn assign_and_revoke_role(role_repo: impl RoleRepository) {
+    let user_id = test_user();
+
+    let roles = role_repo.user_roles(&user_id).await.unwrap();
+
+    assert_eq!(roles.len(), 1);
+    assert_eq!(&roles.first().unwrap().name, ADMIN_ROLE);
+    assert!(role_repo
+        .has_permission(&user_id, permission::AUDIT_READ)
+        .await
+        .unwrap());
+
+    role_repo.assign_role(&user_id, "moderator").await.unwrap();
+
+    let users = role_repo.users_with_role("moderator").await.unwrap();
+
+    assert_eq!(users.len(), 1);
+    assert_eq!(&users.first().unwrap().email, "test@myemail.com");
+
+    match role_repo.assign_role(&user_id, "moderator").await {
+        Ok(_) => panic!("Assigned role twice"),
+        Err(data::Error::AlreadyExists(_)) => (),
+        Err(err) => panic!("Get wrong error for assigned role: {err}"),
+    }
+
+    role_repo.revoke_role(&user_id, "moderator").await.unwrap();
+
+    assert!(role_repo
+        .users_with_role("moderator")
+        .await
+        .unwrap()
+        .is_empty());
+
+    match role_repo.revoke_role(&user_id, "moderator").await {
+        Ok(_) => panic!("Revoked role that isn't assigned"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for role that isn't assigned: {err}"),
+    }
+}
+
+pub async fn has_permission(role_repo: impl RoleRepository) {
+    let user_id = carol();
+
+    assert!(!role_repo
+        .has_permission(&user_id, permission::USERS_READ)
+        .await
+        .unwrap());
+
+    role_repo.assign_role(&user_id, "support").await.unwrap();
+
+    assert!(role_repo
+        .has_permission(&user_id, permission::USERS_READ)
+        .await
+        .unwrap());
+    assert!(role_repo
+        .has_permission(&user_id, permission::USERS_WRITE)
+        .await
+        .unwrap());
+    assert!(!role_repo
+        .has_permission(&user_id, permission::USERS_DELETE)
+        .await
+        .unwrap());
+    assert!(!role_repo
+        .has_permission(&Uuid::new_v4(), permission::USERS_READ)
+        .await
+        .unwrap());
+
+    let admins = role_repo.users_with_role(ADMIN_ROLE).await.unwrap();
+    let emails: Vec<&str> = admins.iter().map(|user| user.email.as_str()).collect();
+
+    assert_eq!(emails, vec!["bob@example.com", "test@myemail.com"]);
+}
+
+pub async fn deleted_user_permissions(
+    user_repo: impl UserRepository,
+    role_repo: impl RoleRepository,
+) {
+    user_repo.delete_user(&bob(), None).await.unwrap();
+
+    assert!(!role_repo
+        .has_permission(&bob(), permission::USERS_READ)
+        .await
+        .unwrap());
+
+    let admins = role_repo.users_with_role(ADMIN_ROLE).await.unwrap();
+    let emails: Vec<&str> = admins.iter().map(|user| user.email.as_str()).collect();
+
+    assert_eq!(emails, vec!["test@myemail.com"]);
+
+    // a deleted admin doesn't count, the remaining one is the last
+    match role_repo.revoke_role(&test_user(), ADMIN_ROLE).await {
+        Ok(_) => panic!("Revoked last active admin"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for last active admin: {err}"),
+    }
+}
+
+pub async fn assign_role_deleted_user(
+    user_repo: impl UserRepository,
+    role_repo: impl RoleRepository,
+) {
+    user_repo.delete_user(&carol(), None).await.unwrap();
+
+    match role_repo.assign_role(&carol(), "support").await {
+        Ok(_) => panic!("Assigned role to deleted user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for deleted user: {err}"),
+    }
+
+    assert!(role_repo.user_roles(&carol()).await.unwrap().is_empty());
+}
+
+pub async fn assign_role_unknown(role_repo: impl RoleRepository) {
+    match role_repo.assign_role(&test_user(), "superuser").await {
+        Ok(_) => panic!("Assigned unknown role"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown role: {err}"),
+    }
+
+    match role_repo.assign_role(&Uuid::new_v4(), "moderator").await {
+        Ok(_) => panic!("Assigned role to unknown user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown user: {err}"),
+    }
+}
+
+pub async fn revoke_last_admin(role_repo: impl RoleRepository) {
+    role_repo.revoke_role(&bob(), ADMIN_ROLE).await.unwrap();
+
+    match role_repo.revoke_role(&test_user(), ADMIN_ROLE).await {
+        Ok(_) => panic!("Revoked last admin"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for last admin: {err}"),
+    }
+
+    assert!(role_repo
+        .has_permission(&test_user(), permission::ROLES_ASSIGN)
+        .await
+        .unwrap());
+}
+
+pub async fn delete_last_admin(user_repo: impl UserRepository, role_repo: impl RoleRepository) {
+    user_repo.delete_user(&bob(), None).await.unwrap();
+
+    // bob was deleted, the remaining admin is the last
+    match user_repo.delete_user(&test_user(), None).await {
+        Ok(_) => panic!("Deleted last active admin"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for last active admin: {err}"),
+    }
+
+    assert!(role_repo
+        .has_permission(&test_user(), permission::ROLES_ASSIGN)
+        .await
+        .unwrap());
+}
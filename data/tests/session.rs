@@ -0,0 +1,233 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use data::repository::{
+    session::{
+        postgres::PostgresSessionRepository, AnySessionRepository, SessionRepository,
+        SessionRepositorySettings, SessionSettings,
+    },
+    user::{postgres::PostgresUserRepository, AnyUserRepository, UserRepositorySettings},
+};
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
+
+mod session_suite;
+mod utils;
+
+use session_suite::test_user;
+use utils::{connect, postgres_settings};
+
+async fn build_repos(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<(PostgresUserRepository, PostgresSessionRepository)> {
+    let conn = connect(pool_options, connect_options).await?;
+
+    let config = Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap();
+
+    Ok((
+        PostgresUserRepository::new(conn.clone(), config),
+        PostgresSessionRepository::new(conn, SessionSettings::default()),
+    ))
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn login(pool_options: PgPoolOptions, connect_options: PgConnectOptions) -> sqlx::Result<()> {
+    let (user_repo, session_repo) = build_repos(pool_options, connect_options).await?;
+
+    session_suite::login(user_repo, session_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn session_tokens_hashed(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, session_repo) = build_repos(pool_options, connect_options).await?;
+
+    let issued = session_repo.create_session(&test_user()).await.unwrap();
+
+    // only hashes are stored
+    for token in [&issued.token, &issued.refresh_token] {
+        let stored: i64 = sqlx::query_scalar(
+            r#"
+                SELECT
+                    (SELECT COUNT(*) FROM sessions WHERE token_hash = $1)
+                    + (SELECT COUNT(*) FROM session_refresh_tokens WHERE token_hash = $1);
+            "#,
+        )
+        .bind(token)
+        .fetch_one(&session_repo.pool)
+        .await?;
+
+        assert_eq!(stored, 0);
+    }
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn login_wrong_password(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (user_repo, session_repo) = build_repos(pool_options, connect_options).await?;
+
+    session_suite::login_wrong_password(user_repo, session_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_session_unknown_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, session_repo) = build_repos(pool_options, connect_options).await?;
+
+    session_suite::create_session_unknown_user(session_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_session_deleted_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (user_repo, session_repo) = build_repos(pool_options, connect_options).await?;
+
+    session_suite::create_session_deleted_user(user_repo, session_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn validate_session_expired(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, session_repo) = build_repos(pool_options, connect_options).await?;
+
+    let idle = session_repo.create_session(&test_user()).await.unwrap();
+    let absolute = session_repo.create_session(&test_user()).await.unwrap();
+
+    sqlx::query("UPDATE sessions SET last_seen_at = NOW() - INTERVAL '15 days' WHERE id = $1;")
+        .bind(idle.session.id)
+        .execute(&session_repo.pool)
+        .await?;
+    sqlx::query("UPDATE sessions SET expires_at = NOW() - INTERVAL '1 second' WHERE id = $1;")
+        .bind(absolute.session.id)
+        .execute(&session_repo.pool)
+        .await?;
+
+    for token in [&idle.token, &absolute.token, &"not a token".to_string()] {
+        match session_repo.validate_session(token).await {
+            Ok(_) => panic!("Validated expired session"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for expired session: {err}"),
+        }
+    }
+
+    match session_repo.refresh_session(&idle.refresh_token).await {
+        Ok(_) => panic!("Refreshed expired session"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for expired session: {err}"),
+    }
+
+    let deleted = session_repo.delete_expired_sessions().await.unwrap();
+
+    assert_eq!(deleted, 2);
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn revoke_sessions(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, session_repo) = build_repos(pool_options, connect_options).await?;
+
+    session_suite::revoke_sessions(session_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn refresh_session(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (_, session_repo) = build_repos(pool_options, connect_options).await?;
+
+    session_suite::refresh_session(session_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn reset_password_revokes_sessions(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let (user_repo, session_repo) = build_repos(pool_options, connect_options).await?;
+
+    session_suite::reset_password_revokes_sessions(user_repo, session_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn from_settings(
+    _pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let settings = postgres_settings(connect_options);
+
+    let config = Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap();
+
+    let user_repo = AnyUserRepository::from_settings(
+        &UserRepositorySettings::Postgres(settings.clone()),
+        config,
+    )
+    .await
+    .unwrap();
+
+    let session_repo = AnySessionRepository::from_settings(
+        &SessionRepositorySettings::Postgres(settings),
+        &user_repo,
+        SessionSettings::default(),
+    )
+    .await
+    .unwrap();
+
+    assert!(matches!(session_repo, AnySessionRepository::Postgres(_)));
+
+    session_repo.delete_expired_sessions().await.unwrap();
+
+    match AnySessionRepository::from_settings(
+        &SessionRepositorySettings::Mock,
+        &user_repo,
+        SessionSettings::default(),
+    )
+    .await
+    {
+        Ok(_) => panic!("Built mock sessions for Postgres users"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for mixed backends: {err}"),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,154 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chrono::{Duration, Utc};
+use data::{
+    model::user::User,
+    repository::{
+        session::{
+            mock::MockSessionRepository, AnySessionRepository, SessionRepository,
+            SessionRepositorySettings, SessionSettings,
+        },
+        user::{
+            mock::MockUserRepository, AnyUserRepository, UserRepository, UserRepositorySettings,
+        },
+    },
+};
+
+mod session_suite;
+
+use session_suite::test_user;
+
+fn argon() -> Argon2<'static> {
+    Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap()
+}
+
+async fn build_repos(settings: SessionSettings) -> (MockUserRepository, MockSessionRepository) {
+    let user_repo = MockUserRepository::new(argon());
+
+    // same user as in fixtures/user.sql
+    user_repo
+        .insert_user(User {
+            id: test_user(),
+            email: "test@myemail.com".to_string(),
+            // dev_only_pass
+            hash: "$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw".to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+            email_verified_at: None,
+            deleted_at: None,
+        })
+        .await
+        .unwrap();
+
+    let session_repo = MockSessionRepository::new(user_repo.clone(), settings);
+
+    (user_repo, session_repo)
+}
+
+#[tokio::test]
+async fn login() {
+    let (user_repo, session_repo) = build_repos(SessionSettings::default()).await;
+    session_suite::login(user_repo, session_repo).await;
+}
+
+#[tokio::test]
+async fn login_wrong_password() {
+    let (user_repo, session_repo) = build_repos(SessionSettings::default()).await;
+    session_suite::login_wrong_password(user_repo, session_repo).await;
+}
+
+#[tokio::test]
+async fn create_session_unknown_user() {
+    let (_, session_repo) = build_repos(SessionSettings::default()).await;
+    session_suite::create_session_unknown_user(session_repo).await;
+}
+
+#[tokio::test]
+async fn create_session_deleted_user() {
+    let (user_repo, session_repo) = build_repos(SessionSettings::default()).await;
+    session_suite::create_session_deleted_user(user_repo, session_repo).await;
+}
+
+#[tokio::test]
+async fn validate_session_expired() {
+    // sessions expire as soon as they are created
+    let (_, idle_repo) = build_repos(SessionSettings {
+        idle_ttl: Duration::zero(),
+        ..SessionSettings::default()
+    })
+    .await;
+    let (_, absolute_repo) = build_repos(SessionSettings {
+        absolute_ttl: Duration::zero(),
+        ..SessionSettings::default()
+    })
+    .await;
+
+    for session_repo in [&idle_repo, &absolute_repo] {
+        let issued = session_repo.create_session(&test_user()).await.unwrap();
+
+        match session_repo.validate_session(&issued.token).await {
+            Ok(_) => panic!("Validated expired session"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for expired session: {err}"),
+        }
+
+        match session_repo.refresh_session(&issued.refresh_token).await {
+            Ok(_) => panic!("Refreshed expired session"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for expired session: {err}"),
+        }
+
+        let deleted = session_repo.delete_expired_sessions().await.unwrap();
+
+        assert_eq!(deleted, 1);
+    }
+}
+
+#[tokio::test]
+async fn revoke_sessions() {
+    let (_, session_repo) = build_repos(SessionSettings::default()).await;
+    session_suite::revoke_sessions(session_repo).await;
+}
+
+#[tokio::test]
+async fn refresh_session() {
+    let (_, session_repo) = build_repos(SessionSettings::default()).await;
+    session_suite::refresh_session(session_repo).await;
+}
+
+#[tokio::test]
+async fn reset_password_revokes_sessions() {
+    let (user_repo, session_repo) = build_repos(SessionSettings::default()).await;
+    session_suite::reset_password_revokes_sessions(user_repo, session_repo).await;
+}
+
+#[tokio::test]
+async fn from_settings() {
+    let user_repo = AnyUserRepository::from_settings(&UserRepositorySettings::Mock, argon())
+        .await
+        .unwrap();
+
+    let session_repo = AnySessionRepository::from_settings(
+        &SessionRepositorySettings::Mock,
+        &user_repo,
+        SessionSettings::default(),
+    )
+    .await
+    .unwrap();
+
+    assert!(matches!(session_repo, AnySessionRepository::Mock(_)));
+
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    let issued = session_repo.create_session(&user.id).await.unwrap();
+
+    assert_eq!(issued.session.user_id, user.id);
+}
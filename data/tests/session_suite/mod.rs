@@ -0,0 +1,190 @@
+//! Behaviour every [`SessionRepository`] backend shares, run by the thin tests in
+//! `session.rs` and `session_mock.rs` for the user of `fixtures/user.sql` or its
+//! in-memory copy.
+
+use data::repository::{session::SessionRepository, user::UserRepository};
+use uuid::Uuid;
+
+pub fn test_user() -> Uuid {
+    Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap()
+}
+
+pub async fn login(
+    user_repo: impl UserRepository + Sync,
+    session_repo: impl SessionRepository + Sync,
+) {
+    let issued = session_repo
+        .login(&user_repo, "test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    assert_eq!(issued.session.user_id, test_user());
+    assert_ne!(issued.token, issued.refresh_token);
+
+    let session = session_repo.validate_session(&issued.token).await.unwrap();
+
+    assert_eq!(session.id, issued.session.id);
+    assert!(session.last_seen_at >= issued.session.last_seen_at);
+}
+
+pub async fn login_wrong_password(
+    user_repo: impl UserRepository + Sync,
+    session_repo: impl SessionRepository + Sync,
+) {
+    match session_repo
+        .login(&user_repo, "test@myemail.com", "not_my_pass")
+        .await
+    {
+        Ok(_) => panic!("Logged in with wrong password"),
+        Err(data::Error::PasswordMismatch) => (),
+        Err(err) => panic!("Get wrong error for wrong password: {err}"),
+    }
+
+    // no session was started
+    let revoked = session_repo
+        .revoke_user_sessions(&test_user())
+        .await
+        .unwrap();
+
+    assert_eq!(revoked, 0);
+}
+
+pub async fn create_session_unknown_user(session_repo: impl SessionRepository) {
+    match session_repo.create_session(&Uuid::new_v4()).await {
+        Ok(_) => panic!("Created session for unknown user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown user: {err}"),
+    }
+}
+
+pub async fn create_session_deleted_user(
+    user_repo: impl UserRepository,
+    session_repo: impl SessionRepository,
+) {
+    // the fixture user is the last admin, which can't be deleted
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    let issued = session_repo.create_session(&user.id).await.unwrap();
+
+    user_repo.delete_user(&user.id, None).await.unwrap();
+
+    match session_repo.validate_session(&issued.token).await {
+        Ok(_) => panic!("Validated session of deleted user"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for revoked session: {err}"),
+    }
+
+    match session_repo.create_session(&user.id).await {
+        Ok(_) => panic!("Created session for deleted user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for deleted user: {err}"),
+    }
+}
+
+pub async fn revoke_sessions(session_repo: impl SessionRepository) {
+    let user_id = test_user();
+
+    let first = session_repo.create_session(&user_id).await.unwrap();
+    let second = session_repo.create_session(&user_id).await.unwrap();
+    let third = session_repo.create_session(&user_id).await.unwrap();
+
+    let revoked = session_repo
+        .revoke_session(&first.session.id)
+        .await
+        .unwrap();
+
+    assert!(revoked.revoked_at.is_some());
+    assert!(session_repo.validate_session(&first.token).await.is_err());
+    assert!(session_repo.validate_session(&second.token).await.is_ok());
+
+    let revoked = session_repo.revoke_user_sessions(&user_id).await.unwrap();
+
+    assert_eq!(revoked, 2);
+    assert!(session_repo.validate_session(&second.token).await.is_err());
+    assert!(session_repo.validate_session(&third.token).await.is_err());
+
+    match session_repo.revoke_session(&Uuid::new_v4()).await {
+        Ok(_) => panic!("Revoked unknown session"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown session: {err}"),
+    }
+
+    let deleted = session_repo.delete_expired_sessions().await.unwrap();
+
+    assert_eq!(deleted, 3);
+}
+
+pub async fn refresh_session(session_repo: impl SessionRepository) {
+    let issued = session_repo.create_session(&test_user()).await.unwrap();
+
+    let refreshed = session_repo
+        .refresh_session(&issued.refresh_token)
+        .await
+        .unwrap();
+
+    assert_eq!(refreshed.session.id, issued.session.id);
+    assert_ne!(refreshed.token, issued.token);
+    assert_ne!(refreshed.refresh_token, issued.refresh_token);
+
+    assert!(session_repo.validate_session(&issued.token).await.is_err());
+    assert!(session_repo
+        .validate_session(&refreshed.token)
+        .await
+        .is_ok());
+
+    // replaying the old refresh token kills the session for everyone
+    match session_repo.refresh_session(&issued.refresh_token).await {
+        Ok(_) => panic!("Refreshed session with used token"),
+        Err(data::Error::TokenReuse) => (),
+        Err(err) => panic!("Get wrong error for reused token: {err}"),
+    }
+
+    assert!(session_repo
+        .validate_session(&refreshed.token)
+        .await
+        .is_err());
+    assert!(session_repo
+        .refresh_session(&refreshed.refresh_token)
+        .await
+        .is_err());
+
+    match session_repo.refresh_session("not a token").await {
+        Ok(_) => panic!("Refreshed session with invalid token"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for invalid token: {err}"),
+    }
+}
+
+pub async fn reset_password_revokes_sessions(
+    user_repo: impl UserRepository + Sync,
+    session_repo: impl SessionRepository + Sync,
+) {
+    let issued = session_repo
+        .login(&user_repo, "test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    let token = user_repo
+        .issue_password_reset("test@myemail.com")
+        .await
+        .unwrap()
+        .unwrap();
+    user_repo
+        .reset_password(&token, "my_new_password")
+        .await
+        .unwrap();
+
+    match session_repo.validate_session(&issued.token).await {
+        Ok(_) => panic!("Validated session after password reset"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for revoked session: {err}"),
+    }
+
+    session_repo
+        .login(&user_repo, "test@myemail.com", "my_new_password")
+        .await
+        .unwrap();
+}
@@ -2,15 +2,20 @@ use argon2::{
     password_hash::{rand_core::OsRng, PasswordHasher, PasswordVerifier, SaltString},
     Algorithm, Argon2, Params, PasswordHash, Version,
 };
+use chrono::Duration;
 use data::{
-    model::user::{PasswordUpdate, UpdateUser},
-    repository::user::{postgres::PostgresUserRepository, UserRepository},
+    model::user::ListUsers,
+    repository::user::{
+        postgres::PostgresUserRepository, AccountSettings, AnyUserRepository, LockoutSettings,
+        UserRepository, UserRepositorySettings,
+    },
 };
 use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 
+mod user_suite;
 mod utils;
 
-use utils::connect;
+use utils::{connect, postgres_settings};
 use uuid::Uuid;
 
 async fn build_repo(
@@ -37,11 +42,55 @@ async fn list_users(
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let users = user_repo.list_users().await.unwrap();
+    user_suite::list_users(user_repo).await;
 
-    assert_eq!(users.len(), 1);
-    let first_user = users.first().unwrap();
-    assert_eq!(&first_user.email, "test@myemail.com");
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "user_list"))]
+async fn list_users_paginated(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::list_users_paginated(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "user_list"))]
+async fn list_users_sorted(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::list_users_sorted(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "user_list"))]
+async fn list_users_filtered(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::list_users_filtered(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user", "user_list"))]
+async fn list_users_invalid_cursor(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::list_users_invalid_cursor(user_repo).await;
 
     Ok(())
 }
@@ -53,162 +102,527 @@ async fn get_user(
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+    user_suite::get_user(user_repo).await;
 
-    let user = user_repo.get_user(&user_id).await.unwrap();
+    Ok(())
+}
 
-    assert_eq!(&user.email, "test@myemail.com");
+#[sqlx::test(fixtures("user"))]
+async fn create_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::create_user(user_repo).await;
 
     Ok(())
 }
 
 #[sqlx::test(fixtures("user"))]
-async fn create_user(
+async fn change_email(
     pool_options: PgPoolOptions,
     connect_options: PgConnectOptions,
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let user = user_repo
-        .create_user("test2@myemail.com", "my_test_password")
-        .await
-        .unwrap();
+    user_suite::change_email(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn change_email_invalid_token(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
 
-    assert_eq!(&user.email, "test2@myemail.com");
+    user_suite::change_email_invalid_token(user_repo).await;
 
-    let users = user_repo.list_users().await.unwrap();
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn update_user_password(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
 
-    assert_eq!(users.len(), 2);
+    user_suite::update_user_password(user_repo).await;
 
     Ok(())
 }
 
 #[sqlx::test(fixtures("user"))]
-async fn update_user_email(
+async fn update_user_password_wrong_old_password(
     pool_options: PgPoolOptions,
     connect_options: PgConnectOptions,
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let update_email = "another@myemail.com";
-    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+    user_suite::update_user_password_wrong_old_password(user_repo).await;
 
-    let update = UpdateUser {
-        email: Some(update_email.to_string()),
-        password: None,
-    };
+    Ok(())
+}
 
-    let user = user_repo.update_user(&user_id, update).await.unwrap();
+#[sqlx::test(fixtures("user"))]
+async fn reset_user_password(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
 
-    assert_eq!(&user.email, update_email);
+    user_suite::reset_user_password(user_repo).await;
 
-    let user = user_repo.get_user(&user_id).await.unwrap();
+    Ok(())
+}
 
-    assert_eq!(&user.email, update_email);
+#[sqlx::test(fixtures("user"))]
+async fn delete_user(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::delete_user(user_repo).await;
 
     Ok(())
 }
 
 #[sqlx::test(fixtures("user"))]
-async fn update_user_password(
+async fn delete_and_restore_user(
     pool_options: PgPoolOptions,
     connect_options: PgConnectOptions,
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let new_password = "brand_new_pass";
-    let old_password = "dev_only_pass";
+    user_suite::delete_and_restore_user(user_repo).await;
 
-    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+    Ok(())
+}
 
-    let update = UpdateUser {
-        email: None,
-        password: Some(PasswordUpdate {
-            new_password: new_password.to_string(),
-            old_password: old_password.to_string(),
-        }),
-    };
+#[sqlx::test(fixtures("user"))]
+async fn purge_deleted_users(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let user = user_repo.update_user(&user_id, update).await.unwrap();
+    user_suite::purge_deleted_users(user_repo).await;
+
+    Ok(())
+}
 
-    let validate = user_repo.verify_password(new_password, &user.hash).await;
+#[sqlx::test(fixtures("user"))]
+async fn get_user_not_found(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
 
-    assert!(validate.is_ok());
+    user_suite::get_user_not_found(user_repo).await;
 
     Ok(())
 }
 
 #[sqlx::test(fixtures("user"))]
-async fn update_user_email_and_password(
+async fn create_user_duplicate_email(
     pool_options: PgPoolOptions,
     connect_options: PgConnectOptions,
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let update_email = "another@myemail.com";
-    let new_password = "brand_new_pass";
-    let old_password = "dev_only_pass";
+    user_suite::create_user_duplicate_email(user_repo).await;
 
-    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn change_email_duplicate_email(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::change_email_duplicate_email(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_user_duplicate_email_case(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let update = UpdateUser {
-        email: Some(update_email.to_string()),
-        password: Some(PasswordUpdate {
-            new_password: new_password.to_string(),
-            old_password: old_password.to_string(),
-        }),
-    };
+    user_suite::create_user_duplicate_email_case(user_repo).await;
 
-    let user = user_repo.update_user(&user_id, update).await.unwrap();
+    Ok(())
+}
 
-    let validate = user_repo.verify_password(new_password, &user.hash).await;
+#[sqlx::test(fixtures("user"))]
+async fn create_user_email_non_ascii(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
 
-    assert!(validate.is_ok());
+    user_suite::create_user_email_non_ascii(user_repo).await;
 
     Ok(())
 }
 
 #[sqlx::test(fixtures("user"))]
-async fn delete_user(
+async fn create_user_invalid_email(
     pool_options: PgPoolOptions,
     connect_options: PgConnectOptions,
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let user = user_repo
+    user_suite::create_user_invalid_email(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn purge_deleted_user_referenced(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    // not the fixture user, the last admin can't be deleted
+    let user_id = user_repo
         .create_user("test2@myemail.com", "my_test_password")
         .await
-        .unwrap();
+        .unwrap()
+        .id;
+
+    sqlx::query("CREATE TABLE user_refs (user_id UUID NOT NULL REFERENCES users (id));")
+        .execute(&user_repo.pool)
+        .await?;
+    sqlx::query("INSERT INTO user_refs (user_id) VALUES ($1);")
+        .bind(user_id)
+        .execute(&user_repo.pool)
+        .await?;
+
+    // only purging removes the row
+    user_repo.delete_user(&user_id, None).await.unwrap();
+
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        deletion_grace_period: Duration::zero(),
+        ..Default::default()
+    });
+
+    match user_repo.purge_deleted_users().await {
+        Ok(_) => panic!("Purged user with active references"),
+        Err(data::Error::CannotDeleteReferenced(_)) => (),
+        Err(err) => panic!("Get wrong error for referenced user: {err}"),
+    }
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn insufficient_privilege(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    // single connection, so the role set below applies to every repository query
+    let user_repo = build_repo(pool_options.max_connections(1), connect_options).await?;
+
+    sqlx::query(
+        r#"
+            DO $$
+            BEGIN
+                IF NOT EXISTS (SELECT FROM pg_roles WHERE rolname = 'slowpocket_no_access') THEN
+                    CREATE ROLE slowpocket_no_access NOLOGIN;
+                END IF;
+            END
+            $$;
+        "#,
+    )
+    .execute(&user_repo.pool)
+    .await?;
+    sqlx::query("SET ROLE slowpocket_no_access;")
+        .execute(&user_repo.pool)
+        .await?;
+
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    match user_repo.get_user(&user_id).await {
+        Ok(_) => panic!("Read user without privileges"),
+        Err(data::Error::InsufficientPrivilege(_)) => (),
+        Err(err) => panic!("Get wrong error for missing privileges: {err}"),
+    }
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn update_user_bumps_updated_at(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::update_user_bumps_updated_at(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn update_user_conflict(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::update_user_conflict(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_email(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::verify_email(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_email_invalid_token(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::verify_email_invalid_token(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_user_password_unverified(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::verify_user_password_unverified(user_repo).await;
 
-    assert_eq!(&user.email, "test2@myemail.com");
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn reset_password(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::reset_password(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn reset_password_invalid_token(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
 
-    // let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+    user_suite::reset_password_invalid_token(user_repo).await;
 
-    let user2 = user_repo.delete_user(&user.id).await.unwrap();
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_user_password_lockout(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
 
-    assert_eq!(user2.id, user.id);
+    user_suite::verify_user_password_lockout(user_repo).await;
 
-    match user_repo.get_user(&user.id).await {
-        Ok(_) => panic!("Can get user after deletion"),
-        Err(data::Error::NotFound(_)) => Ok(()),
-        Err(data::Error::ReadError(sqlx::Error::RowNotFound)) => Ok(()),
-        Err(err) => panic!("Get wrong error after getting deleted user: {err}"),
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_user_password_source_lockout(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::verify_user_password_source_lockout(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_user_password_long_email(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::verify_user_password_long_email(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_user_password_lockout_backoff(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options)
+        .await?
+        .with_account_settings(AccountSettings {
+            lockout: LockoutSettings {
+                max_attempts: 1,
+                lockout: Duration::minutes(1),
+                max_lockout: Duration::minutes(3),
+                ..LockoutSettings::default()
+            },
+            ..AccountSettings::default()
+        });
+
+    let mut lockouts = Vec::new();
+
+    for _ in 0..4 {
+        assert!(user_repo
+            .verify_user_password("test@myemail.com", "wrong_pass")
+            .await
+            .is_err());
+
+        let minutes: f64 = sqlx::query_scalar(
+            r#"
+                SELECT EXTRACT(EPOCH FROM locked_until - last_failed_at)::FLOAT8 / 60
+                FROM login_throttle
+                WHERE scope = 'account';
+            "#,
+        )
+        .fetch_one(&user_repo.pool)
+        .await?;
+        lockouts.push(minutes.round() as i64);
+
+        // let the lock run out
+        sqlx::query("UPDATE login_throttle SET locked_until = NOW();")
+            .execute(&user_repo.pool)
+            .await?;
     }
+
+    assert_eq!(lockouts, vec![1, 2, 3, 3]);
+
+    Ok(())
 }
 
 #[sqlx::test(fixtures("user"))]
-async fn verify_and_hash(
+async fn verify_user_password_rehash(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+    let costly_repo =
+        PostgresUserRepository::new(user_repo.pool.clone(), user_suite::costly_argon());
+
+    user_suite::verify_user_password_rehash(user_repo, costly_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_user_password_pepper_rotation(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::verify_user_password_pepper_rotation(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn import_users(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::import_users(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn import_users_roles(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::import_users_roles(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn import_users_invalid(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::import_users_invalid(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn create_user_weak_password(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::create_user_weak_password(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn update_user_weak_password(
     pool_options: PgPoolOptions,
     connect_options: PgConnectOptions,
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let user_pass = "some_test_pass";
-    let hash = user_repo.hash_password(user_pass).await.unwrap();
+    user_suite::update_user_weak_password(user_repo).await;
 
-    let verify = user_repo.verify_password(user_pass, &hash).await;
+    Ok(())
+}
 
-    assert!(verify.is_ok());
+#[sqlx::test(fixtures("user"))]
+async fn verify_and_hash(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::verify_and_hash(user_repo).await;
 
     Ok(())
 }
@@ -220,7 +634,7 @@ async fn hash_password(
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    user_repo.hash_password("dev_only_pass").await.unwrap();
+    user_suite::hash_password(user_repo).await;
 
     Ok(())
 }
@@ -232,11 +646,105 @@ async fn verify_user_password(
 ) -> sqlx::Result<()> {
     let user_repo = build_repo(pool_options, connect_options).await?;
 
-    let user_pass = "dev_only_pass";
-    let user_email = "test@myemail.com";
-    let verify = user_repo.verify_user_password(user_email, user_pass).await;
+    user_suite::verify_user_password(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_user_password_unknown_email(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::verify_user_password_unknown_email(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn verify_user_password_email_case(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::verify_user_password_email_case(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn totp_login(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::totp_login(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn totp_challenge(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::totp_challenge(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn totp_enroll_email_with_colon(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::totp_enroll_email_with_colon(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test(fixtures("user"))]
+async fn totp_lockout(
+    pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let user_repo = build_repo(pool_options, connect_options).await?;
+
+    user_suite::totp_lockout(user_repo).await;
+
+    Ok(())
+}
+
+#[sqlx::test]
+async fn from_settings(
+    _pool_options: PgPoolOptions,
+    connect_options: PgConnectOptions,
+) -> sqlx::Result<()> {
+    let config = Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap();
+
+    let settings = UserRepositorySettings::Postgres(postgres_settings(connect_options));
+
+    let user_repo = AnyUserRepository::from_settings(&settings, config)
+        .await
+        .unwrap();
+
+    assert!(matches!(user_repo, AnyUserRepository::Postgres(_)));
 
-    assert!(verify.is_ok());
+    user_repo.list_users(ListUsers::default()).await.unwrap();
 
     Ok(())
 }
@@ -0,0 +1,479 @@
+use argon2::{Algorithm, Argon2, Params, Version};
+use chrono::Utc;
+use data::{
+    model::{
+        email::Email,
+        role::ADMIN_ROLE,
+        user::{ListUsers, User},
+    },
+    repository::{
+        role::{mock::MockRoleRepository, RoleRepository},
+        user::{
+            mock::MockUserRepository, pepper::PepperKeyring, policy::BreachedPasswords,
+            totp::TotpSettings, AnyUserRepository, UserRepository, UserRepositorySettings,
+        },
+    },
+};
+use uuid::Uuid;
+
+mod user_suite;
+
+async fn build_repo() -> MockUserRepository {
+    let config = Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap();
+
+    let repo = MockUserRepository::new(config);
+
+    // same user as in fixtures/user.sql
+    repo.insert_user(User {
+        id: Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap(),
+        email: "test@myemail.com".to_string(),
+        // dev_only_pass
+        hash: "$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw".to_string(),
+        created_at: Utc::now(),
+        updated_at: Utc::now(),
+        email_verified_at: None,
+        deleted_at: None,
+    })
+    .await
+    .unwrap();
+
+    MockRoleRepository::new(repo.clone())
+        .assign_role(
+            &Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap(),
+            ADMIN_ROLE,
+        )
+        .await
+        .unwrap();
+
+    repo
+}
+
+// same users as in fixtures/user_list.sql
+async fn seed_user_list(repo: &MockUserRepository) {
+    let users = [
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0001",
+            "alice@example.com",
+            "2024-01-01T10:00:00Z",
+        ),
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002",
+            "bob@example.com",
+            "2024-01-02T10:00:00Z",
+        ),
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0003",
+            "carol@example.com",
+            "2024-01-03T10:00:00Z",
+        ),
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0004",
+            "dave@example.com",
+            "2024-01-04T10:00:00Z",
+        ),
+        (
+            "0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0005",
+            "erin@example.com",
+            "2024-01-05T10:00:00Z",
+        ),
+    ];
+
+    for (id, email, created_at) in users {
+        repo.insert_user(User {
+            id: Uuid::parse_str(id).unwrap(),
+            email: email.to_string(),
+            hash: "$argon2id$v=19$m=19456,t=2,p=1$l9VfAtWMe+bWqP81cgsDuQ$Z+ExthpqUCPuHSwxtHI1RP17OyVGo1/bapupD+cJYzw".to_string(),
+            created_at: created_at.parse().unwrap(),
+            updated_at: created_at.parse().unwrap(),
+            email_verified_at: None,
+            deleted_at: None,
+        })
+        .await
+        .unwrap();
+    }
+
+    MockRoleRepository::new(repo.clone())
+        .assign_role(
+            &Uuid::parse_str("0b1a4c4e-6f4e-4a55-9d3e-0a9c6f1f0002").unwrap(),
+            ADMIN_ROLE,
+        )
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn list_users() {
+    user_suite::list_users(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn list_users_paginated() {
+    let user_repo = build_repo().await;
+    seed_user_list(&user_repo).await;
+
+    user_suite::list_users_paginated(user_repo).await;
+}
+
+#[tokio::test]
+async fn list_users_sorted() {
+    let user_repo = build_repo().await;
+    seed_user_list(&user_repo).await;
+
+    user_suite::list_users_sorted(user_repo).await;
+}
+
+#[tokio::test]
+async fn list_users_filtered() {
+    let user_repo = build_repo().await;
+    seed_user_list(&user_repo).await;
+
+    user_suite::list_users_filtered(user_repo).await;
+}
+
+#[tokio::test]
+async fn list_users_invalid_cursor() {
+    let user_repo = build_repo().await;
+    seed_user_list(&user_repo).await;
+
+    user_suite::list_users_invalid_cursor(user_repo).await;
+}
+
+#[tokio::test]
+async fn get_user() {
+    user_suite::get_user(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_user() {
+    user_suite::create_user(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_user_duplicate_email() {
+    user_suite::create_user_duplicate_email(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn get_user_not_found() {
+    user_suite::get_user_not_found(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn change_email_duplicate_email() {
+    user_suite::change_email_duplicate_email(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_user_duplicate_email_case() {
+    user_suite::create_user_duplicate_email_case(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_user_email_non_ascii() {
+    user_suite::create_user_email_non_ascii(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_user_invalid_email() {
+    user_suite::create_user_invalid_email(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn change_email() {
+    user_suite::change_email(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn change_email_invalid_token() {
+    user_suite::change_email_invalid_token(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn update_user_password() {
+    user_suite::update_user_password(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn update_user_password_wrong_old_password() {
+    user_suite::update_user_password_wrong_old_password(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn reset_user_password() {
+    user_suite::reset_user_password(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn delete_user() {
+    user_suite::delete_user(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn delete_and_restore_user() {
+    user_suite::delete_and_restore_user(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn purge_deleted_users() {
+    user_suite::purge_deleted_users(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn update_user_bumps_updated_at() {
+    user_suite::update_user_bumps_updated_at(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn update_user_conflict() {
+    user_suite::update_user_conflict(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_email() {
+    user_suite::verify_email(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_email_invalid_token() {
+    user_suite::verify_email_invalid_token(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_user_password_unverified() {
+    user_suite::verify_user_password_unverified(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn reset_password() {
+    user_suite::reset_password(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn reset_password_invalid_token() {
+    user_suite::reset_password_invalid_token(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_user_password_lockout() {
+    user_suite::verify_user_password_lockout(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_user_password_source_lockout() {
+    user_suite::verify_user_password_source_lockout(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_user_password_long_email() {
+    user_suite::verify_user_password_long_email(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_user_password_rehash() {
+    let user_repo = build_repo().await;
+    let costly_repo = MockUserRepository::new(user_suite::costly_argon());
+    let fixture = user_repo
+        .get_user(&Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap())
+        .await
+        .unwrap();
+    costly_repo.insert_user(fixture).await.unwrap();
+
+    user_suite::verify_user_password_rehash(user_repo, costly_repo).await;
+}
+
+#[tokio::test]
+async fn verify_user_password_pepper_rotation() {
+    user_suite::verify_user_password_pepper_rotation(build_repo().await).await;
+}
+
+#[test]
+fn pepper_keyring_invalid_id() {
+    let keyring = PepperKeyring::new(
+        "longer_than_8",
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    );
+
+    assert!(matches!(keyring, Err(data::Error::InvalidArgument(_))));
+
+    let keyring = PepperKeyring::new(
+        "k1",
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap()
+    .with_retired("k1", b"myothersecret");
+
+    assert!(matches!(keyring, Err(data::Error::AlreadyExists(_))));
+}
+
+#[tokio::test]
+async fn import_users() {
+    user_suite::import_users(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn import_users_roles() {
+    user_suite::import_users_roles(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn import_users_invalid() {
+    user_suite::import_users_invalid(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn create_user_weak_password() {
+    user_suite::create_user_weak_password(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn update_user_weak_password() {
+    user_suite::update_user_weak_password(build_repo().await).await;
+}
+
+#[test]
+fn breached_passwords_from_file() {
+    let path = std::env::temp_dir().join(format!("breached-{}.txt", Uuid::new_v4()));
+    let passwords: Vec<String> = (0..1000).map(|i| format!("leaked_{i}")).collect();
+    std::fs::write(&path, passwords.join("\n")).unwrap();
+
+    let breached = BreachedPasswords::from_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(passwords.iter().all(|password| breached.contains(password)));
+
+    let false_positives = (0..1000)
+        .filter(|i| breached.contains(&format!("not_leaked_{i}")))
+        .count();
+
+    assert!(false_positives < 50, "{false_positives} false positives");
+
+    assert!(BreachedPasswords::from_file(&path).is_err());
+}
+
+#[tokio::test]
+async fn verify_and_hash() {
+    user_suite::verify_and_hash(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn hash_password() {
+    user_suite::hash_password(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_user_password() {
+    user_suite::verify_user_password(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_user_password_unknown_email() {
+    user_suite::verify_user_password_unknown_email(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn verify_user_password_email_case() {
+    user_suite::verify_user_password_email_case(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn totp_login() {
+    user_suite::totp_login(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn totp_challenge() {
+    user_suite::totp_challenge(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn totp_enroll_email_with_colon() {
+    user_suite::totp_enroll_email_with_colon(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn totp_lockout() {
+    user_suite::totp_lockout(build_repo().await).await;
+}
+
+#[tokio::test]
+async fn from_settings() {
+    let config = Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap();
+
+    let user_repo = AnyUserRepository::from_settings(&UserRepositorySettings::Mock, config)
+        .await
+        .unwrap();
+
+    assert!(matches!(user_repo, AnyUserRepository::Mock(_)));
+
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    let users = user_repo
+        .list_users(ListUsers::default())
+        .await
+        .unwrap()
+        .items;
+
+    assert_eq!(users.len(), 1);
+    assert_eq!(users.first().unwrap().id, user.id);
+}
+
+#[test]
+fn email_parse() {
+    let email = Email::parse(" Foo.Bar+tag@Example.COM ").unwrap();
+
+    assert_eq!(email.as_str(), "Foo.Bar+tag@example.com");
+    assert_eq!(
+        Email::lookup_key(email.as_str()),
+        Email::lookup_key("foo.bar+TAG@example.com")
+    );
+
+    let email: Email = serde_json::from_str(r#""user@Example.org""#).unwrap();
+    assert_eq!(email.as_str(), "user@example.org");
+
+    for invalid in [
+        "user@-example.org",
+        "user@example..org",
+        "a@b@",
+        "user@exa_mple.org",
+    ] {
+        assert!(matches!(
+            Email::parse(invalid),
+            Err(data::Error::InvalidArgument(_))
+        ));
+    }
+
+    assert!(serde_json::from_str::<Email>(r#""user""#).is_err());
+}
+
+#[test]
+fn totp_settings_invalid() {
+    assert!(matches!(
+        TotpSettings::new("slowpocket", b"too short"),
+        Err(data::Error::InvalidArgument(_))
+    ));
+    assert!(matches!(
+        TotpSettings::new("slow:pocket", &[1; 32]),
+        Err(data::Error::InvalidArgument(_))
+    ));
+}
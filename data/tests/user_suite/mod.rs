@@ -0,0 +1,1975 @@
+//! Behaviour every [`UserRepository`] backend shares, run by the thin tests in
+//! `user.rs` and `user_mock.rs` against the fixtures in `fixtures/user.sql` (and
+//! `fixtures/user_list.sql` for the listing tests) or their in-memory copies.
+
+use std::sync::Arc;
+
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHasher, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+use chrono::{DateTime, Duration, Utc};
+use data::{
+    model::{
+        role::ADMIN_ROLE,
+        user::{
+            ImportUser, ListUsers, PasswordUpdate, PasswordViolation, UpdateUser, UserFilter,
+            UserSort,
+        },
+    },
+    repository::user::{
+        mock::MockUserRepository,
+        pepper::PepperKeyring,
+        policy::{BreachedPasswords, PasswordPolicy},
+        postgres::PostgresUserRepository,
+        totp::TotpSettings,
+        AccountSettings, LockoutSettings, UserRepository,
+    },
+};
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+use totp_rs::{Algorithm as TotpAlgorithm, Secret, TOTP};
+use uuid::Uuid;
+
+/// Builder methods of the backends, so a test can tweak the repository it gets.
+pub trait UserRepositoryExt: UserRepository + Sized {
+    fn with_account_settings(self, settings: AccountSettings) -> Self;
+    fn with_pepper_keyring(self, keyring: PepperKeyring) -> Self;
+}
+
+impl UserRepositoryExt for PostgresUserRepository {
+    fn with_account_settings(self, settings: AccountSettings) -> Self {
+        self.with_account_settings(settings)
+    }
+
+    fn with_pepper_keyring(self, keyring: PepperKeyring) -> Self {
+        self.with_pepper_keyring(keyring)
+    }
+}
+
+impl UserRepositoryExt for MockUserRepository {
+    fn with_account_settings(self, settings: AccountSettings) -> Self {
+        self.with_account_settings(settings)
+    }
+
+    fn with_pepper_keyring(self, keyring: PepperKeyring) -> Self {
+        self.with_pepper_keyring(keyring)
+    }
+}
+
+/// Same secret as the test repositories, higher cost than the fixture hash.
+pub fn costly_argon() -> Argon2<'static> {
+    Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::new(32768, 3, 1, None).unwrap(),
+    )
+    .unwrap()
+}
+
+pub async fn list_emails(user_repo: &impl UserRepository, query: ListUsers) -> Vec<String> {
+    let mut emails = Vec::new();
+    let mut query = query;
+
+    loop {
+        let page = user_repo.list_users(query.clone()).await.unwrap();
+        emails.extend(page.items.into_iter().map(|user| user.email));
+
+        match page.next_cursor {
+            Some(cursor) => query.cursor = Some(cursor),
+            None => return emails,
+        }
+    }
+}
+
+// cheap parameters, the hashes only need the legacy formats
+pub fn legacy_hashes(password: &str) -> Vec<String> {
+    let salt = SaltString::generate(&mut OsRng);
+
+    let bcrypt = bcrypt::hash(password, 4).unwrap();
+    let pbkdf2 = Pbkdf2
+        .hash_password_customized(
+            password.as_bytes(),
+            Some(pbkdf2::Algorithm::Pbkdf2Sha256.ident()),
+            None,
+            pbkdf2::Params {
+                rounds: 1000,
+                output_length: 32,
+            },
+            &salt,
+        )
+        .unwrap()
+        .to_string();
+    let scrypt = Scrypt
+        .hash_password_customized(
+            password.as_bytes(),
+            None,
+            None,
+            scrypt::Params::new(10, 8, 1, 32).unwrap(),
+            &salt,
+        )
+        .unwrap()
+        .to_string();
+
+    vec![bcrypt, pbkdf2, scrypt]
+}
+
+pub fn import_user(email: &str, hash: String) -> ImportUser {
+    ImportUser {
+        email: email.to_string(),
+        hash,
+        created_at: None,
+        email_verified_at: None,
+        roles: Vec::new(),
+    }
+}
+
+pub fn totp_settings(key: u8) -> AccountSettings {
+    AccountSettings {
+        totp: Some(TotpSettings::new("slowpocket", &[key; 32]).unwrap()),
+        ..AccountSettings::default()
+    }
+}
+
+pub fn totp_code(secret: &str, offset_steps: i64) -> String {
+    let totp = TOTP::new(
+        TotpAlgorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(secret.to_string()).to_bytes().unwrap(),
+        None,
+        String::new(),
+    )
+    .unwrap();
+
+    totp.generate((Utc::now().timestamp() + offset_steps * 30) as u64)
+}
+
+/// Logs the fixture user in with its password, which stops at the second factor.
+async fn login_challenge(user_repo: &impl UserRepository) -> String {
+    match user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+    {
+        Ok(_) => panic!("Logged in without second factor"),
+        Err(data::Error::SecondFactorRequired(challenge)) => challenge,
+        Err(err) => panic!("Get wrong error for missing second factor: {err}"),
+    }
+}
+
+pub async fn list_users(user_repo: impl UserRepositoryExt) {
+    let users = user_repo
+        .list_users(ListUsers::default())
+        .await
+        .unwrap()
+        .items;
+
+    assert_eq!(users.len(), 1);
+    let first_user = users.first().unwrap();
+    assert_eq!(&first_user.email, "test@myemail.com");
+}
+
+pub async fn list_users_paginated(user_repo: impl UserRepositoryExt) {
+    let page = user_repo
+        .list_users(ListUsers {
+            limit: Some(2),
+            with_total: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(page.total, Some(6));
+    assert!(page.next_cursor.is_some());
+
+    let emails = list_emails(
+        &user_repo,
+        ListUsers {
+            limit: Some(2),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        emails,
+        vec![
+            "alice@example.com",
+            "bob@example.com",
+            "carol@example.com",
+            "dave@example.com",
+            "erin@example.com",
+            "test@myemail.com",
+        ]
+    );
+}
+
+pub async fn list_users_sorted(user_repo: impl UserRepositoryExt) {
+    let emails = list_emails(
+        &user_repo,
+        ListUsers {
+            sort: UserSort::EmailDesc,
+            limit: Some(4),
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        emails,
+        vec![
+            "test@myemail.com",
+            "erin@example.com",
+            "dave@example.com",
+            "carol@example.com",
+            "bob@example.com",
+            "alice@example.com",
+        ]
+    );
+
+    let emails = list_emails(
+        &user_repo,
+        ListUsers {
+            sort: UserSort::CreatedAtDesc,
+            limit: Some(1),
+            filter: UserFilter {
+                email_contains: Some("@example.com".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(
+        emails,
+        vec![
+            "erin@example.com",
+            "dave@example.com",
+            "carol@example.com",
+            "bob@example.com",
+            "alice@example.com",
+        ]
+    );
+}
+
+pub async fn list_users_filtered(user_repo: impl UserRepositoryExt) {
+    let page = user_repo
+        .list_users(ListUsers {
+            filter: UserFilter {
+                email_contains: Some("AR".to_string()),
+                ..Default::default()
+            },
+            with_total: true,
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(page.total, Some(1));
+    assert_eq!(&page.items.first().unwrap().email, "carol@example.com");
+
+    let emails = list_emails(
+        &user_repo,
+        ListUsers {
+            filter: UserFilter {
+                role: Some(ADMIN_ROLE.to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(emails, vec!["bob@example.com", "test@myemail.com"]);
+
+    let emails = list_emails(
+        &user_repo,
+        ListUsers {
+            filter: UserFilter {
+                created_after: Some("2024-01-02T10:00:00Z".parse().unwrap()),
+                created_before: Some("2024-01-04T10:00:00Z".parse().unwrap()),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(emails, vec!["bob@example.com", "carol@example.com"]);
+
+    let page = user_repo
+        .list_users(ListUsers {
+            filter: UserFilter {
+                email_contains: Some("%".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    assert!(page.items.is_empty());
+    assert!(page.next_cursor.is_none());
+}
+
+pub async fn list_users_invalid_cursor(user_repo: impl UserRepositoryExt) {
+    let page = user_repo
+        .list_users(ListUsers {
+            limit: Some(1),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+
+    // cursor of another sort order
+    let query = ListUsers {
+        sort: UserSort::EmailAsc,
+        cursor: page.next_cursor,
+        ..Default::default()
+    };
+
+    match user_repo.list_users(query).await {
+        Ok(_) => panic!("Listed users with mismatched cursor"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for mismatched cursor: {err}"),
+    }
+
+    let query = ListUsers {
+        cursor: Some("not a cursor".to_string()),
+        ..Default::default()
+    };
+
+    match user_repo.list_users(query).await {
+        Ok(_) => panic!("Listed users with invalid cursor"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for invalid cursor: {err}"),
+    }
+}
+
+pub async fn get_user(user_repo: impl UserRepositoryExt) {
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let user = user_repo.get_user(&user_id).await.unwrap();
+
+    assert_eq!(&user.email, "test@myemail.com");
+}
+
+pub async fn create_user(user_repo: impl UserRepositoryExt) {
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    assert_eq!(&user.email, "test2@myemail.com");
+
+    let users = user_repo
+        .list_users(ListUsers::default())
+        .await
+        .unwrap()
+        .items;
+
+    assert_eq!(users.len(), 2);
+}
+
+pub async fn change_email(user_repo: impl UserRepositoryExt) {
+    let update_email = "another@myemail.com";
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    match user_repo
+        .request_email_change(&user_id, update_email, "not_my_pass")
+        .await
+    {
+        Ok(_) => panic!("Requested email change with wrong password"),
+        Err(data::Error::PasswordMismatch) => (),
+        Err(err) => panic!("Get wrong error for wrong password: {err}"),
+    }
+
+    let replaced = user_repo
+        .request_email_change(&user_id, "third@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+    let change = user_repo
+        .request_email_change(&user_id, update_email, "dev_only_pass")
+        .await
+        .unwrap();
+
+    assert_eq!(change.user_id, user_id);
+    assert_eq!(&change.current_email, "test@myemail.com");
+    assert_eq!(&change.new_email, update_email);
+
+    // nothing changes until the new address confirms
+    let user = user_repo.get_user(&user_id).await.unwrap();
+
+    assert_eq!(&user.email, "test@myemail.com");
+
+    match user_repo.confirm_email_change(&replaced.token).await {
+        Ok(_) => panic!("Changed email with replaced token"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for replaced token: {err}"),
+    }
+
+    let changed = user_repo.confirm_email_change(&change.token).await.unwrap();
+
+    assert_eq!(&changed.previous_email, "test@myemail.com");
+    assert_eq!(&changed.user.email, update_email);
+    assert!(changed.user.email_verified_at.is_some());
+
+    let user = user_repo.get_user(&user_id).await.unwrap();
+
+    assert_eq!(&user.email, update_email);
+
+    assert!(user_repo
+        .verify_user_password(update_email, "dev_only_pass")
+        .await
+        .is_ok());
+
+    match user_repo.confirm_email_change(&change.token).await {
+        Ok(_) => panic!("Changed email with used token"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for used token: {err}"),
+    }
+}
+
+pub async fn change_email_invalid_token(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        email_change_ttl: Duration::zero(),
+        ..AccountSettings::default()
+    });
+
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let expired = user_repo
+        .request_email_change(&user_id, "another@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    let user_repo = user_repo.with_account_settings(AccountSettings::default());
+
+    let cancelled = user_repo
+        .request_email_change(&user_id, "another@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    user_repo.cancel_email_change(&user_id).await.unwrap();
+
+    // the password reset drops pending changes too
+    let dropped = user_repo
+        .request_email_change(&user_id, "another@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+    let reset = user_repo
+        .issue_password_reset("test@myemail.com")
+        .await
+        .unwrap()
+        .unwrap();
+
+    user_repo
+        .reset_password(&reset, "brand_new_pass")
+        .await
+        .unwrap();
+
+    for token in [
+        &expired.token,
+        &cancelled.token,
+        &dropped.token,
+        &"not a token".to_string(),
+    ] {
+        match user_repo.confirm_email_change(token).await {
+            Ok(_) => panic!("Changed email with invalid token"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for invalid token: {err}"),
+        }
+    }
+
+    let user = user_repo.get_user(&user_id).await.unwrap();
+
+    assert_eq!(&user.email, "test@myemail.com");
+
+    match user_repo.cancel_email_change(&Uuid::new_v4()).await {
+        Ok(_) => panic!("Cancelled email change of unknown user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown user: {err}"),
+    }
+}
+
+pub async fn update_user_password(user_repo: impl UserRepositoryExt) {
+    let new_password = "brand_new_pass";
+    let old_password = "dev_only_pass";
+
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let update = UpdateUser {
+        password: Some(PasswordUpdate {
+            new_password: new_password.to_string(),
+            old_password: old_password.to_string(),
+        }),
+        expected_updated_at: None,
+    };
+
+    let user = user_repo.update_user(&user_id, update).await.unwrap();
+
+    let validate = user_repo.verify_password(new_password, &user.hash).await;
+
+    assert!(validate.is_ok());
+}
+
+pub async fn update_user_password_wrong_old_password(user_repo: impl UserRepositoryExt) {
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let update = UpdateUser {
+        password: Some(PasswordUpdate {
+            new_password: "brand_new_pass".to_string(),
+            old_password: "not_my_pass".to_string(),
+        }),
+        expected_updated_at: None,
+    };
+
+    match user_repo.update_user(&user_id, update).await {
+        Ok(_) => panic!("Updated password with wrong old password"),
+        Err(data::Error::PasswordMismatch) => (),
+        Err(err) => panic!("Get wrong error for wrong old password: {err}"),
+    }
+
+    let user = user_repo.get_user(&user_id).await.unwrap();
+
+    assert_eq!(&user.email, "test@myemail.com");
+    assert!(user_repo
+        .verify_password("dev_only_pass", &user.hash)
+        .await
+        .is_ok());
+}
+
+pub async fn reset_user_password(user_repo: impl UserRepositoryExt) {
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let user = user_repo
+        .reset_user_password(&user_id, "brand_new_pass")
+        .await
+        .unwrap();
+
+    assert!(user_repo
+        .verify_password("brand_new_pass", &user.hash)
+        .await
+        .is_ok());
+}
+
+pub async fn delete_user(user_repo: impl UserRepositoryExt) {
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    assert_eq!(&user.email, "test2@myemail.com");
+
+    let user2 = user_repo.delete_user(&user.id, None).await.unwrap();
+
+    assert_eq!(user2.id, user.id);
+
+    match user_repo.get_user(&user.id).await {
+        Ok(_) => panic!("Can get user after deletion"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(data::Error::ReadError(sqlx::Error::RowNotFound)) => (),
+        Err(err) => panic!("Get wrong error after getting deleted user: {err}"),
+    }
+}
+
+pub async fn delete_and_restore_user(user_repo: impl UserRepositoryExt) {
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    match user_repo.restore_user(&user.id).await {
+        Ok(_) => panic!("Restored user that isn't deleted"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for restoring active user: {err}"),
+    }
+
+    let deleted = user_repo.delete_user(&user.id, None).await.unwrap();
+
+    assert!(deleted.deleted_at.is_some());
+
+    match user_repo
+        .verify_user_password("test2@myemail.com", "my_test_password")
+        .await
+    {
+        Ok(_) => panic!("Logged in as deleted user"),
+        Err(data::Error::PasswordMismatch) => (),
+        Err(err) => panic!("Get wrong error for deleted user login: {err}"),
+    }
+
+    let emails = list_emails(&user_repo, ListUsers::default()).await;
+
+    assert_eq!(emails, vec!["test@myemail.com"]);
+
+    let emails = list_emails(
+        &user_repo,
+        ListUsers {
+            filter: UserFilter {
+                include_deleted: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(emails, vec!["test@myemail.com", "test2@myemail.com"]);
+
+    // the email is kept for a restore
+    match user_repo
+        .create_user("Test2@myemail.com", "my_test_password")
+        .await
+    {
+        Ok(_) => panic!("Created user with email of deleted user"),
+        Err(data::Error::AlreadyExists(_)) => (),
+        Err(err) => panic!("Get wrong error for email of deleted user: {err}"),
+    }
+
+    match user_repo.delete_user(&user.id, None).await {
+        Ok(_) => panic!("Deleted user twice"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for deleting deleted user: {err}"),
+    }
+
+    let restored = user_repo.restore_user(&user.id).await.unwrap();
+
+    assert!(restored.deleted_at.is_none());
+
+    let user = user_repo
+        .verify_user_password("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    assert_eq!(user.id, restored.id);
+}
+
+pub async fn purge_deleted_users(user_repo: impl UserRepositoryExt) {
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    user_repo.delete_user(&user.id, None).await.unwrap();
+
+    // still within the grace period
+    assert_eq!(user_repo.purge_deleted_users().await.unwrap(), 0);
+
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        deletion_grace_period: Duration::zero(),
+        ..Default::default()
+    });
+
+    match user_repo.restore_user(&user.id).await {
+        Ok(_) => panic!("Restored user after the grace period"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for expired restore: {err}"),
+    }
+
+    assert_eq!(user_repo.purge_deleted_users().await.unwrap(), 1);
+    assert_eq!(user_repo.purge_deleted_users().await.unwrap(), 0);
+
+    let emails = list_emails(
+        &user_repo,
+        ListUsers {
+            filter: UserFilter {
+                include_deleted: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+    )
+    .await;
+
+    assert_eq!(emails, vec!["test@myemail.com"]);
+
+    // purged users free their email
+    user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+}
+
+pub async fn get_user_not_found(user_repo: impl UserRepositoryExt) {
+    let user_id = Uuid::new_v4();
+
+    match user_repo.get_user(&user_id).await {
+        Ok(_) => panic!("Got user that doesn't exist"),
+        Err(data::Error::NotFound(entity)) => assert_eq!(entity, user_id.to_string()),
+        Err(err) => panic!("Get wrong error for missing user: {err}"),
+    }
+}
+
+pub async fn create_user_duplicate_email(user_repo: impl UserRepositoryExt) {
+    match user_repo
+        .create_user("test@myemail.com", "my_test_password")
+        .await
+    {
+        Err(data::Error::AlreadyExists(email)) => assert_eq!(&email, "test@myemail.com"),
+        Ok(_) => panic!("Created user with duplicated email"),
+        Err(err) => panic!("Get wrong error for duplicated email: {err}"),
+    }
+}
+
+pub async fn change_email_duplicate_email(user_repo: impl UserRepositoryExt) {
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    match user_repo
+        .request_email_change(&user.id, "test@myemail.com", "my_test_password")
+        .await
+    {
+        Ok(_) => panic!("Requested change to duplicated email"),
+        Err(data::Error::AlreadyExists(email)) => assert_eq!(&email, "test@myemail.com"),
+        Err(err) => panic!("Get wrong error for duplicated email: {err}"),
+    }
+
+    match user_repo
+        .request_email_change(&user.id, "Test2@myemail.com", "my_test_password")
+        .await
+    {
+        Ok(_) => panic!("Requested change to the current email"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for current email: {err}"),
+    }
+
+    // the address is taken between request and confirmation
+    let change = user_repo
+        .request_email_change(&user.id, "another@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    user_repo
+        .create_user("Another@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    match user_repo.confirm_email_change(&change.token).await {
+        Ok(_) => panic!("Changed to duplicated email"),
+        Err(data::Error::AlreadyExists(email)) => assert_eq!(&email, "another@myemail.com"),
+        Err(err) => panic!("Get wrong error for duplicated email: {err}"),
+    }
+
+    let user = user_repo.get_user(&user.id).await.unwrap();
+
+    assert_eq!(&user.email, "test2@myemail.com");
+}
+
+pub async fn create_user_duplicate_email_case(user_repo: impl UserRepositoryExt) {
+    match user_repo
+        .create_user(" Test@MyEmail.COM ", "my_test_password")
+        .await
+    {
+        Ok(_) => panic!("Created user with duplicated email"),
+        Err(data::Error::AlreadyExists(email)) => assert_eq!(&email, "Test@myemail.com"),
+        Err(err) => panic!("Get wrong error for duplicated email: {err}"),
+    }
+
+    let user = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        user_repo
+            .request_email_change(&user.id, "TEST@myemail.com", "my_test_password")
+            .await,
+        Err(data::Error::AlreadyExists(_))
+    ));
+
+    let import = vec![
+        import_user("imported@myemail.com", user.hash.clone()),
+        import_user("Imported@MyEmail.com", user.hash.clone()),
+    ];
+
+    assert!(matches!(
+        user_repo.import_users(import).await,
+        Err(data::Error::AlreadyExists(_))
+    ));
+}
+
+pub async fn create_user_email_non_ascii(user_repo: impl UserRepositoryExt) {
+    let user = user_repo
+        .create_user("Émile@MyEmail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    assert_eq!(user.email, "Émile@myemail.com");
+
+    // only ASCII letters are folded, on both backends
+    match user_repo
+        .create_user("ÉMILE@myemail.com", "my_test_password")
+        .await
+    {
+        Ok(_) => panic!("Created user with duplicated email"),
+        Err(data::Error::AlreadyExists(email)) => assert_eq!(&email, "ÉMILE@myemail.com"),
+        Err(err) => panic!("Get wrong error for duplicated email: {err}"),
+    }
+
+    let verified = user_repo
+        .verify_user_password("ÉMILE@MYEMAIL.COM", "my_test_password")
+        .await
+        .unwrap();
+
+    assert_eq!(verified.id, user.id);
+
+    match user_repo
+        .verify_user_password("émile@myemail.com", "my_test_password")
+        .await
+    {
+        Ok(_) => panic!("Verified password of another email"),
+        Err(data::Error::PasswordMismatch) => (),
+        Err(err) => panic!("Get wrong error for unknown email: {err}"),
+    }
+
+    let other = user_repo
+        .create_user("émile@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    assert_ne!(other.id, user.id);
+}
+
+pub async fn create_user_invalid_email(user_repo: impl UserRepositoryExt) {
+    for email in [
+        "",
+        "no-at-sign",
+        "@myemail.com",
+        "test@",
+        "test@localhost",
+        "te st@myemail.com",
+    ] {
+        match user_repo.create_user(email, "my_test_password").await {
+            Ok(_) => panic!("Created user with invalid email {email:?}"),
+            Err(data::Error::InvalidArgument(_)) => {}
+            Err(err) => panic!("Get wrong error for invalid email {email:?}: {err}"),
+        }
+    }
+
+    let user = user_repo
+        .create_user("  New.User@MyEmail.COM\n", "my_test_password")
+        .await
+        .unwrap();
+
+    assert_eq!(user.email, "New.User@myemail.com");
+
+    assert!(matches!(
+        user_repo
+            .request_email_change(&user.id, "not an email", "my_test_password")
+            .await,
+        Err(data::Error::InvalidArgument(_))
+    ));
+}
+
+pub async fn update_user_bumps_updated_at(user_repo: impl UserRepositoryExt) {
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let before = user_repo.get_user(&user_id).await.unwrap();
+
+    let update = UpdateUser {
+        password: Some(PasswordUpdate {
+            old_password: "dev_only_pass".to_string(),
+            new_password: "brand_new_pass".to_string(),
+        }),
+        expected_updated_at: Some(before.updated_at),
+    };
+
+    let after = user_repo.update_user(&user_id, update).await.unwrap();
+
+    assert!(after.updated_at > before.updated_at);
+    assert_eq!(after.created_at, before.created_at);
+}
+
+pub async fn update_user_conflict(user_repo: impl UserRepositoryExt) {
+    // not the fixture user, the last admin can't be deleted
+    let user_id = user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+        .unwrap()
+        .id;
+
+    let stale = user_repo.get_user(&user_id).await.unwrap();
+
+    let update = UpdateUser {
+        password: Some(PasswordUpdate {
+            old_password: "my_test_password".to_string(),
+            new_password: "brand_new_pass".to_string(),
+        }),
+        expected_updated_at: Some(stale.updated_at),
+    };
+
+    user_repo.update_user(&user_id, update).await.unwrap();
+
+    let update = UpdateUser {
+        password: Some(PasswordUpdate {
+            old_password: "brand_new_pass".to_string(),
+            new_password: "third_new_pass".to_string(),
+        }),
+        expected_updated_at: Some(stale.updated_at),
+    };
+
+    match user_repo.update_user(&user_id, update).await {
+        Ok(_) => panic!("Updated user with stale updated_at"),
+        Err(data::Error::Conflict(entity)) => assert_eq!(entity, user_id.to_string()),
+        Err(err) => panic!("Get wrong error for stale update: {err}"),
+    }
+
+    match user_repo
+        .delete_user(&user_id, Some(stale.updated_at))
+        .await
+    {
+        Ok(_) => panic!("Deleted user with stale updated_at"),
+        Err(data::Error::Conflict(entity)) => assert_eq!(entity, user_id.to_string()),
+        Err(err) => panic!("Get wrong error for stale delete: {err}"),
+    }
+
+    let user = user_repo.get_user(&user_id).await.unwrap();
+
+    assert!(user_repo
+        .verify_password("brand_new_pass", &user.hash)
+        .await
+        .is_ok());
+
+    user_repo
+        .delete_user(&user_id, Some(user.updated_at))
+        .await
+        .unwrap();
+}
+
+pub async fn verify_email(user_repo: impl UserRepositoryExt) {
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let replaced = user_repo.issue_email_verification(&user_id).await.unwrap();
+    let token = user_repo.issue_email_verification(&user_id).await.unwrap();
+
+    match user_repo.verify_email(&replaced).await {
+        Ok(_) => panic!("Verified email with replaced token"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for replaced token: {err}"),
+    }
+
+    let user = user_repo.verify_email(&token).await.unwrap();
+
+    assert!(user.email_verified_at.is_some());
+
+    match user_repo.verify_email(&token).await {
+        Ok(_) => panic!("Verified email with used token"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for used token: {err}"),
+    }
+
+    match user_repo.issue_email_verification(&user_id).await {
+        Ok(_) => panic!("Issued token for verified email"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for verified email: {err}"),
+    }
+
+    // the new address is verified by confirming the change
+    let change = user_repo
+        .request_email_change(&user_id, "another@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    let changed = user_repo.confirm_email_change(&change.token).await.unwrap();
+
+    assert!(changed.user.email_verified_at > user.email_verified_at);
+}
+
+pub async fn verify_email_invalid_token(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        email_verification_ttl: Duration::zero(),
+        ..AccountSettings::default()
+    });
+
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let expired = user_repo.issue_email_verification(&user_id).await.unwrap();
+
+    let user_repo = user_repo.with_account_settings(AccountSettings::default());
+
+    let stale = user_repo.issue_email_verification(&user_id).await.unwrap();
+
+    // token was sent to the previous address
+    let change = user_repo
+        .request_email_change(&user_id, "another@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    user_repo.confirm_email_change(&change.token).await.unwrap();
+
+    for token in [&expired, &stale, &"not a token".to_string()] {
+        match user_repo.verify_email(token).await {
+            Ok(_) => panic!("Verified email with invalid token"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for invalid token: {err}"),
+        }
+    }
+
+    match user_repo.issue_email_verification(&Uuid::new_v4()).await {
+        Ok(_) => panic!("Issued token for unknown user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown user: {err}"),
+    }
+}
+
+pub async fn verify_user_password_unverified(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        require_verified_email: true,
+        ..AccountSettings::default()
+    });
+
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    // wrong password is reported first
+    match user_repo
+        .verify_user_password("test@myemail.com", "wrong_pass")
+        .await
+    {
+        Ok(_) => panic!("Verified wrong password"),
+        Err(data::Error::PasswordMismatch) => (),
+        Err(err) => panic!("Get wrong error for wrong password: {err}"),
+    }
+
+    match user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+    {
+        Ok(_) => panic!("Verified password of unverified user"),
+        Err(data::Error::EmailNotVerified) => (),
+        Err(err) => panic!("Get wrong error for unverified user: {err}"),
+    }
+
+    let token = user_repo.issue_email_verification(&user_id).await.unwrap();
+    user_repo.verify_email(&token).await.unwrap();
+
+    user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+}
+
+pub async fn reset_password(user_repo: impl UserRepositoryExt) {
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let unknown = user_repo
+        .issue_password_reset("nobody@myemail.com")
+        .await
+        .unwrap();
+
+    assert!(unknown.is_none());
+
+    let verification = user_repo.issue_email_verification(&user_id).await.unwrap();
+    let token = user_repo
+        .issue_password_reset("test@myemail.com")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let user = user_repo
+        .reset_password(&token, "my_new_password")
+        .await
+        .unwrap();
+
+    assert_eq!(user.id, user_id);
+
+    user_repo
+        .verify_user_password("test@myemail.com", "my_new_password")
+        .await
+        .unwrap();
+
+    match user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+    {
+        Ok(_) => panic!("Verified old password after reset"),
+        Err(data::Error::PasswordMismatch) => (),
+        Err(err) => panic!("Get wrong error for old password: {err}"),
+    }
+
+    match user_repo.reset_password(&token, "another_password").await {
+        Ok(_) => panic!("Reset password with used token"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for used token: {err}"),
+    }
+
+    // outstanding tokens of the user are gone
+    match user_repo.verify_email(&verification).await {
+        Ok(_) => panic!("Verified email with token issued before reset"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for dropped token: {err}"),
+    }
+}
+
+pub async fn reset_password_invalid_token(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        password_reset_ttl: Duration::zero(),
+        ..AccountSettings::default()
+    });
+
+    let expired = user_repo
+        .issue_password_reset("test@myemail.com")
+        .await
+        .unwrap()
+        .unwrap();
+
+    let user_repo = user_repo.with_account_settings(AccountSettings::default());
+
+    let replaced = user_repo
+        .issue_password_reset("test@myemail.com")
+        .await
+        .unwrap()
+        .unwrap();
+    user_repo
+        .issue_password_reset("test@myemail.com")
+        .await
+        .unwrap();
+
+    for token in [&expired, &replaced, &"not a token".to_string()] {
+        match user_repo.reset_password(token, "my_new_password").await {
+            Ok(_) => panic!("Reset password with invalid token"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for invalid token: {err}"),
+        }
+    }
+
+    user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+}
+
+pub async fn verify_user_password_lockout(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        lockout: LockoutSettings {
+            max_attempts: 2,
+            ..LockoutSettings::default()
+        },
+        ..AccountSettings::default()
+    });
+
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    for _ in 0..2 {
+        match user_repo
+            .verify_user_password("test@myemail.com", "wrong_pass")
+            .await
+        {
+            Ok(_) => panic!("Verified wrong password"),
+            Err(data::Error::PasswordMismatch) => (),
+            Err(err) => panic!("Get wrong error for wrong password: {err}"),
+        }
+    }
+
+    // locked even with the right password
+    match user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+    {
+        Ok(_) => panic!("Verified password of locked user"),
+        Err(data::Error::LockedOut(_)) => (),
+        Err(err) => panic!("Get wrong error for locked user: {err}"),
+    }
+
+    user_repo.unlock_user(&user_id).await.unwrap();
+
+    user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    // unknown emails lock the same way
+    for _ in 0..2 {
+        match user_repo
+            .verify_user_password("nobody@myemail.com", "wrong_pass")
+            .await
+        {
+            Ok(_) => panic!("Verified unknown user"),
+            Err(data::Error::PasswordMismatch) => (),
+            Err(err) => panic!("Get wrong error for unknown user: {err}"),
+        }
+    }
+
+    match user_repo
+        .verify_user_password("nobody@myemail.com", "wrong_pass")
+        .await
+    {
+        Ok(_) => panic!("Verified unknown user"),
+        Err(data::Error::LockedOut(_)) => (),
+        Err(err) => panic!("Get wrong error for locked unknown user: {err}"),
+    }
+
+    match user_repo.unlock_user(&Uuid::new_v4()).await {
+        Ok(_) => panic!("Unlocked unknown user"),
+        Err(data::Error::NotFound(_)) => (),
+        Err(err) => panic!("Get wrong error for unknown user: {err}"),
+    }
+}
+
+pub async fn verify_user_password_source_lockout(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        lockout: LockoutSettings {
+            max_source_attempts: 3,
+            ..LockoutSettings::default()
+        },
+        ..AccountSettings::default()
+    });
+
+    // spread over accounts, each one stays below its own limit
+    for email in ["a@myemail.com", "b@myemail.com", "test@myemail.com"] {
+        assert!(user_repo
+            .verify_user_password_from(email, "wrong_pass", "10.0.0.1")
+            .await
+            .is_err());
+    }
+
+    match user_repo
+        .verify_user_password_from("test@myemail.com", "dev_only_pass", "10.0.0.1")
+        .await
+    {
+        Ok(_) => panic!("Verified password from locked source"),
+        Err(data::Error::LockedOut(_)) => (),
+        Err(err) => panic!("Get wrong error for locked source: {err}"),
+    }
+
+    user_repo
+        .verify_user_password_from("test@myemail.com", "dev_only_pass", "10.0.0.2")
+        .await
+        .unwrap();
+}
+
+pub async fn verify_user_password_long_email(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        lockout: LockoutSettings {
+            max_attempts: 2,
+            max_source_attempts: 2,
+            ..LockoutSettings::default()
+        },
+        ..AccountSettings::default()
+    });
+
+    // longer than any column an email or source could be kept in
+    let email = format!("{}@myemail.com", "a".repeat(300));
+    let source = "s".repeat(300);
+
+    for _ in 0..2 {
+        match user_repo
+            .verify_user_password_from(&email, "wrong_pass", &source)
+            .await
+        {
+            Ok(_) => panic!("Verified unknown long email"),
+            Err(data::Error::PasswordMismatch) => (),
+            Err(err) => panic!("Get wrong error for unknown long email: {err}"),
+        }
+    }
+
+    match user_repo
+        .verify_user_password_from(&email, "wrong_pass", "10.0.0.1")
+        .await
+    {
+        Ok(_) => panic!("Verified locked long email"),
+        Err(data::Error::LockedOut(_)) => (),
+        Err(err) => panic!("Get wrong error for locked long email: {err}"),
+    }
+
+    match user_repo
+        .verify_user_password_from("test@myemail.com", "dev_only_pass", &source)
+        .await
+    {
+        Ok(_) => panic!("Verified password from locked long source"),
+        Err(data::Error::LockedOut(_)) => (),
+        Err(err) => panic!("Get wrong error for locked long source: {err}"),
+    }
+}
+
+/// `costly_repo` sees the users of `user_repo`, its argon2 parameters are
+/// [`costly_argon`].
+pub async fn verify_user_password_rehash(
+    user_repo: impl UserRepositoryExt,
+    costly_repo: impl UserRepositoryExt,
+) {
+    let report = user_repo.outdated_hash_report().await.unwrap();
+
+    assert_eq!(report.total, 1);
+    assert_eq!(report.outdated, 0);
+
+    let report = costly_repo.outdated_hash_report().await.unwrap();
+
+    assert_eq!(report.outdated, 1);
+
+    let before = costly_repo
+        .get_user(&Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap())
+        .await
+        .unwrap();
+
+    // wrong passwords leave the hash alone
+    assert!(costly_repo
+        .verify_user_password("test@myemail.com", "wrong_pass")
+        .await
+        .is_err());
+
+    let user = costly_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    assert_ne!(user.hash, before.hash);
+    assert!(user.hash.starts_with("$argon2id$v=19$m=32768,t=3,p=1$"));
+
+    let report = costly_repo.outdated_hash_report().await.unwrap();
+
+    assert_eq!(report.outdated, 0);
+
+    let user = costly_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    assert!(user.hash.starts_with("$argon2id$v=19$m=32768,t=3,p=1$"));
+}
+
+pub async fn verify_user_password_pepper_rotation(user_repo: impl UserRepositoryExt) {
+    let fixture_pepper = Argon2::new_with_secret(
+        b"mysecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap();
+    let keyring = PepperKeyring::new(
+        "k2",
+        b"mynewsecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap()
+    .with_unkeyed(fixture_pepper);
+    let user_repo = user_repo.with_pepper_keyring(keyring);
+
+    assert_eq!(user_repo.outdated_hash_report().await.unwrap().outdated, 1);
+
+    let user = user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    assert!(user.hash.contains(",keyid="));
+    assert_eq!(user_repo.outdated_hash_report().await.unwrap().outdated, 0);
+
+    // the old pepper can go once every hash was rotated
+    let keyring = PepperKeyring::new(
+        "k3",
+        b"mythirdsecret",
+        Algorithm::Argon2id,
+        Version::V0x13,
+        Params::default(),
+    )
+    .unwrap();
+    let user_repo = user_repo.with_pepper_keyring(keyring.clone());
+
+    match user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+    {
+        Ok(_) => panic!("Verified hash of unknown pepper"),
+        Err(data::Error::Hash) => (),
+        Err(err) => panic!("Get wrong error for unknown pepper: {err}"),
+    }
+
+    let user_repo =
+        user_repo.with_pepper_keyring(keyring.with_retired("k2", b"mynewsecret").unwrap());
+
+    let rotated = user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    assert_ne!(rotated.hash, user.hash);
+    assert_eq!(user_repo.outdated_hash_report().await.unwrap().outdated, 0);
+}
+
+pub async fn import_users(user_repo: impl UserRepositoryExt) {
+    let emails = [
+        "bcrypt@example.com",
+        "pbkdf2@example.com",
+        "scrypt@example.com",
+    ];
+    let mut users: Vec<ImportUser> = emails
+        .iter()
+        .zip(legacy_hashes("legacy_pass"))
+        .map(|(email, hash)| import_user(email, hash))
+        .collect();
+    users[0].created_at = Some("2020-01-01T00:00:00Z".parse().unwrap());
+
+    let imported = user_repo.import_users(users).await.unwrap();
+
+    assert_eq!(imported.len(), 3);
+    assert_eq!(
+        imported[0].created_at,
+        "2020-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+    );
+
+    let report = user_repo.outdated_hash_report().await.unwrap();
+
+    assert_eq!(report.total, 4);
+    assert_eq!(report.outdated, 3);
+
+    for email in emails {
+        match user_repo.verify_user_password(email, "wrong_pass").await {
+            Ok(_) => panic!("Verified wrong password of {email}"),
+            Err(data::Error::PasswordMismatch) => (),
+            Err(err) => panic!("Get wrong error for wrong password of {email}: {err}"),
+        }
+
+        let user = user_repo
+            .verify_user_password(email, "legacy_pass")
+            .await
+            .unwrap();
+
+        assert!(user.hash.starts_with("$argon2id$"));
+    }
+
+    let report = user_repo.outdated_hash_report().await.unwrap();
+
+    assert_eq!(report.outdated, 0);
+}
+
+pub async fn import_users_roles(user_repo: impl UserRepositoryExt) {
+    let hash = legacy_hashes("legacy_pass").remove(0);
+
+    let mut moderator = import_user("moderator@example.com", hash.clone());
+    moderator.roles = vec!["moderator".to_string(), "support".to_string()];
+
+    user_repo
+        .import_users(vec![
+            moderator,
+            import_user("plain@example.com", hash.clone()),
+        ])
+        .await
+        .unwrap();
+
+    for role in ["moderator", "support"] {
+        let emails = list_emails(
+            &user_repo,
+            ListUsers {
+                filter: UserFilter {
+                    role: Some(role.to_string()),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )
+        .await;
+
+        assert_eq!(emails, vec!["moderator@example.com"]);
+    }
+
+    let mut superuser = import_user("superuser@example.com", hash.clone());
+    superuser.roles = vec!["superuser".to_string()];
+
+    match user_repo
+        .import_users(vec![import_user("new@example.com", hash), superuser])
+        .await
+    {
+        Ok(_) => panic!("Imported unknown role"),
+        Err(data::Error::NotFound(role)) => assert_eq!(&role, "superuser"),
+        Err(err) => panic!("Get wrong error for unknown role: {err}"),
+    }
+
+    // nothing of a failed import is kept
+    let users = user_repo.list_users(ListUsers::default()).await.unwrap();
+
+    assert_eq!(users.items.len(), 3);
+}
+
+pub async fn import_users_invalid(user_repo: impl UserRepositoryExt) {
+    let hash = legacy_hashes("legacy_pass").remove(0);
+
+    let users = vec![
+        import_user("new@example.com", hash.clone()),
+        import_user(
+            "md5@example.com",
+            "5f4dcc3b5aa765d61d8327deb882cf99".to_string(),
+        ),
+    ];
+
+    match user_repo.import_users(users).await {
+        Ok(_) => panic!("Imported unsupported hash"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for unsupported hash: {err}"),
+    }
+
+    let users = vec![
+        import_user("new@example.com", hash.clone()),
+        import_user("test@myemail.com", hash),
+    ];
+
+    match user_repo.import_users(users).await {
+        Ok(_) => panic!("Imported duplicate email"),
+        Err(data::Error::AlreadyExists(email)) => assert_eq!(&email, "test@myemail.com"),
+        Err(err) => panic!("Get wrong error for duplicate email: {err}"),
+    }
+
+    // nothing of a failed import is kept
+    let users = user_repo.list_users(ListUsers::default()).await.unwrap();
+
+    assert_eq!(users.items.len(), 1);
+}
+
+pub async fn create_user_weak_password(user_repo: impl UserRepositoryExt) {
+    for (password, violation) in [
+        ("", PasswordViolation::TooShort { min: 8 }),
+        ("test2@myemail.com", PasswordViolation::ContainsEmail),
+        ("TEST2", PasswordViolation::ContainsEmail),
+    ] {
+        match user_repo.create_user("test2@myemail.com", password).await {
+            Ok(_) => panic!("Created user with weak password {password}"),
+            Err(data::Error::WeakPassword(violations)) => assert!(violations.contains(&violation)),
+            Err(err) => panic!("Get wrong error for weak password: {err}"),
+        }
+    }
+
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        password_policy: PasswordPolicy {
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: true,
+            breached: Some(Arc::new(BreachedPasswords::from_passwords([
+                "password",
+                "Passw0rd!",
+            ]))),
+            ..PasswordPolicy::default()
+        },
+        ..AccountSettings::default()
+    });
+
+    match user_repo
+        .create_user("test2@myemail.com", "my_test_password")
+        .await
+    {
+        Ok(_) => panic!("Created user with weak password"),
+        Err(data::Error::WeakPassword(violations)) => assert_eq!(
+            violations,
+            vec![
+                PasswordViolation::MissingUppercase,
+                PasswordViolation::MissingDigit
+            ]
+        ),
+        Err(err) => panic!("Get wrong error for weak password: {err}"),
+    }
+
+    match user_repo
+        .create_user("test2@myemail.com", "Passw0rd!")
+        .await
+    {
+        Ok(_) => panic!("Created user with breached password"),
+        Err(data::Error::WeakPassword(violations)) => {
+            assert_eq!(violations, vec![PasswordViolation::Breached])
+        }
+        Err(err) => panic!("Get wrong error for breached password: {err}"),
+    }
+
+    user_repo
+        .create_user("test2@myemail.com", "My_test_passw0rd")
+        .await
+        .unwrap();
+}
+
+pub async fn update_user_weak_password(user_repo: impl UserRepositoryExt) {
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let update = UpdateUser {
+        password: Some(PasswordUpdate {
+            old_password: "dev_only_pass".to_string(),
+            new_password: "test".to_string(),
+        }),
+        expected_updated_at: None,
+    };
+
+    match user_repo.update_user(&user_id, update).await {
+        Ok(_) => panic!("Updated user with weak password"),
+        Err(data::Error::WeakPassword(violations)) => assert_eq!(
+            violations,
+            vec![
+                PasswordViolation::TooShort { min: 8 },
+                PasswordViolation::ContainsEmail
+            ]
+        ),
+        Err(err) => panic!("Get wrong error for weak password: {err}"),
+    }
+
+    match user_repo.reset_user_password(&user_id, "short").await {
+        Ok(_) => panic!("Reset user password to weak password"),
+        Err(data::Error::WeakPassword(_)) => (),
+        Err(err) => panic!("Get wrong error for weak password: {err}"),
+    }
+
+    let user = user_repo.get_user(&user_id).await.unwrap();
+
+    assert_eq!(&user.email, "test@myemail.com");
+
+    user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .unwrap();
+}
+
+pub async fn verify_and_hash(user_repo: impl UserRepositoryExt) {
+    let user_pass = "some_test_pass";
+    let hash = user_repo.hash_password(user_pass).await.unwrap();
+
+    let verify = user_repo.verify_password(user_pass, &hash).await;
+
+    assert!(verify.is_ok());
+}
+
+pub async fn hash_password(user_repo: impl UserRepositoryExt) {
+    user_repo.hash_password("dev_only_pass").await.unwrap();
+}
+
+pub async fn verify_user_password(user_repo: impl UserRepositoryExt) {
+    let user_pass = "dev_only_pass";
+    let user_email = "test@myemail.com";
+    let verify = user_repo.verify_user_password(user_email, user_pass).await;
+
+    assert!(verify.is_ok());
+
+    let verify = user_repo
+        .verify_user_password(user_email, "wrong_pass")
+        .await;
+
+    assert!(verify.is_err());
+}
+
+pub async fn verify_user_password_unknown_email(user_repo: impl UserRepositoryExt) {
+    let wrong_password = user_repo
+        .verify_user_password("test@myemail.com", "wrong_pass")
+        .await;
+    let unknown_email = user_repo
+        .verify_user_password("nobody@myemail.com", "wrong_pass")
+        .await;
+
+    match (wrong_password, unknown_email) {
+        (Err(data::Error::PasswordMismatch), Err(data::Error::PasswordMismatch)) => (),
+        (wrong_password, unknown_email) => panic!(
+            "Get different answers for wrong password and unknown email: {:?} / {:?}",
+            wrong_password.map(|user| user.id),
+            unknown_email.map(|user| user.id)
+        ),
+    }
+}
+
+pub async fn verify_user_password_email_case(user_repo: impl UserRepositoryExt) {
+    let user = user_repo
+        .verify_user_password(" TEST@MyEmail.com", "dev_only_pass")
+        .await
+        .unwrap();
+
+    assert_eq!(user.email, "test@myemail.com");
+
+    assert!(user_repo
+        .issue_password_reset("Test@MYEMAIL.com")
+        .await
+        .unwrap()
+        .is_some());
+}
+
+pub async fn totp_login(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(totp_settings(1));
+
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let replaced = user_repo.enroll_totp(&user_id).await.unwrap();
+    let enrollment = user_repo.enroll_totp(&user_id).await.unwrap();
+
+    assert_ne!(replaced.secret, enrollment.secret);
+    assert!(enrollment
+        .otpauth_uri
+        .starts_with("otpauth://totp/slowpocket:test%40myemail.com?secret="));
+
+    // unconfirmed enrollments don't count yet
+    assert!(user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .is_ok());
+
+    for code in ["abcdef", &totp_code(&replaced.secret, 0)] {
+        match user_repo.confirm_totp(&user_id, code).await {
+            Ok(_) => panic!("Confirmed TOTP with wrong code"),
+            Err(data::Error::InvalidCode) => (),
+            Err(err) => panic!("Get wrong error for wrong code: {err}"),
+        }
+    }
+
+    let code = totp_code(&enrollment.secret, 0);
+    let recovery_codes = user_repo.confirm_totp(&user_id, &code).await.unwrap();
+
+    assert_eq!(recovery_codes.len(), 10);
+    // 5 bits a letter, at least 128 bits a code
+    assert!(recovery_codes.iter().all(|code| code
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .count()
+        * 5
+        >= 128));
+
+    let challenge = login_challenge(&user_repo).await;
+
+    // the confirming code was used already
+    match user_repo.verify_totp(&challenge, &code).await {
+        Ok(_) => panic!("Accepted replayed code"),
+        Err(data::Error::InvalidCode) => (),
+        Err(err) => panic!("Get wrong error for replayed code: {err}"),
+    }
+
+    let next = totp_code(&enrollment.secret, 1);
+    let user = user_repo.verify_totp(&challenge, &next).await.unwrap();
+
+    assert_eq!(user.id, user_id);
+
+    let challenge = login_challenge(&user_repo).await;
+
+    match user_repo.verify_totp(&challenge, &next).await {
+        Ok(_) => panic!("Accepted replayed code"),
+        Err(data::Error::InvalidCode) => (),
+        Err(err) => panic!("Get wrong error for replayed code: {err}"),
+    }
+
+    let recovery_code = recovery_codes[0].to_uppercase();
+
+    user_repo
+        .verify_recovery_code(&challenge, &recovery_code)
+        .await
+        .unwrap();
+
+    let challenge = login_challenge(&user_repo).await;
+
+    match user_repo
+        .verify_recovery_code(&challenge, &recovery_code)
+        .await
+    {
+        Ok(_) => panic!("Accepted used recovery code"),
+        Err(data::Error::InvalidCode) => (),
+        Err(err) => panic!("Get wrong error for used recovery code: {err}"),
+    }
+
+    match user_repo.enroll_totp(&user_id).await {
+        Ok(_) => panic!("Enrolled TOTP twice"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for enabled TOTP: {err}"),
+    }
+
+    match user_repo.disable_totp(&user_id, "not_my_pass").await {
+        Ok(_) => panic!("Disabled TOTP with wrong password"),
+        Err(data::Error::PasswordMismatch) => (),
+        Err(err) => panic!("Get wrong error for wrong password: {err}"),
+    }
+
+    user_repo
+        .disable_totp(&user_id, "dev_only_pass")
+        .await
+        .unwrap();
+
+    assert!(user_repo
+        .verify_user_password("test@myemail.com", "dev_only_pass")
+        .await
+        .is_ok());
+
+    match user_repo
+        .verify_recovery_code(&challenge, &recovery_codes[1])
+        .await
+    {
+        Ok(_) => panic!("Accepted recovery code of disabled TOTP"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for disabled TOTP: {err}"),
+    }
+}
+
+pub async fn totp_enroll_email_with_colon(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(totp_settings(1));
+
+    let user = user_repo
+        .create_user("first:last@myemail.com", "my_test_password")
+        .await
+        .unwrap();
+
+    let enrollment = user_repo.enroll_totp(&user.id).await.unwrap();
+
+    assert!(enrollment
+        .otpauth_uri
+        .starts_with("otpauth://totp/slowpocket:first%3Alast%40myemail.com?secret="));
+
+    user_repo
+        .confirm_totp(&user.id, &totp_code(&enrollment.secret, 0))
+        .await
+        .unwrap();
+}
+
+pub async fn totp_lockout(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        lockout: LockoutSettings {
+            max_attempts: 2,
+            ..LockoutSettings::default()
+        },
+        ..totp_settings(1)
+    });
+
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let enrollment = user_repo.enroll_totp(&user_id).await.unwrap();
+    user_repo
+        .confirm_totp(&user_id, &totp_code(&enrollment.secret, 0))
+        .await
+        .unwrap();
+
+    // passing the password doesn't clear failed second factors
+    let mut challenge = String::new();
+    for _ in 0..2 {
+        challenge = login_challenge(&user_repo).await;
+
+        match user_repo
+            .verify_recovery_code(&challenge, "wrong-code")
+            .await
+        {
+            Ok(_) => panic!("Accepted wrong recovery code"),
+            Err(data::Error::InvalidCode) => (),
+            Err(err) => panic!("Get wrong error for wrong code: {err}"),
+        }
+    }
+
+    match user_repo
+        .verify_totp(&challenge, &totp_code(&enrollment.secret, 1))
+        .await
+    {
+        Ok(_) => panic!("Accepted code of locked account"),
+        Err(data::Error::LockedOut(_)) => (),
+        Err(err) => panic!("Get wrong error for locked account: {err}"),
+    }
+
+    assert!(matches!(
+        user_repo
+            .verify_user_password("test@myemail.com", "dev_only_pass")
+            .await,
+        Err(data::Error::LockedOut(_))
+    ));
+
+    user_repo.unlock_user(&user_id).await.unwrap();
+
+    // secrets can't be read with another key
+    let user_repo = user_repo.with_account_settings(totp_settings(2));
+
+    match user_repo
+        .verify_totp(&challenge, &totp_code(&enrollment.secret, 1))
+        .await
+    {
+        Ok(_) => panic!("Accepted code with wrong key"),
+        Err(data::Error::DataIntegrity(_)) => (),
+        Err(err) => panic!("Get wrong error for wrong key: {err}"),
+    }
+
+    let user_repo = user_repo.with_account_settings(AccountSettings::default());
+
+    match user_repo.enroll_totp(&user_id).await {
+        Ok(_) => panic!("Enrolled TOTP without settings"),
+        Err(data::Error::InvalidArgument(_)) => (),
+        Err(err) => panic!("Get wrong error for missing settings: {err}"),
+    }
+}
+
+pub async fn totp_challenge(user_repo: impl UserRepositoryExt) {
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        max_second_factor_attempts: 2,
+        ..totp_settings(1)
+    });
+
+    let user_id = Uuid::parse_str("a74f9b43-8a49-4d97-8270-9879d37c600d").unwrap();
+
+    let enrollment = user_repo.enroll_totp(&user_id).await.unwrap();
+    let recovery_codes = user_repo
+        .confirm_totp(&user_id, &totp_code(&enrollment.secret, 0))
+        .await
+        .unwrap();
+
+    // the second factor alone doesn't log in
+    for challenge in [user_id.to_string(), "garbage".to_string()] {
+        match user_repo
+            .verify_totp(&challenge, &totp_code(&enrollment.secret, 1))
+            .await
+        {
+            Ok(_) => panic!("Accepted code without password"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for missing challenge: {err}"),
+        }
+
+        match user_repo
+            .verify_recovery_code(&challenge, &recovery_codes[0])
+            .await
+        {
+            Ok(_) => panic!("Accepted recovery code without password"),
+            Err(data::Error::InvalidToken) => (),
+            Err(err) => panic!("Get wrong error for missing challenge: {err}"),
+        }
+    }
+
+    let challenge = login_challenge(&user_repo).await;
+
+    user_repo
+        .verify_totp(&challenge, &totp_code(&enrollment.secret, 1))
+        .await
+        .unwrap();
+
+    match user_repo
+        .verify_recovery_code(&challenge, &recovery_codes[0])
+        .await
+    {
+        Ok(_) => panic!("Accepted used challenge"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for used challenge: {err}"),
+    }
+
+    // every wrong code counts, the challenge is dropped after the last one
+    let challenge = login_challenge(&user_repo).await;
+
+    for _ in 0..2 {
+        match user_repo
+            .verify_recovery_code(&challenge, "wrong-code")
+            .await
+        {
+            Ok(_) => panic!("Accepted wrong recovery code"),
+            Err(data::Error::InvalidCode) => (),
+            Err(err) => panic!("Get wrong error for wrong code: {err}"),
+        }
+    }
+
+    match user_repo
+        .verify_recovery_code(&challenge, &recovery_codes[0])
+        .await
+    {
+        Ok(_) => panic!("Accepted challenge after too many wrong codes"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for dropped challenge: {err}"),
+    }
+
+    let user_repo = user_repo.with_account_settings(AccountSettings {
+        second_factor_ttl: Duration::zero(),
+        ..totp_settings(1)
+    });
+
+    let challenge = login_challenge(&user_repo).await;
+
+    match user_repo
+        .verify_recovery_code(&challenge, &recovery_codes[0])
+        .await
+    {
+        Ok(_) => panic!("Accepted expired challenge"),
+        Err(data::Error::InvalidToken) => (),
+        Err(err) => panic!("Get wrong error for expired challenge: {err}"),
+    }
+}
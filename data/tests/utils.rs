@@ -1,6 +1,7 @@
+use data::settings::PostgresSettings;
 use sqlx::{
     postgres::{PgConnectOptions, PgPoolOptions},
-    PgPool,
+    ConnectOptions, PgPool,
 };
 
 pub async fn connect(
@@ -15,3 +16,16 @@ pub async fn connect(
         .connect_with(connect_options.username(&username).password(&password))
         .await
 }
+
+/// Settings pointing at the database of a `#[sqlx::test]`, for the `from_settings` tests.
+pub fn postgres_settings(connect_options: PgConnectOptions) -> PostgresSettings {
+    let connect_options = connect_options
+        .username("postgres")
+        .password("dev_only_pwd");
+
+    PostgresSettings {
+        url: connect_options.to_url_lossy(),
+        min_connections: None,
+        max_connections: Some(1),
+    }
+}